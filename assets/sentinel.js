@@ -12,6 +12,29 @@
  *   native_registry  – full sysdata + appdata snapshot (periodic, ~100ms)
  *   native_pause     – wallpaper paused/resumed
  *   native_css_vars  – live CSS variable updates from manifest editable changes
+ *   native_editable_schema – the manifest's full editable tree; sent after
+ *                      each page load and whenever it changes
+ *   native_mouse     – cursor position / buttons when over this monitor
+ *   native_scroll    – mouse-wheel delta when over this monitor (opt-in)
+ *   native_key       – key down/up while the desktop is focused (opt-in)
+ *   native_audio     – loopback RMS + coarse spectrum bands (0–1)
+ *   native_monitor_info – DPI scale, refresh rate, primary flag, device name
+ *   native_fps_limit – frame-rate cap hint (fps, 0 = unlimited); sent on
+ *                      battery/AC transitions and after each page load
+ *   native_volume    – section volume (0–1); applied to every <audio> /
+ *                      <video> unless Sentinel.autoVolume is false
+ *
+ * Messages this SDK posts back:
+ *   sentinel_demands – data sections the page subscribes to
+ *   snapshot_ready   – optional; see Sentinel.snapshotReady().  Once a page
+ *                      has sent it, background snapshots wait for a fresh
+ *                      signal (within ~2s) after each interval, falling
+ *                      back to twice the interval if none arrives.  Pages
+ *                      that never send it are captured on the interval.
+ *   heartbeat        – see Sentinel.heartbeat(); only watched for assets
+ *                      whose manifest sets "heartbeat": true
+ *   request_reload   – see Sentinel.requestReload()
+ *   set_editable     – see Sentinel.setEditable()
  *
  * Registry format (v2):
  *   sysdata: {
@@ -79,6 +102,12 @@
   let _appdata = null;
   let _paused = false;
   let _monitorBounds = null;
+  let _monitorInfo = null;
+  let _fpsLimit = 0;
+  let _volume = 1;
+  let _editableSchema = null;
+  let _mouse = null;
+  let _audio = null;
   const _lastDemandSig = { value: '' };
 
   const SYS_SECTION_KEYS = {
@@ -188,6 +217,14 @@
         }
         break;
 
+      /* ─── Full manifest editable tree (for in-page settings UI) ─── */
+      case 'native_editable_schema':
+        if (d.editable && typeof d.editable === 'object') {
+          _editableSchema = d.editable;
+          emit('editableschema', _editableSchema);
+        }
+        break;
+
       /* ─── Per-monitor bounds (for local cursor mapping) ─── */
       case 'native_monitor_bounds':
         _monitorBounds = {
@@ -198,9 +235,80 @@
         };
         emit('monitorbounds', _monitorBounds);
         break;
+
+      /* ─── Static monitor details (sent after every page load) ─── */
+      case 'native_monitor_info':
+        _monitorInfo = {
+          index: Number(d.index) || 0,
+          deviceName: String(d.device_name || ''),
+          primary: !!d.primary,
+          dpiScale: Number(d.dpi_scale) || 1,
+          refreshRate: d.refresh_rate == null ? null : Number(d.refresh_rate),
+        };
+        emit('monitorinfo', _monitorInfo);
+        break;
+
+      /* ─── Frame-rate cap hint (battery power) ─── */
+      case 'native_fps_limit':
+        _fpsLimit = Math.max(0, Number(d.fps) || 0);
+        emit('fpslimit', { fps: _fpsLimit });
+        break;
+
+      /* ─── Section volume ─── */
+      case 'native_volume':
+        _volume = Math.min(1, Math.max(0, Number(d.volume)));
+        if (isNaN(_volume)) _volume = 1;
+        applyVolume();
+        emit('volume', { volume: _volume });
+        break;
+
+      /* ─── Native cursor (local px, normalized, DOM-style buttons mask) ─── */
+      case 'native_mouse':
+        _mouse = {
+          x: Number(d.x) || 0,
+          y: Number(d.y) || 0,
+          nx: Number(d.nx) || 0,
+          ny: Number(d.ny) || 0,
+          buttons: Number(d.buttons) || 0,
+        };
+        emit('mouse', _mouse);
+        break;
+
+      /* ─── Native key transitions (desktop focus only, opt-in) ─── */
+      case 'native_key':
+        emit('key', {
+          key: String(d.key || ''),
+          vk: Number(d.vk) || 0,
+          down: !!d.down,
+        });
+        break;
+
+      /* ─── Native mouse wheel (120 per notch, + = up / right) ─── */
+      case 'native_scroll':
+        emit('scroll', {
+          delta: Number(d.delta) || 0,
+          deltaX: Number(d.delta_x) || 0,
+        });
+        break;
+
+      /* ─── Native audio loopback (RMS + low→high spectrum bands) ─── */
+      case 'native_audio':
+        _audio = {
+          rms: Number(d.rms) || 0,
+          bands: Array.isArray(d.bands) ? d.bands.map(function (b) { return Number(b) || 0; }) : [],
+        };
+        emit('audio', _audio);
+        break;
     }
   }
 
+  /* ─── Apply the section volume to the page's media elements ─── */
+  function applyVolume() {
+    if (Sentinel.autoVolume === false || typeof document === 'undefined') return;
+    var media = document.querySelectorAll('audio, video');
+    for (var i = 0; i < media.length; i++) media[i].volume = _volume;
+  }
+
   /* ─── Dispatch per-category sysdata subscriptions ─── */
   function dispatchSysdata(sys) {
     // Dynamically iterate all keys present in the sysdata object
@@ -331,6 +439,54 @@
      */
     get monitorBounds() { return _monitorBounds; },
 
+    /**
+     * Details of the monitor this WebView is hosted on.
+     * { index, deviceName, primary, dpiScale, refreshRate } (refreshRate in Hz or null), or null.
+     * @returns {object|null}
+     */
+    get monitorInfo() { return _monitorInfo; },
+
+    /**
+     * Frame-rate cap the addon asks animations to respect (e.g. on battery).
+     * 0 means unlimited. Cooperating wallpapers should skip
+     * requestAnimationFrame ticks to stay at or below this rate.
+     * @returns {number}
+     */
+    get fpsLimit() { return _fpsLimit; },
+
+    /**
+     * Section volume (0–1) last sent by the addon.  Unless autoVolume is
+     * set to false, the SDK applies it to every <audio> / <video> element,
+     * including ones that start playing later.
+     * @returns {number}
+     */
+    get volume() { return _volume; },
+
+    /** Set to false to manage media volume yourself (see volume). */
+    autoVolume: true,
+
+    /**
+     * The manifest's `editable` tree as last sent by the addon, or null.
+     * Entries carry { variable, value, selector, ... }; groups nest them.
+     * Listen for 'editableschema' to rebuild controls when it changes.
+     * @returns {object|null}
+     */
+    get editableSchema() { return _editableSchema; },
+
+    /**
+     * Latest native cursor sample for this monitor.
+     * { x, y, nx, ny, buttons } (buttons: 1 = left, 2 = right, 4 = middle), or null.
+     * @returns {object|null}
+     */
+    get mouse() { return _mouse; },
+
+    /**
+     * Latest native audio frame from system output loopback.
+     * { rms, bands } with values in 0–1 (bands ordered low → high), or null.
+     * @returns {object|null}
+     */
+    get audio() { return _audio; },
+
     /**
      * Flat array of display metadata objects (unwrapped from registry entries).
      * Each element is the raw metadata: { id, primary, x, y, width, height, scale, ... }
@@ -454,6 +610,14 @@
      *   pausechange – { paused: bool }
      *   registry    – { sysdata, appdata }  (raw, every update)
      *   cssvarchange – { varName: value, ... }
+     *   editableschema – manifest `editable` tree (see editableSchema)
+     *   mouse       – { x, y, nx, ny, buttons }
+     *   scroll      – { delta, deltaX }  (120 per notch)
+     *   key         – { key, vk, down }
+     *   audio       – { rms, bands }
+     *   monitorinfo – { index, deviceName, primary, dpiScale, refreshRate }
+     *   fpslimit    – { fps }  (0 = unlimited)
+     *   volume      – { volume }  (0–1)
      *
      * @param {string}   event    - Event name
      * @param {function} callback - function(data)
@@ -496,6 +660,59 @@
       publishDemandSections();
     },
 
+    /**
+     * Tell the addon this is a good moment to capture the background
+     * snapshot, e.g. right after a transition settles.  Optional: call it
+     * regularly or not at all, since the first call opts the page in.
+     */
+    snapshotReady() {
+      if (!(root.chrome && root.chrome.webview && typeof root.chrome.webview.postMessage === 'function')) return;
+      try {
+        root.chrome.webview.postMessage({ type: 'snapshot_ready' });
+      } catch (_) {}
+    },
+
+    /**
+     * Report that the page is alive.  Assets whose manifest sets
+     * "heartbeat": true must call this regularly (e.g. once a second from
+     * the render loop); a page silent for `heartbeat_timeout_ms` while
+     * unpaused is logged as hung and reloaded.
+     */
+    heartbeat() {
+      if (!(root.chrome && root.chrome.webview && typeof root.chrome.webview.postMessage === 'function')) return;
+      try {
+        root.chrome.webview.postMessage({ type: 'heartbeat' });
+      } catch (_) {}
+    },
+
+    /**
+     * Ask the addon to reload this page from disk (cache bypassed), e.g.
+     * after an unrecoverable error.  Ignored during the first 5 seconds
+     * after a load so a page cannot reload itself in a loop.
+     */
+    requestReload() {
+      if (!(root.chrome && root.chrome.webview && typeof root.chrome.webview.postMessage === 'function')) return;
+      try {
+        root.chrome.webview.postMessage({ type: 'request_reload' });
+      } catch (_) {}
+    },
+
+    /**
+     * Save an editable value back to this asset's manifest.json, e.g. from
+     * an in-page settings panel built on Sentinel.editableSchema.  The
+     * addon then pushes the new value to every monitor showing the asset
+     * (as 'cssvarchange' / 'editableschema'), and the options editor sees
+     * it too.  `variable` must be declared in the manifest's editable tree.
+     * @param {string} variable - CSS variable name, e.g. '--accent-color'
+     * @param {string|number|boolean} value
+     */
+    setEditable(variable, value) {
+      if (!(root.chrome && root.chrome.webview && typeof root.chrome.webview.postMessage === 'function')) return;
+      try {
+        root.chrome.webview.postMessage({ type: 'set_editable', variable: variable, value: value });
+      } catch (_) {}
+    },
+
     /* ─── Utility helpers ─── */
 
     /**
@@ -557,6 +774,15 @@
     });
   }
 
+  // Media started after the last native_volume picks it up when it plays.
+  if (typeof document !== 'undefined') {
+    document.addEventListener('play', function (e) {
+      if (Sentinel.autoVolume !== false && e.target && 'volume' in e.target) {
+        e.target.volume = _volume;
+      }
+    }, true);
+  }
+
   // Also support manual dispatch for testing / other environments
   Sentinel._handleMessage = handleMessage;
 
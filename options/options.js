@@ -334,12 +334,26 @@ function sendIPC(payload) {
 }
 
 function postConfigUpdate(path, value) {
-  return sendIPC({
+  const sent = sendIPC({
     type: 'config_update',
     addonId: addonId,
     path: path,
     value: value
   });
+  // The host handles messages in order, so the write lands before the
+  // reload; the addon's mtime watcher remains a backstop.
+  if (sent) postAddonCommand('reload_config');
+  return sent;
+}
+
+/* Queue a command for the addon process (see README "Addon Commands"). */
+function postAddonCommand(cmd, args) {
+  return sendIPC({
+    type: 'addon_command',
+    addonId: addonId,
+    cmd: cmd,
+    args: args || {}
+  });
 }
 
 function postWallpaperPropertyUpdate(property, value) {
@@ -0,0 +1,466 @@
+// ~/Sentinel/sentinel-addons/wallpaper/src/audio.rs
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use windows::Win32::{
+    Media::Audio::{
+        eConsole, eRender, IAudioCaptureClient, IAudioClient, IMMDevice, IMMDeviceEnumerator,
+        MMDeviceEnumerator, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED,
+        AUDCLNT_STREAMFLAGS_LOOPBACK, WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+    },
+    System::Com::{
+        CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, CLSCTX_ALL,
+        COINIT_MULTITHREADED,
+    },
+};
+
+use crate::{data_loaders::config::AudioSettings, info, warn, DEBUG_NAME};
+
+/// Number of mono samples analysed per frame (power of two for the FFT).
+const WINDOW_SIZE: usize = 512;
+/// Number of log-spaced spectrum bands reported to wallpapers.
+const BAND_COUNT: usize = 8;
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioFrame {
+    pub rms: f32,
+    pub bands: Vec<f32>,
+}
+
+impl AudioFrame {
+    /// Round every value to `decimals` places so tiny jitter doesn't count as change.
+    pub fn quantized(&self, decimals: u8) -> Self {
+        let factor = 10f32.powi(decimals as i32);
+        let q = |v: f32| (v * factor).round() / factor;
+        Self {
+            rms: q(self.rms),
+            bands: self.bands.iter().map(|b| q(*b)).collect(),
+        }
+    }
+
+    /// Largest absolute difference across rms and all bands.
+    pub fn max_delta(&self, other: &AudioFrame) -> f32 {
+        let band_delta = self
+            .bands
+            .iter()
+            .zip(other.bands.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        let len_changed = if self.bands.len() != other.bands.len() { 1.0 } else { 0.0 };
+        (self.rms - other.rms).abs().max(band_delta).max(len_changed)
+    }
+}
+
+struct AudioShared {
+    active: AtomicBool,
+    shutdown: AtomicBool,
+    settings: Mutex<AudioSettings>,
+    latest: Mutex<Option<AudioFrame>>,
+}
+
+/// WASAPI loopback capture running on its own MTA thread.  The tick loop
+/// reads the latest analysed frame; capture is torn down whenever the
+/// monitor is inactive (all hosts paused) so no audio work happens then.
+pub struct AudioMonitor {
+    shared: Arc<AudioShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AudioMonitor {
+    pub fn start(settings: &AudioSettings) -> Option<Self> {
+        let shared = Arc::new(AudioShared {
+            active: AtomicBool::new(false),
+            shutdown: AtomicBool::new(false),
+            settings: Mutex::new(settings.clone()),
+            latest: Mutex::new(None),
+        });
+
+        let worker_shared = Arc::clone(&shared);
+        let handle = thread::Builder::new()
+            .name("audio-loopback".into())
+            .spawn(move || audio_worker(worker_shared))
+            .map_err(|e| warn!("[{}][AUDIO] Failed to spawn capture thread: {e}", DEBUG_NAME))
+            .ok()?;
+
+        Some(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn update_settings(&self, settings: &AudioSettings) {
+        if let Ok(mut current) = self.shared.settings.lock() {
+            *current = settings.clone();
+        }
+    }
+
+    pub fn set_active(&self, active: bool) {
+        let was_active = self.shared.active.swap(active, Ordering::Relaxed);
+        if was_active && !active {
+            if let Ok(mut latest) = self.shared.latest.lock() {
+                *latest = None;
+            }
+        }
+    }
+
+    pub fn latest(&self) -> Option<AudioFrame> {
+        self.shared.latest.lock().ok()?.clone()
+    }
+}
+
+impl Drop for AudioMonitor {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct LoopbackSession {
+    client: IAudioClient,
+    capture: IAudioCaptureClient,
+    device_id: String,
+    channels: usize,
+    sample_format: SampleFormat,
+}
+
+#[derive(Clone, Copy)]
+enum SampleFormat {
+    F32,
+    I16,
+}
+
+fn audio_worker(shared: Arc<AudioShared>) {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+    }
+
+    while !shared.shutdown.load(Ordering::Relaxed) {
+        if !shared.active.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let settings = current_settings(&shared);
+        match open_loopback_session() {
+            Ok(session) => {
+                info!(
+                    "[{}][AUDIO] Loopback capture started on endpoint {}",
+                    DEBUG_NAME, session.device_id
+                );
+                if let Err(e) = run_session(&shared, &session) {
+                    warn!("[{}][AUDIO] Capture failed: {}", DEBUG_NAME, e);
+                    sleep_unless_shutdown(&shared, Duration::from_millis(settings.retry_interval_ms));
+                }
+                unsafe {
+                    let _ = session.client.Stop();
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "[{}][AUDIO] Failed to open loopback endpoint ({}); retrying in {}ms",
+                    DEBUG_NAME, e, settings.retry_interval_ms
+                );
+                sleep_unless_shutdown(&shared, Duration::from_millis(settings.retry_interval_ms));
+            }
+        }
+    }
+
+    unsafe {
+        CoUninitialize();
+    }
+}
+
+fn current_settings(shared: &AudioShared) -> AudioSettings {
+    shared
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .unwrap_or_default()
+}
+
+fn sleep_unless_shutdown(shared: &AudioShared, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline && !shared.shutdown.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Capture until the monitor goes inactive, shuts down, or the default
+/// render endpoint changes (checked every `endpoint_refresh_ms`).
+fn run_session(shared: &AudioShared, session: &LoopbackSession) -> std::result::Result<(), String> {
+    unsafe {
+        session
+            .client
+            .Start()
+            .map_err(|e| format!("IAudioClient::Start failed: {e:?}"))?;
+    }
+
+    let mut samples: VecDeque<f32> = VecDeque::with_capacity(WINDOW_SIZE * 2);
+    let mut last_endpoint_check = Instant::now();
+    let mut last_analysis = Instant::now();
+
+    loop {
+        if shared.shutdown.load(Ordering::Relaxed) || !shared.active.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let settings = current_settings(shared);
+
+        if last_endpoint_check.elapsed() >= Duration::from_millis(settings.endpoint_refresh_ms) {
+            last_endpoint_check = Instant::now();
+            if default_endpoint_id().ok().as_deref() != Some(session.device_id.as_str()) {
+                info!("[{}][AUDIO] Default render endpoint changed; reopening", DEBUG_NAME);
+                return Ok(());
+            }
+        }
+
+        read_available_packets(session, &mut samples)?;
+        while samples.len() > WINDOW_SIZE {
+            samples.pop_front();
+        }
+
+        if last_analysis.elapsed() >= Duration::from_millis(settings.sample_interval_ms) {
+            last_analysis = Instant::now();
+            let frame = analyse_window(samples.make_contiguous());
+            if let Ok(mut latest) = shared.latest.lock() {
+                *latest = Some(frame);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn read_available_packets(
+    session: &LoopbackSession,
+    samples: &mut VecDeque<f32>,
+) -> std::result::Result<(), String> {
+    unsafe {
+        loop {
+            let packet = session
+                .capture
+                .GetNextPacketSize()
+                .map_err(|e| format!("GetNextPacketSize failed: {e:?}"))?;
+            if packet == 0 {
+                return Ok(());
+            }
+
+            let mut data: *mut u8 = std::ptr::null_mut();
+            let mut frames: u32 = 0;
+            let mut flags: u32 = 0;
+            session
+                .capture
+                .GetBuffer(&mut data, &mut frames, &mut flags, None, None)
+                .map_err(|e| format!("GetBuffer failed: {e:?}"))?;
+
+            let silent = flags & (AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0;
+            for frame in 0..frames as usize {
+                if silent || data.is_null() {
+                    samples.push_back(0.0);
+                    continue;
+                }
+
+                let mut sum = 0.0f32;
+                for channel in 0..session.channels {
+                    let index = frame * session.channels + channel;
+                    sum += match session.sample_format {
+                        SampleFormat::F32 => *(data as *const f32).add(index),
+                        SampleFormat::I16 => *(data as *const i16).add(index) as f32 / 32768.0,
+                    };
+                }
+                samples.push_back(sum / session.channels.max(1) as f32);
+            }
+
+            session
+                .capture
+                .ReleaseBuffer(frames)
+                .map_err(|e| format!("ReleaseBuffer failed: {e:?}"))?;
+        }
+    }
+}
+
+fn device_enumerator() -> std::result::Result<IMMDeviceEnumerator, String> {
+    unsafe {
+        CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+            .map_err(|e| format!("MMDeviceEnumerator unavailable: {e:?}"))
+    }
+}
+
+fn device_id(device: &IMMDevice) -> std::result::Result<String, String> {
+    unsafe {
+        let raw = device
+            .GetId()
+            .map_err(|e| format!("IMMDevice::GetId failed: {e:?}"))?;
+        let id = raw.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(raw.0 as *const core::ffi::c_void));
+        Ok(id)
+    }
+}
+
+fn default_endpoint_id() -> std::result::Result<String, String> {
+    let enumerator = device_enumerator()?;
+    let device = unsafe {
+        enumerator
+            .GetDefaultAudioEndpoint(eRender, eConsole)
+            .map_err(|e| format!("No default render endpoint: {e:?}"))?
+    };
+    device_id(&device)
+}
+
+fn open_loopback_session() -> std::result::Result<LoopbackSession, String> {
+    let enumerator = device_enumerator()?;
+    unsafe {
+        let device = enumerator
+            .GetDefaultAudioEndpoint(eRender, eConsole)
+            .map_err(|e| format!("No default render endpoint: {e:?}"))?;
+        let device_id = device_id(&device)?;
+
+        let client: IAudioClient = device
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| format!("IAudioClient activation failed: {e:?}"))?;
+
+        let format_ptr = client
+            .GetMixFormat()
+            .map_err(|e| format!("GetMixFormat failed: {e:?}"))?;
+        let format: WAVEFORMATEX = std::ptr::read_unaligned(format_ptr);
+        let tag = format.wFormatTag;
+        let bits = format.wBitsPerSample;
+        let channels = format.nChannels as usize;
+
+        let sub_format = if tag == WAVE_FORMAT_EXTENSIBLE {
+            let extensible: WAVEFORMATEXTENSIBLE =
+                std::ptr::read_unaligned(format_ptr as *const WAVEFORMATEXTENSIBLE);
+            extensible.SubFormat.data1 as u16
+        } else {
+            tag
+        };
+
+        let sample_format = match (sub_format, bits) {
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => Some(SampleFormat::F32),
+            (WAVE_FORMAT_PCM, 16) => Some(SampleFormat::I16),
+            _ => None,
+        };
+
+        let init = client.Initialize(
+            AUDCLNT_SHAREMODE_SHARED,
+            AUDCLNT_STREAMFLAGS_LOOPBACK,
+            10_000_000,
+            0,
+            format_ptr,
+            None,
+        );
+        CoTaskMemFree(Some(format_ptr as *const core::ffi::c_void));
+
+        let sample_format = sample_format.ok_or_else(|| {
+            format!("Unsupported mix format (tag=0x{:X}, bits={})", sub_format, bits)
+        })?;
+        init.map_err(|e| format!("IAudioClient::Initialize (loopback) failed: {e:?}"))?;
+
+        let capture: IAudioCaptureClient = client
+            .GetService()
+            .map_err(|e| format!("IAudioCaptureClient unavailable: {e:?}"))?;
+
+        Ok(LoopbackSession {
+            client,
+            capture,
+            device_id,
+            channels: channels.max(1),
+            sample_format,
+        })
+    }
+}
+
+/// RMS plus `BAND_COUNT` log-spaced magnitude bands (each roughly 0–1)
+/// from a Hann-windowed FFT of the most recent samples.
+fn analyse_window(samples: &[f32]) -> AudioFrame {
+    if samples.is_empty() {
+        return AudioFrame {
+            rms: 0.0,
+            bands: vec![0.0; BAND_COUNT],
+        };
+    }
+
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32)
+        .sqrt()
+        .min(1.0);
+
+    let mut re = vec![0.0f32; WINDOW_SIZE];
+    let mut im = vec![0.0f32; WINDOW_SIZE];
+    let offset = WINDOW_SIZE.saturating_sub(samples.len());
+    for (i, sample) in samples.iter().take(WINDOW_SIZE).enumerate() {
+        let n = i + offset;
+        let hann = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * n as f32 / (WINDOW_SIZE - 1) as f32).cos();
+        re[n] = sample * hann;
+    }
+    fft_in_place(&mut re, &mut im);
+
+    let half = WINDOW_SIZE / 2;
+    let mut bands = Vec::with_capacity(BAND_COUNT);
+    for band in 0..BAND_COUNT {
+        // Log-spaced bin edges from bin 1 to the Nyquist bin.
+        let lo = (half as f32).powf(band as f32 / BAND_COUNT as f32).floor().max(1.0) as usize;
+        let hi = ((half as f32).powf((band + 1) as f32 / BAND_COUNT as f32).ceil() as usize)
+            .clamp(lo + 1, half);
+        let magnitude = (lo..hi)
+            .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+            .sum::<f32>()
+            / (hi - lo) as f32;
+        bands.push((magnitude / (WINDOW_SIZE as f32 / 4.0)).sqrt().min(1.0));
+    }
+
+    AudioFrame { rms, bands }
+}
+
+/// Iterative radix-2 Cooley–Tukey FFT; `re.len()` must be a power of two.
+fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0usize;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -1,8 +1,9 @@
 // ~/Sentinel/sentinel-addons/wallpaper/src/bootstrap.rs
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::ADDON_NAME;
+use crate::data_loaders::config::{AddonConfig, BootstrapSettings};
 use crate::utility::{sentinel_addons_dir, sentinel_assets_dir};
 use crate::{info, warn};
 
@@ -45,6 +46,15 @@ fn is_running_from_install_dir() -> bool {
     }
   }
 
+  fn other_wallpaper_processes_running() -> bool {
+    let pid_filter = format!("PID ne {}", std::process::id());
+    std::process::Command::new("tasklist")
+      .args(["/FI", "IMAGENAME eq sentinel-wallpaper.exe", "/FI", &pid_filter, "/NH"])
+      .output()
+      .map(|o| String::from_utf8_lossy(&o.stdout).contains("sentinel-wallpaper.exe"))
+      .unwrap_or(false)
+  }
+
   fn terminate_other_wallpaper_processes() {
     let current_pid = std::process::id();
     let pid_filter = format!("PID ne {}", current_pid);
@@ -75,8 +85,38 @@ fn is_running_from_install_dir() -> bool {
     }
   }
 
-/// Check if sentinelc.exe (the backend) is running; if not, start it.
-fn ensure_backend_running() {
+/// Bootstrap options from `config_path`, or from the installed config when
+/// that file doesn't exist yet (first run from a download folder).  Env vars
+/// override the file.
+fn load_bootstrap_settings(config_path: &Path) -> BootstrapSettings {
+    let installed = addon_install_dir().map(|d| d.join("config.yaml"));
+    let path = if config_path.exists() {
+        Some(config_path.to_path_buf())
+    } else {
+        installed.filter(|p| p.exists())
+    };
+
+    let mut settings = path
+        .and_then(|p| AddonConfig::load(&p))
+        .map(|config| config.settings.bootstrap)
+        .unwrap_or_default();
+
+    if let Ok(value) = std::env::var("SENTINEL_AUTO_START_BACKEND") {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "0" | "false" | "no" | "off" => settings.auto_start_backend = false,
+            "1" | "true" | "yes" | "on" => settings.auto_start_backend = true,
+            _ => warn!(
+                "[{}] Ignoring SENTINEL_AUTO_START_BACKEND='{}' (expected 0/1)",
+                ADDON_NAME, value
+            ),
+        }
+    }
+    settings
+}
+
+/// Check if sentinelc.exe (the backend) is running; if not, start it (unless
+/// `auto_start_backend` is off, in which case wait for someone else to).
+fn ensure_backend_running(settings: &BootstrapSettings) {
     info!("[{}] Checking if sentinelc.exe is running...", ADDON_NAME);
     let backend_running = std::process::Command::new("tasklist")
         .args(["/FI", "IMAGENAME eq sentinelc.exe", "/NH"])
@@ -89,16 +129,19 @@ fn ensure_backend_running() {
         return;
     }
 
+    if !settings.auto_start_backend {
+        warn!(
+            "[{}] sentinelc.exe is NOT running and auto_start_backend is off; waiting for an external backend",
+            ADDON_NAME
+        );
+        wait_for_ipc_pipe();
+        return;
+    }
+
     warn!("[{}] sentinelc.exe is NOT running, attempting to start it", ADDON_NAME);
-    let Some(home) = std::env::var("USERPROFILE").ok() else {
-        warn!("[{}] Cannot resolve USERPROFILE to find sentinelc.exe", ADDON_NAME);
+    let Some(backend_exe) = resolve_backend_exe(settings) else {
         return;
     };
-    let backend_exe = PathBuf::from(&home).join(".Sentinel").join("sentinelc.exe");
-    if !backend_exe.exists() {
-        warn!("[{}] Backend not found at {}", ADDON_NAME, backend_exe.display());
-        return;
-    }
     match std::process::Command::new(&backend_exe).spawn() {
         Ok(_) => {
             info!("[{}] Started sentinelc.exe from {}", ADDON_NAME, backend_exe.display());
@@ -109,6 +152,47 @@ fn ensure_backend_running() {
     }
 }
 
+/// Pick the backend executable: `SENTINEL_BACKEND_EXE`, then
+/// `bootstrap.backend_path`, then `~/.Sentinel/sentinelc.exe`.  Overrides
+/// that don't exist are logged and skipped.
+fn resolve_backend_exe(settings: &BootstrapSettings) -> Option<PathBuf> {
+    let env_path = std::env::var("SENTINEL_BACKEND_EXE")
+        .ok()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from);
+    let overrides = [
+        ("SENTINEL_BACKEND_EXE", env_path),
+        ("bootstrap.backend_path", settings.backend_path.clone()),
+    ];
+
+    for (source, path) in overrides {
+        let Some(path) = path else { continue };
+        if path.is_file() {
+            info!("[{}] Using backend from {}: {}", ADDON_NAME, source, path.display());
+            return Some(path);
+        }
+        warn!(
+            "[{}] Backend from {} not found at {} — trying the next location",
+            ADDON_NAME,
+            source,
+            path.display()
+        );
+    }
+
+    let Some(home) = std::env::var("USERPROFILE").ok() else {
+        warn!("[{}] Cannot resolve USERPROFILE to find sentinelc.exe", ADDON_NAME);
+        return None;
+    };
+    let backend_exe = PathBuf::from(&home).join(".Sentinel").join("sentinelc.exe");
+    if !backend_exe.exists() {
+        warn!("[{}] Backend not found at {}", ADDON_NAME, backend_exe.display());
+        return None;
+    }
+    info!("[{}] Using default backend: {}", ADDON_NAME, backend_exe.display());
+    Some(backend_exe)
+}
+
 /// Poll until the Sentinel IPC named pipe exists, or give up after ~10 seconds.
 fn wait_for_ipc_pipe() {
     use crate::utility::to_wstring;
@@ -139,12 +223,12 @@ fn wait_for_ipc_pipe() {
 
 /// Bootstrap the addon: create directory structure, scaffold default files,
 /// copy the exe into `bin/`, and relaunch from the installed location.
-pub fn bootstrap_addon() {
+pub fn bootstrap_addon(config_path: &Path) {
     info!("[{}] === Bootstrap starting ===", ADDON_NAME);
     info!("[{}] Current exe: {:?}", ADDON_NAME, std::env::current_exe());
 
     // Ensure the backend is running first
-    ensure_backend_running();
+    ensure_backend_running(&load_bootstrap_settings(config_path));
 
     let addon_dir = match addon_install_dir() {
         Some(d) => d,
@@ -154,22 +238,8 @@ pub fn bootstrap_addon() {
         }
     };
     info!("[{}] Addon directory: {}", ADDON_NAME, addon_dir.display());
-
-    // Create directory structure
+    scaffold_addon_dir(&addon_dir);
     let bin_dir = addon_dir.join("bin");
-    let options_dir = addon_dir.join("options");
-    let _ = fs::create_dir_all(&bin_dir);
-    let _ = fs::create_dir_all(&options_dir);
-    info!("[{}] Created directory structure at {}", ADDON_NAME, addon_dir.display());
-
-    // Scaffold default files (only if they don't already exist)
-    scaffold_addon_json(&addon_dir);
-    scaffold_config_yaml(&addon_dir);
-    scaffold_schema_yaml(&addon_dir);
-    scaffold_options_html(&options_dir);
-    scaffold_options_assets(&options_dir);
-    scaffold_default_asset();
-    info!("[{}] Scaffolding complete", ADDON_NAME);
 
     // If already running from the install dir, nothing more to do
     if is_running_from_install_dir() {
@@ -239,6 +309,100 @@ pub fn bootstrap_addon() {
     }
 }
 
+/// Create the addon directory structure and scaffold the default files
+/// (only those that don't already exist) plus the default asset.
+fn scaffold_addon_dir(addon_dir: &PathBuf) {
+    let bin_dir = addon_dir.join("bin");
+    let options_dir = addon_dir.join("options");
+    let _ = fs::create_dir_all(&bin_dir);
+    let _ = fs::create_dir_all(&options_dir);
+    info!("[{}] Created directory structure at {}", ADDON_NAME, addon_dir.display());
+
+    scaffold_addon_json(addon_dir);
+    scaffold_config_yaml(addon_dir);
+    scaffold_schema_yaml(addon_dir);
+    scaffold_options_html(&options_dir);
+    scaffold_options_assets(&options_dir);
+    scaffold_default_asset();
+    info!("[{}] Scaffolding complete", ADDON_NAME);
+}
+
+/// `install` subcommand: scaffold the addon directory and copy this exe into
+/// `bin/`, without starting the backend or relaunching.  Returns the addon
+/// directory.
+pub fn install_addon() -> Result<PathBuf, String> {
+    let addon_dir = addon_install_dir().ok_or("Cannot resolve addon install directory")?;
+    scaffold_addon_dir(&addon_dir);
+
+    if !is_running_from_install_dir() {
+        let current_exe = std::env::current_exe()
+            .map_err(|e| format!("Cannot determine current exe path: {e}"))?;
+        let dst = addon_dir.join("bin").join(EXE_NAME);
+        if !files_are_identical(&current_exe, &dst) {
+            fs::copy(&current_exe, &dst)
+                .map_err(|e| format!("Failed to copy exe to {}: {e}", dst.display()))?;
+            info!("[{}] Copied exe -> {}", ADDON_NAME, dst.display());
+        }
+    }
+    Ok(addon_dir)
+}
+
+/// First half of `uninstall`: stop every other running addon instance so
+/// nothing holds files in the install directory.  Instances are asked to
+/// quit first, so they still save their shutdown snapshot; `taskkill` only
+/// handles whatever is left after `STOP_TIMEOUT`.
+pub fn stop_running_instances() {
+    const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+    let stopped = crate::wallpaper_engine::stop_other_instances(STOP_TIMEOUT);
+    if stopped > 0 {
+        info!("[{}] Stopped {} running instance(s)", ADDON_NAME, stopped);
+    }
+    if other_wallpaper_processes_running() {
+        warn!("[{}] Wallpaper processes still running; terminating them", ADDON_NAME);
+        terminate_other_wallpaper_processes();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+    }
+}
+
+/// Second half of `uninstall`: remove the addon directory and the scaffolded
+/// `sentinel.default` asset.  Snapshots under `Assets/wallpaper/snapshots`
+/// stay, since the desktop wallpaper now points at one.  When this exe runs
+/// from `bin/` it can't delete itself, so a detached `cmd` removes the
+/// directory once this process has exited.  Returns the removed paths.
+pub fn remove_installed_files() -> Result<Vec<PathBuf>, String> {
+    let addon_dir = addon_install_dir().ok_or("Cannot resolve addon install directory")?;
+    let mut removed = Vec::new();
+
+    if let Some(assets_dir) = sentinel_assets_dir() {
+        let default_asset = assets_dir.join("wallpaper").join("sentinel.default");
+        if default_asset.exists() {
+            fs::remove_dir_all(&default_asset)
+                .map_err(|e| format!("Failed to remove {}: {e}", default_asset.display()))?;
+            removed.push(default_asset);
+        }
+    }
+
+    if !addon_dir.exists() {
+        return Ok(removed);
+    }
+    if is_running_from_install_dir() {
+        let script = format!(
+            "ping -n 3 127.0.0.1 >NUL & rmdir /S /Q \"{}\"",
+            addon_dir.display()
+        );
+        std::process::Command::new("cmd")
+            .args(["/C", &script])
+            .spawn()
+            .map_err(|e| format!("Failed to schedule removal of {}: {e}", addon_dir.display()))?;
+        info!("[{}] Scheduled removal of {} after exit", ADDON_NAME, addon_dir.display());
+    } else {
+        fs::remove_dir_all(&addon_dir)
+            .map_err(|e| format!("Failed to remove {}: {e}", addon_dir.display()))?;
+    }
+    removed.push(addon_dir);
+    Ok(removed)
+}
+
 fn scaffold_addon_json(addon_dir: &PathBuf) {
     let path = addon_dir.join("addon.json");
     if path.exists() { return; }
@@ -275,16 +439,36 @@ fn scaffold_config_yaml(addon_dir: &PathBuf) {
       focus: "per-monitor"
       maximized: "per-monitor"
       fullscreen: "all-monitors"
+      presenting: "off"
       idle_timeout_ms: 0
+      idle_unpause_grace_ms: 0
+      unpause_delay_ms: 0
       check_interval_ms: 500
+      battery_pause_percent: 0
+      gpu: "off"
+      gpu_pause_percent: 90
     watcher:
       enabled: true
       interval_ms: 600
+      ignore_globs: []
     interactions:
       send_move: true
       send_click: true
+      click_through: false
       poll_interval_ms: 8
       move_threshold_px: 0.5
+      send_scroll: false
+      scroll_threshold: 40
+      send_keys: false
+    snapshot:
+      max_dimension: 0
+      interval_ms: 5000
+      warmup_ms: 1500
+      format: "bmp"
+      jpeg_quality: 85
+      full_virtual_screen: false
+      keep_history: 0
+      hdr_gamma_correct: false
     audio:
       enabled: true
       sample_interval_ms: 100
@@ -292,16 +476,39 @@ fn scaffold_config_yaml(addon_dir: &PathBuf) {
       retry_interval_ms: 2000
       change_threshold: 0.015
       quantize_decimals: 2
+    editable_poll_ms: 250
+    battery_fps_limit: 30
   runtime:
     tick_sleep_ms: 8
+    idle_tick_sleep_ms: 100
     reapply_on_pause_change: true
+    allow_remote_session: false
+    host_strategy: "auto"
+    pause_all_on_lock: true
+    manage_static_wallpaper: true
+    idle_screensaver: false
+  rendering:
+    navigation_retries: 3
+    fallback_color: "off"
+    capture_protection: false
+    heartbeat_timeout_ms: 10000
+    heartbeat_reload: true
+    max_preload_wait_ms: 2000
   diagnostics:
     log_pause_state_changes: true
     log_watcher_reloads: true
+    recent_log_lines: 500
   development:
     update_check: true
     debug: false
     log_level: warn
+    show_error_wallpaper: false
+    hot_css_only: false
+  hotkeys:
+    toggle_pause: "Ctrl+Alt+W"
+  bootstrap:
+    auto_start_backend: true
+    backend_path: ""
 
 wallpaper:
   enabled: true
@@ -348,12 +555,40 @@ ui:
               min: 0
               max: 3600000
               step: 1000
+            - path: "idle_unpause_grace_ms"
+              label: "Idle Resume Grace (ms)"
+              control: "number_range"
+              min: 0
+              max: 10000
+              step: 250
+            - path: "unpause_delay_ms"
+              label: "Resume Stagger (ms)"
+              control: "number_range"
+              min: 0
+              max: 2000
+              step: 50
             - path: "check_interval_ms"
               label: "Pause Check Interval (ms)"
               control: "number_range"
               min: 50
               max: 5000
               step: 50
+            - path: "battery_pause_percent"
+              label: "Battery Pause Below (%)"
+              control: "number_range"
+              min: 0
+              max: 100
+              step: 5
+            - path: "gpu"
+              label: "Pause On High GPU Usage"
+              control: "dropdown"
+              options: ["off", "all-monitors"]
+            - path: "gpu_pause_percent"
+              label: "GPU Pause Above (%)"
+              control: "number_range"
+              min: 1
+              max: 100
+              step: 5
 
         - title: "Watcher"
           path: "watcher"
@@ -377,6 +612,9 @@ ui:
             - path: "send_click"
               label: "Send Mouse Click"
               control: "toggle"
+            - path: "click_through"
+              label: "Click Through To Desktop"
+              control: "toggle"
             - path: "poll_interval_ms"
               label: "Interaction Poll Interval (ms)"
               control: "number_range"
@@ -436,9 +674,24 @@ ui:
           min: 1
           max: 100
           step: 1
+        - path: "idle_tick_sleep_ms"
+          label: "Idle Tick Sleep (ms)"
+          control: "number_range"
+          min: 0
+          max: 500
+          step: 10
         - path: "reapply_on_pause_change"
           label: "Reapply On Pause Change"
           control: "toggle"
+        - path: "pause_all_on_lock"
+          label: "Pause While Locked"
+          control: "toggle"
+        - path: "manage_static_wallpaper"
+          label: "Manage Windows Wallpaper"
+          control: "toggle"
+        - path: "idle_screensaver"
+          label: "Screensaver When Idle"
+          control: "toggle"
 
     - title: "Diagnostics"
       path: "settings.diagnostics"
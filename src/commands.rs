@@ -0,0 +1,59 @@
+// ~/Sentinel/sentinel-addons/wallpaper/src/commands.rs
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{ipc_connector::request_quick, ADDON_NAME};
+
+/// A command queued for this addon by the backend (usually from the options UI).
+#[derive(Debug, Deserialize)]
+pub struct AddonCommand {
+    #[serde(default)]
+    pub id: Value,
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+impl AddonCommand {
+    pub fn str_arg(&self, key: &str) -> Option<&str> {
+        self.args.get(key).and_then(|v| v.as_str())
+    }
+
+    pub fn bool_arg(&self, key: &str) -> Option<bool> {
+        self.args.get(key).and_then(|v| v.as_bool())
+    }
+
+    pub fn u64_arg(&self, key: &str) -> Option<u64> {
+        self.args.get(key).and_then(|v| v.as_u64())
+    }
+}
+
+/// Drain pending commands for this addon.  Uses `request_quick` so the tick
+/// loop never blocks; an unreachable backend simply yields no commands.
+pub fn poll() -> Vec<AddonCommand> {
+    let args = serde_json::json!({ "addon": ADDON_NAME });
+    let Some(raw) = request_quick("addon", "poll_commands", Some(args)) else {
+        return Vec::new();
+    };
+
+    serde_json::from_str::<Vec<AddonCommand>>(&raw).unwrap_or_default()
+}
+
+/// Report a command's outcome back to the backend so the caller can resolve it.
+pub fn respond(command: &AddonCommand, result: Result<Value, String>) {
+    let (ok, data, error) = match result {
+        Ok(data) => (true, data, Value::Null),
+        Err(e) => (false, Value::Null, Value::String(e)),
+    };
+
+    let args = serde_json::json!({
+        "addon": ADDON_NAME,
+        "id": command.id,
+        "cmd": command.cmd,
+        "ok": ok,
+        "data": data,
+        "error": error,
+    });
+    let _ = request_quick("addon", "command_result", Some(args));
+}
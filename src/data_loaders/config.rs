@@ -1,8 +1,11 @@
-use std::path::Path;
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
 
+use chrono::{Datelike, Local, Weekday};
 use serde_yaml::{Mapping, Value};
 
-use super::yaml::load_yaml;
+use super::{validation::validate, yaml::load_yaml};
+
+pub use super::validation::ConfigError;
 
 #[derive(Debug, Clone)]
 pub struct AddonConfig {
@@ -15,8 +18,11 @@ pub struct AddonConfig {
 pub struct AddonSettings {
     pub performance: PerformanceSettings,
     pub runtime: RuntimeSettings,
+    pub rendering: RenderingSettings,
     pub diagnostics: DiagnosticsSettings,
     pub development: DevelopmentSettings,
+    pub hotkeys: HotkeySettings,
+    pub bootstrap: BootstrapSettings,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +31,11 @@ pub struct PerformanceSettings {
     pub watcher: WatcherSettings,
     pub interactions: InteractionSettings,
     pub audio: AudioSettings,
+    pub snapshot: SnapshotSettings,
+    /// How often hosted assets are checked for changed editable properties.
+    pub editable_poll_ms: u64,
+    /// Frame-rate cap hinted to wallpapers while on battery (0 = no hint).
+    pub battery_fps_limit: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -33,22 +44,47 @@ pub struct PausingSettings {
     pub maximized: PauseMode,
     pub fullscreen: PauseMode,
     pub battery: PauseMode,
+    /// Pause while Windows reports presentation mode (global).
+    pub presenting: PauseMode,
     pub idle_timeout_ms: u64,
+    /// How long input must persist before an idle pause is lifted.
+    pub idle_unpause_grace_ms: u64,
+    /// Gap between successive hosts resuming after a pause lifts (0 = all at once).
+    pub unpause_delay_ms: u64,
     pub check_interval_ms: u64,
+    /// Only honour `battery` pausing below this charge percent (0 = any charge).
+    pub battery_pause_percent: u8,
+    /// Pause while GPU usage stays above `gpu_pause_percent` (global).
+    pub gpu: PauseMode,
+    pub gpu_pause_percent: u8,
 }
 
 #[derive(Debug, Clone)]
 pub struct WatcherSettings {
     pub enabled: bool,
     pub interval_ms: u64,
+    /// Extra glob patterns for asset files whose changes never trigger a
+    /// reload, on top of the built-in temp/backup/preview rules.
+    pub ignore_globs: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct InteractionSettings {
     pub send_move: bool,
     pub send_click: bool,
+    /// Let desktop clicks fall through the host window to the real desktop.
+    pub click_through: bool,
     pub poll_interval_ms: u64,
     pub move_threshold_px: f32,
+    /// Forward mouse-wheel input as `native_scroll` (installs a low-level
+    /// mouse hook while enabled).
+    pub send_scroll: bool,
+    /// Accumulated wheel delta (120 = one notch) needed before a
+    /// `native_scroll` is sent.
+    pub scroll_threshold: u32,
+    /// Forward key presses as `native_key` while the desktop is focused
+    /// (installs a low-level keyboard hook while enabled).
+    pub send_keys: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -61,23 +97,111 @@ pub struct AudioSettings {
     pub quantize_decimals: u8,
 }
 
+#[derive(Debug, Clone)]
+pub struct SnapshotSettings {
+    /// Longest edge (px) of the stitched snapshot; 0 keeps full resolution.
+    /// A downscaled snapshot covers the whole virtual screen and is spanned.
+    pub max_dimension: u32,
+    /// Period of the background snapshot BMP save.
+    pub interval_ms: u64,
+    /// Delay after a wallpaper finishes loading before it is snapshotted.
+    pub warmup_ms: u64,
+    pub format: SnapshotFormat,
+    /// JPEG encoder quality (1-100) when `format` is `jpeg`.
+    pub jpeg_quality: u8,
+    /// Size the snapshot to the whole virtual screen instead of the hosted
+    /// monitors' bounding box, so it also lines up when only some monitors
+    /// are hosted; unhosted monitors get `fallback_color` (or black).
+    pub full_virtual_screen: bool,
+    /// Previous snapshots kept as `paused_wallpaper_snapshot.N.<ext>`
+    /// (0 = only the active file).
+    pub keep_history: u32,
+    /// Darken captures of HDR monitors with `HDR_SNAPSHOT_GAMMA`, which
+    /// `PrintWindow` returns washed out.
+    pub hdr_gamma_correct: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct RuntimeSettings {
     pub tick_sleep_ms: u64,
+    /// Loop sleep while every host is paused or none exist (0 = always use
+    /// `tick_sleep_ms`).
+    pub idle_tick_sleep_ms: u64,
     pub reapply_on_pause_change: bool,
+    /// Host wallpapers inside a Remote Desktop session; when off, RDP
+    /// sessions only get the static snapshot wallpaper.
+    pub allow_remote_session: bool,
+    /// Which desktop window hosts wallpapers; `auto` runs the fallback cascade.
+    pub host_strategy: HostStrategy,
+    /// Pause every host while the workstation is locked.
+    pub pause_all_on_lock: bool,
+    /// Keep the Windows static wallpaper in sync with snapshots; when off,
+    /// snapshots are neither saved nor applied via `SPI_SETDESKWALLPAPER`.
+    pub manage_static_wallpaper: bool,
+    /// Once idle for `pausing.idle_timeout_ms`, lift the hosts over every
+    /// window instead of pausing them, until the next input.
+    pub idle_screensaver: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderingSettings {
+    /// Times a failed WebView2 navigation is retried (with backoff) before giving up.
+    pub navigation_retries: u32,
+    /// Solid `[r, g, b]` shown where a section's asset does not resolve (and
+    /// no error page is wanted); `None` leaves those monitors unhosted.
+    pub fallback_color: Option<[u8; 3]>,
+    /// Hide host windows from screen capture (`WDA_EXCLUDEFROMCAPTURE`).
+    pub capture_protection: bool,
+    /// Silence after which a manifest `heartbeat` wallpaper counts as hung.
+    pub heartbeat_timeout_ms: u64,
+    /// Reload a hung wallpaper instead of only logging it.
+    pub heartbeat_reload: bool,
+    /// How long a rebuild waits for its new, hidden hosts to load before
+    /// swapping them in for the old ones (0 = tear down first, no preload).
+    pub max_preload_wait_ms: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct DiagnosticsSettings {
     pub log_pause_state_changes: bool,
     pub log_watcher_reloads: bool,
+    /// Log lines kept in memory for the `get_recent_logs` command.
+    pub recent_log_lines: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct DevelopmentSettings {
     pub update_check: bool,
     pub debug: bool,
-    }
+    /// Show a built-in error page on monitors whose wallpaper failed to load.
+    pub show_error_wallpaper: bool,
+    /// Live-reload CSS-only edits by swapping stylesheets instead of navigating.
+    pub hot_css_only: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct HotkeySettings {
+    /// Global hotkey that pauses/resumes every wallpaper; `None` disables it.
+    pub toggle_pause: Option<Hotkey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapSettings {
+    /// Launch `sentinelc.exe` at startup when it is not already running.
+    /// `SENTINEL_AUTO_START_BACKEND=0` overrides it to off.
+    pub auto_start_backend: bool,
+    /// Backend executable to launch instead of `~/.Sentinel/sentinelc.exe`.
+    /// `SENTINEL_BACKEND_EXE` takes precedence over it.
+    pub backend_path: Option<PathBuf>,
+}
+
+/// A global hotkey as passed to `RegisterHotKey`: `MOD_*` flags plus a
+/// virtual-key code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub vk: u32,
+}
 
 #[derive(Debug, Clone)]
 pub struct WallpaperConfig {
@@ -86,11 +210,39 @@ pub struct WallpaperConfig {
     pub monitor_index: Vec<String>,
     pub mode: String,
     pub z_index: String,
+    /// Whether the section set `z_index` itself; when it did not, an asset's
+    /// manifest `default_z_index` takes its place.
+    pub z_index_set: bool,
+    /// Optional monitor key -> z_index overrides (`"0"`, `"1"`, `"p"`).
+    pub z_index_per_monitor: HashMap<String, String>,
     pub wallpaper_id: String,
+    /// Optional monitor key -> wallpaper id overrides (`"0"`, `"1"`, `"p"`).
+    pub per_monitor: HashMap<String, String>,
+    /// Seed for the `random` monitor keyword; `None` picks a new screen each session.
+    pub random_seed: Option<u64>,
+    /// Weekdays the section is active on; empty means every day.
+    pub days: Vec<Weekday>,
+    pub click_through: bool,
+    /// 0.0 (invisible) to 1.0 (opaque, default).
+    pub opacity: f32,
+    /// Media volume posted to the page as `native_volume`, 0.0 to 1.0
+    /// (default).
+    pub volume: f32,
+    /// Explicit placement relative to the desktop icons; `None` leaves it to
+    /// `settings.runtime.host_strategy`.
+    pub icon_layer: Option<IconLayer>,
+    /// Layer onto monitors other sections already claimed instead of
+    /// skipping them; stacked hosts sit above the claiming section's.
+    pub stack: bool,
     pub pause_focus_mode: PauseMode,
     pub pause_maximized_mode: PauseMode,
     pub pause_fullscreen_mode: PauseMode,
     pub pause_battery_mode: PauseMode,
+    pub pause_presenting_mode: PauseMode,
+    pub pause_gpu_mode: PauseMode,
+    /// Image names (lowercase, without `.exe`) whose presence in
+    /// `sysdata.processes` pauses this section, focused or not.
+    pub pause_when_process: Vec<String>,
 }
 
 impl Default for AddonSettings {
@@ -98,8 +250,11 @@ impl Default for AddonSettings {
         Self {
             performance: PerformanceSettings::default(),
             runtime: RuntimeSettings::default(),
+            rendering: RenderingSettings::default(),
             diagnostics: DiagnosticsSettings::default(),
             development: DevelopmentSettings::default(),
+            hotkeys: HotkeySettings::default(),
+            bootstrap: BootstrapSettings::default(),
         }
     }
 }
@@ -111,6 +266,9 @@ impl Default for PerformanceSettings {
             watcher: WatcherSettings::default(),
             interactions: InteractionSettings::default(),
             audio: AudioSettings::default(),
+            snapshot: SnapshotSettings::default(),
+            editable_poll_ms: 250,
+            battery_fps_limit: 30,
         }
     }
 }
@@ -122,8 +280,14 @@ impl Default for PausingSettings {
             maximized: PauseMode::Off,
             fullscreen: PauseMode::Off,
             battery: PauseMode::Off,
+            presenting: PauseMode::Off,
             idle_timeout_ms: 0,
+            idle_unpause_grace_ms: 0,
+            unpause_delay_ms: 0,
             check_interval_ms: 500,
+            battery_pause_percent: 0,
+            gpu: PauseMode::Off,
+            gpu_pause_percent: 90,
         }
     }
 }
@@ -133,6 +297,7 @@ impl Default for WatcherSettings {
         Self {
             enabled: true,
             interval_ms: 600,
+            ignore_globs: Vec::new(),
         }
     }
 }
@@ -142,8 +307,12 @@ impl Default for InteractionSettings {
         Self {
             send_move: true,
             send_click: true,
+            click_through: false,
             poll_interval_ms: 8,
             move_threshold_px: 0.5,
+            send_scroll: false,
+            scroll_threshold: 40,
+            send_keys: false,
         }
     }
 }
@@ -161,11 +330,45 @@ impl Default for AudioSettings {
     }
 }
 
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            max_dimension: 0,
+            interval_ms: 5000,
+            warmup_ms: 1500,
+            format: SnapshotFormat::Bmp,
+            jpeg_quality: 85,
+            full_virtual_screen: false,
+            keep_history: 0,
+            hdr_gamma_correct: false,
+        }
+    }
+}
+
 impl Default for RuntimeSettings {
     fn default() -> Self {
         Self {
             tick_sleep_ms: 8,
+            idle_tick_sleep_ms: 100,
             reapply_on_pause_change: true,
+            allow_remote_session: false,
+            host_strategy: HostStrategy::Auto,
+            pause_all_on_lock: true,
+            manage_static_wallpaper: true,
+            idle_screensaver: false,
+        }
+    }
+}
+
+impl Default for RenderingSettings {
+    fn default() -> Self {
+        Self {
+            navigation_retries: 3,
+            fallback_color: None,
+            capture_protection: false,
+            heartbeat_timeout_ms: 10_000,
+            heartbeat_reload: true,
+            max_preload_wait_ms: 2000,
         }
     }
 }
@@ -175,6 +378,7 @@ impl Default for DiagnosticsSettings {
         Self {
             log_pause_state_changes: true,
             log_watcher_reloads: true,
+            recent_log_lines: 500,
         }
     }
 }
@@ -184,8 +388,101 @@ impl Default for DevelopmentSettings {
         Self {
             update_check: true,
             debug: false,
+            show_error_wallpaper: false,
+            hot_css_only: false,
+        }
+    }
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        Self {
+            toggle_pause: Hotkey::parse("Ctrl+Alt+W"),
+        }
+    }
+}
+
+impl Default for BootstrapSettings {
+    fn default() -> Self {
+        Self {
+            auto_start_backend: true,
+            backend_path: None,
+        }
+    }
+}
+
+impl Hotkey {
+    const MOD_ALT: u32 = 0x0001;
+    const MOD_CONTROL: u32 = 0x0002;
+    const MOD_SHIFT: u32 = 0x0004;
+    const MOD_WIN: u32 = 0x0008;
+
+    /// Parse `Modifier+...+Key`, e.g. `Ctrl+Shift+F12`.  Case-insensitive;
+    /// at least one modifier is required so a global hotkey never swallows
+    /// plain typing.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut modifiers = 0u32;
+        let mut vk = None;
+
+        for part in value.split('+').map(|p| p.trim().to_lowercase()) {
+            match part.as_str() {
+                "ctrl" | "control" => modifiers |= Self::MOD_CONTROL,
+                "alt" => modifiers |= Self::MOD_ALT,
+                "shift" => modifiers |= Self::MOD_SHIFT,
+                "win" | "super" | "meta" => modifiers |= Self::MOD_WIN,
+                key => {
+                    if vk.is_some() {
+                        return None;
+                    }
+                    vk = Some(Self::parse_key(key)?);
+                }
+            }
+        }
+
+        if modifiers == 0 {
+            return None;
+        }
+        Some(Self { modifiers, vk: vk? })
+    }
+
+    /// Parse a config value: `Some(None)` when the hotkey is switched off,
+    /// `None` when it is not a valid combo.
+    pub(super) fn parse_setting(value: &str) -> Option<Option<Self>> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "off" | "none" | "disabled" => Some(None),
+            _ => Self::parse(value).map(Some),
         }
     }
+
+    fn parse_key(key: &str) -> Option<u32> {
+        let bytes = key.as_bytes();
+        if bytes.len() == 1 && bytes[0].is_ascii_alphanumeric() {
+            return Some(bytes[0].to_ascii_uppercase() as u32);
+        }
+        if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+            return (1..=24).contains(&n).then_some(0x70 + n - 1);
+        }
+
+        let vk = match key {
+            "space" => 0x20,
+            "enter" | "return" => 0x0D,
+            "tab" => 0x09,
+            "esc" | "escape" => 0x1B,
+            "pause" => 0x13,
+            "pageup" => 0x21,
+            "pagedown" => 0x22,
+            "end" => 0x23,
+            "home" => 0x24,
+            "left" => 0x25,
+            "up" => 0x26,
+            "right" => 0x27,
+            "down" => 0x28,
+            "insert" | "ins" => 0x2D,
+            "delete" | "del" => 0x2E,
+            _ => return None,
+        };
+        Some(vk)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -196,7 +493,7 @@ pub enum PauseMode {
 }
 
 impl PauseMode {
-    fn parse(value: &str) -> Option<Self> {
+    pub(super) fn parse(value: &str) -> Option<Self> {
         match value.trim().to_lowercase().as_str() {
             "off" | "none" | "disabled" | "false" => Some(Self::Off),
             "per-monitor" | "per_monitor" | "permonitor" | "monitor" | "true" => {
@@ -218,12 +515,125 @@ impl PauseMode {
     }
 }
 
+/// On-disk encoding of the stitched snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Bmp,
+    Jpeg,
+}
+
+impl SnapshotFormat {
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "bmp" | "bitmap" => Some(Self::Bmp),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Bmp => "bmp",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Desktop window that parents the wallpaper hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostStrategy {
+    /// WorkerW behind the icons, falling back to DefView's host, then Progman.
+    Auto,
+    WorkerW,
+    Progman,
+    DefView,
+}
+
+impl HostStrategy {
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "workerw" => Some(Self::WorkerW),
+            "progman" => Some(Self::Progman),
+            "defview" | "shelldll_defview" => Some(Self::DefView),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::WorkerW => "workerw",
+            Self::Progman => "progman",
+            Self::DefView => "defview",
+        }
+    }
+}
+
+/// Where a section's host sits relative to the desktop icons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconLayer {
+    /// WorkerW parent, behind the icons.
+    Behind,
+    /// Progman parent, above the icons but still below app windows.
+    Over,
+}
+
+impl IconLayer {
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "behind" | "below" => Some(Self::Behind),
+            "over" | "above" => Some(Self::Over),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Behind => "behind",
+            Self::Over => "over",
+        }
+    }
+
+    /// Desktop host that gives this relationship with the icons.
+    pub fn host_strategy(self) -> HostStrategy {
+        match self {
+            Self::Behind => HostStrategy::WorkerW,
+            Self::Over => HostStrategy::Progman,
+        }
+    }
+}
+
 impl AddonConfig {
     pub fn load(path: &Path) -> Option<Self> {
         let value = load_yaml(path)?;
         Self::from_yaml(&value)
     }
 
+    /// Strict load: reads `path` uncached and rejects it if the YAML fails to
+    /// parse or `validate` reports unknown keys, bad types, out-of-range
+    /// numbers or unparseable pause modes.
+    pub fn load_validated(path: &Path) -> Result<Self, Vec<ConfigError>> {
+        let file_error = |message: String| {
+            vec![ConfigError {
+                path: String::new(),
+                message,
+            }]
+        };
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| file_error(format!("failed to read {}: {e}", path.display())))?;
+        let root: Value =
+            serde_yaml::from_str(&text).map_err(|e| file_error(format!("invalid YAML: {e}")))?;
+
+        let errors = validate(&root);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Self::from_yaml(&root).ok_or_else(|| file_error("top level must be a mapping".to_string()))
+    }
+
     pub fn from_yaml(root: &Value) -> Option<Self> {
         let map = root.as_mapping()?;
 
@@ -240,8 +650,192 @@ impl AddonConfig {
         })
     }
 
+    /// Sections that are enabled and scheduled for today (local time).
     pub fn enabled_wallpapers(&self) -> Vec<&WallpaperConfig> {
-        self.wallpapers.iter().filter(|w| w.enabled).collect()
+        let today = Local::now().weekday();
+        self.wallpapers
+            .iter()
+            .filter(|w| w.enabled && w.active_on(today))
+            .collect()
+    }
+
+    /// Whether any section is limited to certain weekdays, so the set of
+    /// active sections can change at midnight.
+    pub fn has_day_schedules(&self) -> bool {
+        self.wallpapers.iter().any(|w| !w.days.is_empty())
+    }
+}
+
+impl WallpaperConfig {
+    /// Throwaway profile for a live preview of `wallpaper_id`: one monitor,
+    /// `fill`, behind the icons and never paused automatically.
+    pub fn preview(wallpaper_id: &str) -> Self {
+        Self {
+            section: "preview".to_string(),
+            enabled: true,
+            monitor_index: Vec::new(),
+            mode: "fill".to_string(),
+            z_index: "desktop".to_string(),
+            z_index_set: false,
+            z_index_per_monitor: HashMap::new(),
+            wallpaper_id: wallpaper_id.to_string(),
+            per_monitor: HashMap::new(),
+            random_seed: None,
+            days: Vec::new(),
+            click_through: false,
+            opacity: 1.0,
+            volume: 1.0,
+            icon_layer: None,
+            stack: false,
+            pause_focus_mode: PauseMode::Off,
+            pause_maximized_mode: PauseMode::Off,
+            pause_fullscreen_mode: PauseMode::Off,
+            pause_battery_mode: PauseMode::Off,
+            pause_presenting_mode: PauseMode::Off,
+            pause_gpu_mode: PauseMode::Off,
+            pause_when_process: Vec::new(),
+        }
+    }
+
+    pub fn active_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+}
+
+/// Parse a `#rrggbb` / `#rgb` color (leading `#` optional).  `off`, `none`
+/// or an empty string parse as `Some(None)`; anything else invalid is `None`.
+pub(super) fn parse_hex_color(value: &str) -> Option<Option<[u8; 3]>> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("off") || value.eq_ignore_ascii_case("none") {
+        return Some(None);
+    }
+
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    let rgb = match hex.len() {
+        6 => [channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?],
+        3 => [
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        ],
+        _ => return None,
+    };
+    Some(Some(rgb))
+}
+
+/// Parse one `days` entry: a weekday (`mon`, `monday`, ...), `weekdays`,
+/// `weekends`, or `daily` / `*`.
+pub(super) fn parse_day_entry(entry: &str) -> Option<Vec<Weekday>> {
+    const WEEKDAYS: [Weekday; 5] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+
+    match entry.trim().to_ascii_lowercase().as_str() {
+        "weekdays" | "weekday" => Some(WEEKDAYS.to_vec()),
+        "weekends" | "weekend" => Some(vec![Weekday::Sat, Weekday::Sun]),
+        "daily" | "everyday" | "all" | "*" => Some(
+            WEEKDAYS
+                .into_iter()
+                .chain([Weekday::Sat, Weekday::Sun])
+                .collect(),
+        ),
+        day => day.parse::<Weekday>().ok().map(|d| vec![d]),
+    }
+}
+
+/// `days` as a single string or a list of entries; unknown entries are
+/// skipped (validation reports them).
+fn days_at(map: &Mapping, key: &str) -> Vec<Weekday> {
+    let entries: Vec<&str> = match map.get(Value::String(key.to_string())) {
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Sequence(list)) => list.iter().filter_map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut days = Vec::new();
+    for day in entries.into_iter().filter_map(parse_day_entry).flatten() {
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    days
+}
+
+/// Write validation errors to `config.errors.log` next to `config_path` for the
+/// options UI; the file is removed once the config validates again.
+pub fn write_config_error_log(config_path: &Path, errors: &[ConfigError]) {
+    let log_path = config_path.with_file_name("config.errors.log");
+    if errors.is_empty() {
+        let _ = fs::remove_file(&log_path);
+        return;
+    }
+
+    let content: String = errors.iter().map(|e| format!("{e}\n")).collect();
+    let _ = fs::write(&log_path, content);
+}
+
+/// Hash of the config's YAML data rather than its text: comments, quoting,
+/// indentation and key order don't change it.  FNV-1a over a canonical
+/// rendering, so the value is stable across builds and can be persisted.
+/// `None` when the file is missing or not valid YAML.
+pub fn config_content_hash(path: &Path) -> Option<u64> {
+    let text = fs::read_to_string(path).ok()?;
+    let value: Value = serde_yaml::from_str(&text).ok()?;
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical);
+
+    Some(canonical.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    }))
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push('~'),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => out.push_str(&format!("{s:?}")),
+        Value::Sequence(items) => {
+            out.push('[');
+            for item in items {
+                write_canonical(item, out);
+                out.push(',');
+            }
+            out.push(']');
+        }
+        Value::Mapping(map) => {
+            let mut entries: Vec<(String, String)> = map
+                .iter()
+                .map(|(k, v)| {
+                    let (mut key, mut val) = (String::new(), String::new());
+                    write_canonical(k, &mut key);
+                    write_canonical(v, &mut val);
+                    (key, val)
+                })
+                .collect();
+            entries.sort();
+            out.push('{');
+            for (key, val) in entries {
+                out.push_str(&key);
+                out.push(':');
+                out.push_str(&val);
+                out.push(',');
+            }
+            out.push('}');
+        }
+        Value::Tagged(tagged) => {
+            out.push_str(&tagged.tag.to_string());
+            out.push(' ');
+            write_canonical(&tagged.value, out);
+        }
     }
 }
 
@@ -290,16 +884,38 @@ fn parse_wallpaper_section(
     section_map: &Mapping,
     settings: &AddonSettings,
 ) -> Option<WallpaperConfig> {
-    let wallpaper_id = str_at(section_map, "wallpaper_id")?.trim().to_string();
-    if wallpaper_id.is_empty() {
+    let wallpaper_id = str_at(section_map, "wallpaper_id")
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default();
+    let per_monitor = string_map_at(section_map, "per_monitor").unwrap_or_default();
+    if wallpaper_id.is_empty() && per_monitor.is_empty() {
         return None;
     }
 
     let enabled = bool_at(section_map, "enabled").unwrap_or(true);
     let monitor_index =
         string_list_at(section_map, "monitor_index").unwrap_or_else(|| vec!["*".to_string()]);
+    let random_seed = u64_at(section_map, "random_seed");
+    let days = days_at(section_map, "days");
     let mode = str_at(section_map, "mode").unwrap_or("fill").to_lowercase();
+    let z_index_set = str_at(section_map, "z_index").is_some();
     let z_index = str_at(section_map, "z_index").unwrap_or("desktop").to_lowercase();
+    let z_index_per_monitor = string_map_at(section_map, "z_index_per_monitor")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, layer)| (key, layer.to_lowercase()))
+        .collect();
+
+    let click_through = bool_at(section_map, "click_through")
+        .unwrap_or(settings.performance.interactions.click_through);
+    let opacity = f32_at(section_map, "opacity")
+        .map(|v| v.clamp(0.0, 1.0))
+        .unwrap_or(1.0);
+    let volume = f32_at(section_map, "volume")
+        .map(|v| v.clamp(0.0, 1.0))
+        .unwrap_or(1.0);
+    let icon_layer = str_at(section_map, "icon_layer").and_then(IconLayer::parse);
+    let stack = bool_at(section_map, "stack").unwrap_or(false);
 
     let legacy_focus = bool_at(section_map, "pause_on_focus").map(PauseMode::from_legacy_bool);
     let legacy_maximized = bool_at(section_map, "pause_on_maximized").map(PauseMode::from_legacy_bool);
@@ -324,6 +940,24 @@ fn parse_wallpaper_section(
         .or_else(|| pause_mode_in_pausing(section_map, "battery"))
         .unwrap_or(settings.performance.pausing.battery);
 
+    let pause_presenting_mode = pause_mode_at(section_map, "pause_presenting")
+        .or_else(|| pause_mode_at(section_map, "pause_when_presenting"))
+        .or_else(|| pause_mode_in_pausing(section_map, "presenting"))
+        .or_else(|| bool_at(section_map, "pause_when_presenting").map(PauseMode::from_legacy_bool))
+        .unwrap_or(settings.performance.pausing.presenting);
+
+    let pause_gpu_mode = pause_mode_at(section_map, "pause_gpu")
+        .or_else(|| pause_mode_in_pausing(section_map, "gpu"))
+        .unwrap_or(settings.performance.pausing.gpu);
+
+    let pause_when_process = string_list_at(section_map, "pause_when_process")
+        .or_else(|| str_at(section_map, "pause_when_process").map(|name| vec![name.to_string()]))
+        .unwrap_or_default()
+        .iter()
+        .map(|name| process_image_key(name))
+        .filter(|name| !name.is_empty())
+        .collect();
+
     if bool_at(section_map, "pause_fullscreen_all_monitors").unwrap_or(false) {
         pause_fullscreen_mode = PauseMode::AllMonitors;
     }
@@ -334,11 +968,24 @@ fn parse_wallpaper_section(
         monitor_index,
         mode,
         z_index,
+        z_index_set,
+        z_index_per_monitor,
         wallpaper_id,
+        per_monitor,
+        random_seed,
+        days,
+        click_through,
+        opacity,
+        volume,
+        icon_layer,
+        stack,
         pause_focus_mode,
         pause_maximized_mode,
         pause_fullscreen_mode,
         pause_battery_mode,
+        pause_presenting_mode,
+        pause_gpu_mode,
+        pause_when_process,
     })
 }
 
@@ -351,8 +998,11 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
     let settings_map = mapping_at(root, "settings");
     let performance_map = settings_map.and_then(|v| mapping_at(v, "performance"));
     let runtime_map = settings_map.and_then(|v| mapping_at(v, "runtime"));
+    let rendering_map = settings_map.and_then(|v| mapping_at(v, "rendering"));
     let diagnostics_map = settings_map.and_then(|v| mapping_at(v, "diagnostics"));
     let development_map = settings_map.and_then(|v| mapping_at(v, "development"));
+    let hotkeys_map = settings_map.and_then(|v| mapping_at(v, "hotkeys"));
+    let bootstrap_map = settings_map.and_then(|v| mapping_at(v, "bootstrap"));
 
     if let Some(perf) = performance_map {
         if let Some(pausing) = mapping_at(perf, "pausing") {
@@ -364,14 +1014,42 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
                 .unwrap_or(settings.performance.pausing.fullscreen);
             settings.performance.pausing.battery = pause_mode_at(pausing, "battery")
                 .unwrap_or(settings.performance.pausing.battery);
+            settings.performance.pausing.presenting = pause_mode_at(pausing, "presenting")
+                .or_else(|| pause_mode_at(pausing, "presentation"))
+                .unwrap_or(settings.performance.pausing.presenting);
             settings.performance.pausing.idle_timeout_ms = u64_any(
                 pausing,
                 &["idle_timeout_ms", "idle_pause_ms", "pause_on_idle_ms", "idle_ms"],
             )
             .unwrap_or(settings.performance.pausing.idle_timeout_ms);
+            settings.performance.pausing.idle_unpause_grace_ms = u64_any(
+                pausing,
+                &["idle_unpause_grace_ms", "idle_resume_grace_ms", "idle_grace_ms"],
+            )
+            .unwrap_or(settings.performance.pausing.idle_unpause_grace_ms);
+            settings.performance.pausing.unpause_delay_ms = u64_any(
+                pausing,
+                &["unpause_delay_ms", "resume_stagger_ms", "resume_delay_ms"],
+            )
+            .map(|v| v.min(10_000))
+            .unwrap_or(settings.performance.pausing.unpause_delay_ms);
             settings.performance.pausing.check_interval_ms = u64_at(pausing, "check_interval_ms")
                 .unwrap_or(settings.performance.pausing.check_interval_ms)
                 .max(100);
+            settings.performance.pausing.battery_pause_percent = u64_any(
+                pausing,
+                &["battery_pause_percent", "battery_percent", "battery_threshold_percent"],
+            )
+            .map(|v| v.min(100) as u8)
+            .unwrap_or(settings.performance.pausing.battery_pause_percent);
+            settings.performance.pausing.gpu = pause_mode_at(pausing, "gpu")
+                .unwrap_or(settings.performance.pausing.gpu);
+            settings.performance.pausing.gpu_pause_percent = u64_any(
+                pausing,
+                &["gpu_pause_percent", "gpu_percent", "gpu_threshold_percent"],
+            )
+            .map(|v| v.clamp(1, 100) as u8)
+            .unwrap_or(settings.performance.pausing.gpu_pause_percent);
         }
 
         if let Some(watcher) = mapping_at(perf, "watcher") {
@@ -386,6 +1064,9 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
             )
                 .unwrap_or(settings.performance.watcher.interval_ms)
                 .max(100);
+            if let Some(globs) = string_list_at(watcher, "ignore_globs") {
+                settings.performance.watcher.ignore_globs = globs;
+            }
         }
 
         if let Some(interactions) = mapping_at(perf, "interactions") {
@@ -399,6 +1080,9 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
                 &["send_click", "pointer_click", "cursor_click"],
             )
                 .unwrap_or(settings.performance.interactions.send_click);
+            settings.performance.interactions.click_through =
+                bool_any(interactions, &["click_through", "pass_through_clicks"])
+                    .unwrap_or(settings.performance.interactions.click_through);
             settings.performance.interactions.poll_interval_ms =
                 u64_any(interactions, &["poll_interval_ms", "sample_interval_ms", "tick_ms"])
                     .unwrap_or(settings.performance.interactions.poll_interval_ms)
@@ -407,6 +1091,16 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
                 f32_any(interactions, &["move_threshold_px", "movement_threshold_px", "threshold_px"])
                     .unwrap_or(settings.performance.interactions.move_threshold_px)
                     .max(0.0);
+            settings.performance.interactions.send_scroll =
+                bool_any(interactions, &["send_scroll", "pointer_scroll", "wheel"])
+                    .unwrap_or(settings.performance.interactions.send_scroll);
+            settings.performance.interactions.scroll_threshold =
+                u64_any(interactions, &["scroll_threshold", "wheel_threshold"])
+                    .map(|v| v.clamp(1, u32::MAX as u64) as u32)
+                    .unwrap_or(settings.performance.interactions.scroll_threshold);
+            settings.performance.interactions.send_keys =
+                bool_any(interactions, &["send_keys", "keyboard", "send_keyboard"])
+                    .unwrap_or(settings.performance.interactions.send_keys);
         }
 
         if let Some(audio) = mapping_at(perf, "audio") {
@@ -434,14 +1128,96 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
                 .unwrap_or(settings.performance.audio.quantize_decimals)
                 .min(4);
         }
+
+        if let Some(snapshot) = mapping_at(perf, "snapshot") {
+            settings.performance.snapshot.max_dimension =
+                u64_any(snapshot, &["max_dimension", "max_size_px", "max_px"])
+                .map(|v| v.min(u32::MAX as u64) as u32)
+                .unwrap_or(settings.performance.snapshot.max_dimension);
+            settings.performance.snapshot.interval_ms =
+                u64_at(snapshot, "interval_ms")
+                .unwrap_or(settings.performance.snapshot.interval_ms)
+                .max(1000);
+            settings.performance.snapshot.warmup_ms =
+                u64_any(snapshot, &["warmup_ms", "snapshot_warmup_ms", "settle_delay_ms"])
+                .unwrap_or(settings.performance.snapshot.warmup_ms);
+            settings.performance.snapshot.format = str_at(snapshot, "format")
+                .and_then(SnapshotFormat::parse)
+                .unwrap_or(settings.performance.snapshot.format);
+            settings.performance.snapshot.jpeg_quality =
+                u64_any(snapshot, &["jpeg_quality", "quality"])
+                .map(|v| v.clamp(1, 100) as u8)
+                .unwrap_or(settings.performance.snapshot.jpeg_quality);
+            settings.performance.snapshot.full_virtual_screen =
+                bool_any(snapshot, &["full_virtual_screen", "virtual_screen"])
+                .unwrap_or(settings.performance.snapshot.full_virtual_screen);
+            settings.performance.snapshot.keep_history =
+                u64_any(snapshot, &["keep_history", "history"])
+                .map(|v| v.min(50) as u32)
+                .unwrap_or(settings.performance.snapshot.keep_history);
+            settings.performance.snapshot.hdr_gamma_correct =
+                bool_any(snapshot, &["hdr_gamma_correct", "hdr_correction"])
+                .unwrap_or(settings.performance.snapshot.hdr_gamma_correct);
+        }
+
+        settings.performance.editable_poll_ms =
+            u64_at(perf, "editable_poll_ms")
+            .unwrap_or(settings.performance.editable_poll_ms)
+            .max(50);
+
+        settings.performance.battery_fps_limit =
+            u64_any(perf, &["battery_fps_limit", "battery_max_fps"])
+            .map(|v| v.min(1000) as u32)
+            .unwrap_or(settings.performance.battery_fps_limit);
     }
 
     if let Some(runtime) = runtime_map {
         settings.runtime.tick_sleep_ms = u64_at(runtime, "tick_sleep_ms")
             .unwrap_or(settings.runtime.tick_sleep_ms)
             .max(1);
+        // Capped so the message pump (hotkey, session changes) stays responsive.
+        settings.runtime.idle_tick_sleep_ms = u64_any(runtime, &["idle_tick_sleep_ms", "idle_sleep_ms"])
+            .map(|v| v.min(500))
+            .unwrap_or(settings.runtime.idle_tick_sleep_ms);
         settings.runtime.reapply_on_pause_change = bool_at(runtime, "reapply_on_pause_change")
             .unwrap_or(settings.runtime.reapply_on_pause_change);
+        settings.runtime.allow_remote_session =
+            bool_any(runtime, &["allow_remote_session", "allow_rdp"])
+            .unwrap_or(settings.runtime.allow_remote_session);
+        settings.runtime.host_strategy = str_any(runtime, &["host_strategy", "desktop_host"])
+            .and_then(HostStrategy::parse)
+            .unwrap_or(settings.runtime.host_strategy);
+        settings.runtime.pause_all_on_lock = bool_any(runtime, &["pause_all_on_lock", "pause_on_lock"])
+            .unwrap_or(settings.runtime.pause_all_on_lock);
+        settings.runtime.manage_static_wallpaper =
+            bool_any(runtime, &["manage_static_wallpaper", "manage_wallpaper"])
+            .unwrap_or(settings.runtime.manage_static_wallpaper);
+        settings.runtime.idle_screensaver = bool_any(runtime, &["idle_screensaver", "screensaver"])
+            .unwrap_or(settings.runtime.idle_screensaver);
+    }
+
+    if let Some(rendering) = rendering_map {
+        settings.rendering.navigation_retries =
+            u64_any(rendering, &["navigation_retries", "nav_retries", "load_retries"])
+            .map(|v| v.min(20) as u32)
+            .unwrap_or(settings.rendering.navigation_retries);
+        if let Some(color) = str_any(rendering, &["fallback_color", "solid_color"]) {
+            settings.rendering.fallback_color = parse_hex_color(color).flatten();
+        }
+        settings.rendering.capture_protection =
+            bool_any(rendering, &["capture_protection", "exclude_from_capture"])
+            .unwrap_or(settings.rendering.capture_protection);
+        settings.rendering.heartbeat_timeout_ms =
+            u64_any(rendering, &["heartbeat_timeout_ms", "hang_timeout_ms"])
+            .map(|v| v.max(1000))
+            .unwrap_or(settings.rendering.heartbeat_timeout_ms);
+        settings.rendering.heartbeat_reload =
+            bool_any(rendering, &["heartbeat_reload", "reload_hung"])
+            .unwrap_or(settings.rendering.heartbeat_reload);
+        settings.rendering.max_preload_wait_ms =
+            u64_any(rendering, &["max_preload_wait_ms", "preload_wait_ms"])
+            .map(|v| v.min(10_000))
+            .unwrap_or(settings.rendering.max_preload_wait_ms);
     }
 
     if let Some(diag) = diagnostics_map {
@@ -455,12 +1231,39 @@ fn parse_settings(root: &Mapping) -> AddonSettings {
             &["log_watcher_reloads", "log_live_reload"],
         )
             .unwrap_or(settings.diagnostics.log_watcher_reloads);
+        settings.diagnostics.recent_log_lines =
+            u64_any(diag, &["recent_log_lines", "log_buffer_lines"])
+            .map(|v| v.min(100_000) as usize)
+            .unwrap_or(settings.diagnostics.recent_log_lines);
     }
 
     if let Some(dev) = development_map {
         settings.development.update_check =
             bool_any(dev, &["update_check", "check_for_updates"]).unwrap_or(settings.development.update_check);
         settings.development.debug = bool_any(dev, &["debug", "debug_mode"]).unwrap_or(settings.development.debug);
+        settings.development.show_error_wallpaper = bool_any(dev, &["show_error_wallpaper", "error_wallpaper"])
+            .unwrap_or(settings.development.show_error_wallpaper);
+        settings.development.hot_css_only = bool_any(dev, &["hot_css_only", "css_hot_reload"])
+            .unwrap_or(settings.development.hot_css_only);
+    }
+
+    if let Some(hotkeys) = hotkeys_map {
+        if let Some(value) = str_any(hotkeys, &["toggle_pause", "pause_toggle"]) {
+            settings.hotkeys.toggle_pause =
+                Hotkey::parse_setting(value).unwrap_or(settings.hotkeys.toggle_pause);
+        }
+    }
+
+    if let Some(bootstrap) = bootstrap_map {
+        settings.bootstrap.auto_start_backend =
+            bool_any(bootstrap, &["auto_start_backend", "start_backend"])
+            .unwrap_or(settings.bootstrap.auto_start_backend);
+        if let Some(path) = str_any(bootstrap, &["backend_path", "backend_exe"])
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            settings.bootstrap.backend_path = Some(PathBuf::from(path));
+        }
     }
 
     settings
@@ -478,6 +1281,10 @@ fn str_at<'a>(map: &'a Mapping, key: &str) -> Option<&'a str> {
     map.get(Value::String(key.to_string()))?.as_str()
 }
 
+fn str_any<'a>(map: &'a Mapping, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|k| str_at(map, k))
+}
+
 fn mapping_at<'a>(map: &'a Mapping, key: &str) -> Option<&'a Mapping> {
     map.get(Value::String(key.to_string()))?.as_mapping()
 }
@@ -525,6 +1332,46 @@ fn string_list_at(map: &Mapping, key: &str) -> Option<Vec<String>> {
     }
 }
 
+/// Bounds of an `"A-B"` monitor range entry, as written; callers reject
+/// reversed ranges (`start > end`).  `None` for anything else.
+pub fn parse_monitor_range(key: &str) -> Option<(usize, usize)> {
+    let (start, end) = key.split_once('-')?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+/// Lowercase image name without a trailing `.exe`, so `Game.EXE`, `game.exe`
+/// and `game` all compare equal.
+pub fn process_image_key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe").unwrap_or(&name).to_string()
+}
+
+fn string_map_at(map: &Mapping, key: &str) -> Option<HashMap<String, String>> {
+    let entries = mapping_at(map, key)?;
+    let mut parsed = HashMap::new();
+
+    for (k, v) in entries.iter() {
+        let monitor_key = match k {
+            Value::String(s) => s.trim().to_string(),
+            Value::Number(n) => n.to_string(),
+            _ => continue,
+        };
+        let Some(id) = v.as_str().map(|s| s.trim()) else {
+            continue;
+        };
+        if monitor_key.is_empty() || id.is_empty() {
+            continue;
+        }
+        parsed.insert(monitor_key, id.to_string());
+    }
+
+    if parsed.is_empty() {
+        None
+    } else {
+        Some(parsed)
+    }
+}
+
 fn section_order_key(section: &str) -> (u8, u32, String) {
     if section == "wallpaper" {
         return (0, 0, section.to_string());
@@ -538,3 +1385,80 @@ fn section_order_key(section: &str) -> (u8, u32, String) {
 
     (2, u32::MAX, section.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkey_parses_modifier_combos() {
+        assert_eq!(
+            Hotkey::parse("Ctrl+Shift+F12"),
+            Some(Hotkey {
+                modifiers: Hotkey::MOD_CONTROL | Hotkey::MOD_SHIFT,
+                vk: 0x7B,
+            })
+        );
+        assert_eq!(
+            Hotkey::parse("ctrl + alt + w"),
+            Some(Hotkey {
+                modifiers: Hotkey::MOD_CONTROL | Hotkey::MOD_ALT,
+                vk: 'W' as u32,
+            })
+        );
+    }
+
+    #[test]
+    fn hotkey_rejects_invalid_combos() {
+        // A bare key would swallow plain typing.
+        assert_eq!(Hotkey::parse("W"), None);
+        assert_eq!(Hotkey::parse("Ctrl+A+B"), None);
+        assert_eq!(Hotkey::parse("Ctrl+F25"), None);
+        assert_eq!(Hotkey::parse("Ctrl+Shift"), None);
+    }
+
+    #[test]
+    fn hotkey_setting_can_be_switched_off() {
+        assert_eq!(Hotkey::parse_setting("off"), Some(None));
+        assert_eq!(Hotkey::parse_setting(" Off "), Some(None));
+        assert_eq!(Hotkey::parse_setting("F25"), None);
+    }
+
+    #[test]
+    fn day_entries_parse() {
+        assert_eq!(parse_day_entry("mon"), Some(vec![Weekday::Mon]));
+        assert_eq!(parse_day_entry(" Friday "), Some(vec![Weekday::Fri]));
+        assert_eq!(
+            parse_day_entry("weekdays"),
+            Some(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ])
+        );
+        assert_eq!(parse_day_entry("weekends"), Some(vec![Weekday::Sat, Weekday::Sun]));
+        assert_eq!(parse_day_entry("someday"), None);
+        assert_eq!(parse_day_entry(""), None);
+    }
+
+    #[test]
+    fn days_filter_sections_by_weekday() {
+        let config = AddonConfig::from_yaml(
+            &serde_yaml::from_str(
+                "wallpaper:\n  wallpaper_id: a\n  days: [weekends, mon, bogus]\n\
+                 wallpaper2:\n  wallpaper_id: b\n",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let scheduled = &config.wallpapers[0];
+        assert_eq!(scheduled.days, vec![Weekday::Sat, Weekday::Sun, Weekday::Mon]);
+        assert!(scheduled.active_on(Weekday::Sun));
+        assert!(scheduled.active_on(Weekday::Mon));
+        assert!(!scheduled.active_on(Weekday::Wed));
+        // No `days` means every day.
+        assert!(config.wallpapers[1].active_on(Weekday::Wed));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod config;
+pub mod validation;
 pub mod yaml;
\ No newline at end of file
@@ -0,0 +1,548 @@
+// ~/src/data_loaders/validation.rs
+
+use std::fmt;
+
+use serde_yaml::{Mapping, Value};
+
+/// A single problem found in `config.yaml`, addressed by its dotted YAML path.
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Expected shape of a config value.  Aliases accepted by `parse_settings`
+/// are listed as separate keys so they validate too.
+#[derive(Clone, Copy)]
+enum Rule {
+    Bool,
+    Int { min: u64, max: u64 },
+    Float { min: f64, max: f64 },
+    Mode,
+    /// `Modifier+Key` combo, or `off` to disable.
+    Hotkey,
+    /// `#rrggbb` / `#rgb` hex color, or `off`.
+    Color,
+    /// Snapshot file format: `bmp` or `jpeg`.
+    SnapshotFormat,
+    /// Desktop host: `auto`, `workerw`, `progman` or `defview`.
+    HostStrategy,
+    /// Section `icon_layer`: `behind` or `over`.
+    IconLayer,
+    /// `monitor_index` list: `*`, `p`, `random`, `N`, `A-B`, or `!N` / `!A-B` / `!p`.
+    MonitorKeys,
+    /// `days`: a weekday / `weekdays` / `weekends`, or a list of them.
+    Days,
+    Text,
+    /// Free-form value (monitor lists, id maps) checked by the parser itself.
+    Any,
+    Section(&'static [(&'static str, Rule)]),
+}
+
+const ANY_INT: Rule = Rule::Int { min: 0, max: u64::MAX };
+
+const PAUSING: &[(&str, Rule)] = &[
+    ("focus", Rule::Mode),
+    ("maximized", Rule::Mode),
+    ("fullscreen", Rule::Mode),
+    ("battery", Rule::Mode),
+    ("presenting", Rule::Mode),
+    ("presentation", Rule::Mode),
+    ("idle_timeout_ms", ANY_INT),
+    ("idle_pause_ms", ANY_INT),
+    ("pause_on_idle_ms", ANY_INT),
+    ("idle_ms", ANY_INT),
+    ("idle_unpause_grace_ms", ANY_INT),
+    ("idle_resume_grace_ms", ANY_INT),
+    ("idle_grace_ms", ANY_INT),
+    ("unpause_delay_ms", ANY_INT),
+    ("resume_stagger_ms", ANY_INT),
+    ("resume_delay_ms", ANY_INT),
+    ("check_interval_ms", ANY_INT),
+    ("battery_pause_percent", Rule::Int { min: 0, max: 100 }),
+    ("battery_percent", Rule::Int { min: 0, max: 100 }),
+    ("battery_threshold_percent", Rule::Int { min: 0, max: 100 }),
+    ("gpu", Rule::Mode),
+    ("gpu_pause_percent", Rule::Int { min: 1, max: 100 }),
+    ("gpu_percent", Rule::Int { min: 1, max: 100 }),
+    ("gpu_threshold_percent", Rule::Int { min: 1, max: 100 }),
+];
+
+const WATCHER: &[(&str, Rule)] = &[
+    ("enabled", Rule::Bool),
+    ("auto_reload", Rule::Bool),
+    ("live_reload", Rule::Bool),
+    ("watch_files", Rule::Bool),
+    ("interval_ms", ANY_INT),
+    ("scan_interval_ms", ANY_INT),
+    ("check_interval_ms", ANY_INT),
+    ("ignore_globs", Rule::Any),
+];
+
+const INTERACTIONS: &[(&str, Rule)] = &[
+    ("send_move", Rule::Bool),
+    ("pointer_move", Rule::Bool),
+    ("cursor_move", Rule::Bool),
+    ("track_pointer", Rule::Bool),
+    ("send_click", Rule::Bool),
+    ("pointer_click", Rule::Bool),
+    ("cursor_click", Rule::Bool),
+    ("click_through", Rule::Bool),
+    ("pass_through_clicks", Rule::Bool),
+    ("poll_interval_ms", ANY_INT),
+    ("sample_interval_ms", ANY_INT),
+    ("tick_ms", ANY_INT),
+    ("move_threshold_px", Rule::Float { min: 0.0, max: f64::MAX }),
+    ("movement_threshold_px", Rule::Float { min: 0.0, max: f64::MAX }),
+    ("threshold_px", Rule::Float { min: 0.0, max: f64::MAX }),
+    ("send_scroll", Rule::Bool),
+    ("pointer_scroll", Rule::Bool),
+    ("wheel", Rule::Bool),
+    ("scroll_threshold", Rule::Int { min: 1, max: 1200 }),
+    ("wheel_threshold", Rule::Int { min: 1, max: 1200 }),
+    ("send_keys", Rule::Bool),
+    ("keyboard", Rule::Bool),
+    ("send_keyboard", Rule::Bool),
+];
+
+const AUDIO: &[(&str, Rule)] = &[
+    ("enabled", Rule::Bool),
+    ("reactive", Rule::Bool),
+    ("reactivity", Rule::Bool),
+    ("sample_interval_ms", ANY_INT),
+    ("update_interval_ms", ANY_INT),
+    ("tick_ms", ANY_INT),
+    ("endpoint_refresh_ms", ANY_INT),
+    ("device_refresh_ms", ANY_INT),
+    ("retry_interval_ms", ANY_INT),
+    ("device_retry_ms", ANY_INT),
+    ("change_threshold", Rule::Float { min: 0.0, max: 1.0 }),
+    ("sensitivity_threshold", Rule::Float { min: 0.0, max: 1.0 }),
+    ("delta_threshold", Rule::Float { min: 0.0, max: 1.0 }),
+    ("quantize_decimals", Rule::Int { min: 0, max: 4 }),
+    ("precision_decimals", Rule::Int { min: 0, max: 4 }),
+];
+
+const SNAPSHOT: &[(&str, Rule)] = &[
+    ("max_dimension", ANY_INT),
+    ("max_size_px", ANY_INT),
+    ("max_px", ANY_INT),
+    ("interval_ms", Rule::Int { min: 1000, max: u64::MAX }),
+    ("warmup_ms", ANY_INT),
+    ("snapshot_warmup_ms", ANY_INT),
+    ("settle_delay_ms", ANY_INT),
+    ("format", Rule::SnapshotFormat),
+    ("jpeg_quality", Rule::Int { min: 1, max: 100 }),
+    ("quality", Rule::Int { min: 1, max: 100 }),
+    ("full_virtual_screen", Rule::Bool),
+    ("virtual_screen", Rule::Bool),
+    ("keep_history", Rule::Int { min: 0, max: 50 }),
+    ("history", Rule::Int { min: 0, max: 50 }),
+    ("hdr_gamma_correct", Rule::Bool),
+    ("hdr_correction", Rule::Bool),
+];
+
+const PERFORMANCE: &[(&str, Rule)] = &[
+    ("pausing", Rule::Section(PAUSING)),
+    ("watcher", Rule::Section(WATCHER)),
+    ("interactions", Rule::Section(INTERACTIONS)),
+    ("audio", Rule::Section(AUDIO)),
+    ("snapshot", Rule::Section(SNAPSHOT)),
+    ("editable_poll_ms", Rule::Int { min: 50, max: u64::MAX }),
+    ("battery_fps_limit", Rule::Int { min: 0, max: 1000 }),
+    ("battery_max_fps", Rule::Int { min: 0, max: 1000 }),
+];
+
+const RUNTIME: &[(&str, Rule)] = &[
+    ("tick_sleep_ms", ANY_INT),
+    ("idle_tick_sleep_ms", Rule::Int { min: 0, max: 500 }),
+    ("idle_sleep_ms", Rule::Int { min: 0, max: 500 }),
+    ("reapply_on_pause_change", Rule::Bool),
+    ("allow_remote_session", Rule::Bool),
+    ("allow_rdp", Rule::Bool),
+    ("host_strategy", Rule::HostStrategy),
+    ("desktop_host", Rule::HostStrategy),
+    ("pause_all_on_lock", Rule::Bool),
+    ("pause_on_lock", Rule::Bool),
+    ("manage_static_wallpaper", Rule::Bool),
+    ("manage_wallpaper", Rule::Bool),
+    ("idle_screensaver", Rule::Bool),
+    ("screensaver", Rule::Bool),
+];
+
+const RENDERING: &[(&str, Rule)] = &[
+    ("navigation_retries", Rule::Int { min: 0, max: 20 }),
+    ("nav_retries", Rule::Int { min: 0, max: 20 }),
+    ("load_retries", Rule::Int { min: 0, max: 20 }),
+    ("fallback_color", Rule::Color),
+    ("solid_color", Rule::Color),
+    ("capture_protection", Rule::Bool),
+    ("exclude_from_capture", Rule::Bool),
+    ("heartbeat_timeout_ms", Rule::Int { min: 1000, max: u64::MAX }),
+    ("hang_timeout_ms", Rule::Int { min: 1000, max: u64::MAX }),
+    ("heartbeat_reload", Rule::Bool),
+    ("reload_hung", Rule::Bool),
+    ("max_preload_wait_ms", Rule::Int { min: 0, max: 10_000 }),
+    ("preload_wait_ms", Rule::Int { min: 0, max: 10_000 }),
+];
+
+const DIAGNOSTICS: &[(&str, Rule)] = &[
+    ("log_pause_state_changes", Rule::Bool),
+    ("log_pause_changes", Rule::Bool),
+    ("log_watcher_reloads", Rule::Bool),
+    ("log_live_reload", Rule::Bool),
+    ("recent_log_lines", Rule::Int { min: 0, max: 100_000 }),
+    ("log_buffer_lines", Rule::Int { min: 0, max: 100_000 }),
+];
+
+const DEVELOPMENT: &[(&str, Rule)] = &[
+    ("update_check", Rule::Bool),
+    ("check_for_updates", Rule::Bool),
+    ("debug", Rule::Bool),
+    ("debug_mode", Rule::Bool),
+    ("log_level", Rule::Text),
+    ("show_error_wallpaper", Rule::Bool),
+    ("error_wallpaper", Rule::Bool),
+    ("hot_css_only", Rule::Bool),
+    ("css_hot_reload", Rule::Bool),
+];
+
+const HOTKEYS: &[(&str, Rule)] = &[
+    ("toggle_pause", Rule::Hotkey),
+    ("pause_toggle", Rule::Hotkey),
+];
+
+const BOOTSTRAP: &[(&str, Rule)] = &[
+    ("auto_start_backend", Rule::Bool),
+    ("start_backend", Rule::Bool),
+    ("backend_path", Rule::Text),
+    ("backend_exe", Rule::Text),
+];
+
+const SETTINGS: &[(&str, Rule)] = &[
+    ("performance", Rule::Section(PERFORMANCE)),
+    ("runtime", Rule::Section(RUNTIME)),
+    ("rendering", Rule::Section(RENDERING)),
+    ("diagnostics", Rule::Section(DIAGNOSTICS)),
+    ("development", Rule::Section(DEVELOPMENT)),
+    ("hotkeys", Rule::Section(HOTKEYS)),
+    ("bootstrap", Rule::Section(BOOTSTRAP)),
+];
+
+const SECTION_PAUSING: &[(&str, Rule)] = &[
+    ("focus", Rule::Mode),
+    ("maximized", Rule::Mode),
+    ("fullscreen", Rule::Mode),
+    ("battery", Rule::Mode),
+    ("presenting", Rule::Mode),
+    ("gpu", Rule::Mode),
+];
+
+const WALLPAPER_SECTION: &[(&str, Rule)] = &[
+    ("enabled", Rule::Bool),
+    ("wallpaper_id", Rule::Text),
+    ("per_monitor", Rule::Any),
+    ("monitor_index", Rule::MonitorKeys),
+    ("random_seed", ANY_INT),
+    ("days", Rule::Days),
+    ("mode", Rule::Text),
+    ("z_index", Rule::Text),
+    ("z_index_per_monitor", Rule::Any),
+    ("click_through", Rule::Bool),
+    ("opacity", Rule::Float { min: 0.0, max: 1.0 }),
+    ("volume", Rule::Float { min: 0.0, max: 1.0 }),
+    ("icon_layer", Rule::IconLayer),
+    ("stack", Rule::Bool),
+    ("pause_focus", Rule::Mode),
+    ("pause_maximized", Rule::Mode),
+    ("pause_fullscreen", Rule::Mode),
+    ("pause_battery", Rule::Mode),
+    ("pause_presenting", Rule::Mode),
+    ("pause_gpu", Rule::Mode),
+    // A process name or a list of them.
+    ("pause_when_process", Rule::Any),
+    // Accepts either a pause mode or a legacy bool.
+    ("pause_when_presenting", Rule::Any),
+    ("pause_on_focus", Rule::Bool),
+    ("pause_on_maximized", Rule::Bool),
+    ("pause_on_fullscreen", Rule::Bool),
+    ("pause_fullscreen_all_monitors", Rule::Bool),
+    ("pausing", Rule::Section(SECTION_PAUSING)),
+];
+
+/// Check the whole document: unknown keys, wrong types, out-of-range numbers
+/// and unparseable pause modes.  An empty result means the config is valid.
+pub fn validate(root: &Value) -> Vec<ConfigError> {
+    let mut errors = Vec::new();
+    let Some(map) = root.as_mapping() else {
+        errors.push(ConfigError {
+            path: String::new(),
+            message: "top level must be a mapping".to_string(),
+        });
+        return errors;
+    };
+
+    for (key, value) in map {
+        let Some(key) = key.as_str() else {
+            errors.push(non_string_key(""));
+            continue;
+        };
+
+        match key {
+            "settings" => check_rule(key, value, Rule::Section(SETTINGS), &mut errors),
+            "update_check" | "debug" => check_rule(key, value, Rule::Bool, &mut errors),
+            "wallpapers" => check_wallpapers_map(key, value, &mut errors),
+            _ if key.starts_with("wallpaper") => {
+                check_rule(key, value, Rule::Section(WALLPAPER_SECTION), &mut errors)
+            }
+            _ => errors.push(unknown_key(key)),
+        }
+    }
+
+    errors
+}
+
+fn check_wallpapers_map(path: &str, value: &Value, errors: &mut Vec<ConfigError>) {
+    let Some(map) = value.as_mapping() else {
+        errors.push(type_error(path, "a mapping of wallpaper sections"));
+        return;
+    };
+
+    for (key, section) in map {
+        let Some(key) = key.as_str() else {
+            errors.push(non_string_key(path));
+            continue;
+        };
+        let child = format!("{path}.{key}");
+        if !key.starts_with("wallpaper") {
+            errors.push(unknown_key(&child));
+            continue;
+        }
+        check_rule(&child, section, Rule::Section(WALLPAPER_SECTION), errors);
+    }
+}
+
+fn check_rule(path: &str, value: &Value, rule: Rule, errors: &mut Vec<ConfigError>) {
+    match rule {
+        Rule::Bool => {
+            if !value.is_bool() {
+                errors.push(type_error(path, "true or false"));
+            }
+        }
+        Rule::Int { min, max } => match value.as_u64() {
+            Some(v) if v < min || v > max => errors.push(range_error(path, &v.to_string(), min, max)),
+            Some(_) => {}
+            None => errors.push(type_error(path, "a non-negative integer")),
+        },
+        Rule::Float { min, max } => match value.as_f64() {
+            Some(v) if v < min || v > max => errors.push(range_error(path, &v.to_string(), min, max)),
+            Some(_) => {}
+            None => errors.push(type_error(path, "a number")),
+        },
+        Rule::Mode => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::PauseMode::parse(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "off | per-monitor | all-monitors"));
+            }
+        }
+        Rule::Hotkey => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::Hotkey::parse_setting(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "a hotkey like \"Ctrl+Alt+W\" (or \"off\")"));
+            }
+        }
+        Rule::Color => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::parse_hex_color(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "a hex color like \"#101014\" (or \"off\")"));
+            }
+        }
+        Rule::SnapshotFormat => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::SnapshotFormat::parse(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "bmp | jpeg"));
+            }
+        }
+        Rule::HostStrategy => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::HostStrategy::parse(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "auto | workerw | progman | defview"));
+            }
+        }
+        Rule::IconLayer => {
+            let valid = value
+                .as_str()
+                .map(|v| super::config::IconLayer::parse(v).is_some())
+                .unwrap_or(false);
+            if !valid {
+                errors.push(type_error(path, "behind | over"));
+            }
+        }
+        Rule::MonitorKeys => check_monitor_keys(path, value, errors),
+        Rule::Days => check_days(path, value, errors),
+        Rule::Text => {
+            if !value.is_string() {
+                errors.push(type_error(path, "a string"));
+            }
+        }
+        Rule::Any => {}
+        Rule::Section(fields) => check_section(path, value, fields, errors),
+    }
+}
+
+fn check_monitor_keys(path: &str, value: &Value, errors: &mut Vec<ConfigError>) {
+    let Some(list) = value.as_sequence() else {
+        errors.push(type_error(path, "a list of monitor keys"));
+        return;
+    };
+
+    let mut has_wildcard = false;
+    let mut has_exclusion = false;
+    for (i, entry) in list.iter().enumerate() {
+        let child = format!("{path}[{i}]");
+        let Some(key) = entry.as_str().map(str::trim) else {
+            errors.push(type_error(&child, "a quoted monitor key such as \"0\""));
+            continue;
+        };
+
+        let valid = match key.strip_prefix('!') {
+            Some(excluded) => {
+                has_exclusion = true;
+                excluded.eq_ignore_ascii_case("p") || is_monitor_number_or_range(excluded)
+            }
+            None => {
+                has_wildcard |= key == "*";
+                key == "*"
+                    || key.eq_ignore_ascii_case("p")
+                    || key.eq_ignore_ascii_case("random")
+                    || is_monitor_number_or_range(key)
+            }
+        };
+        if !valid {
+            errors.push(type_error(
+                &child,
+                "\"*\", \"p\", \"random\", a monitor number, an ascending range like \"1-3\", or \"!N\" / \"!p\" to drop a monitor from \"*\"",
+            ));
+        }
+    }
+
+    if has_exclusion && !has_wildcard {
+        errors.push(ConfigError {
+            path: path.to_string(),
+            message: "\"!N\" only removes monitors from \"*\" (explicit numbers and \"p\" always win); add \"*\" to the list".to_string(),
+        });
+    }
+}
+
+fn is_monitor_number_or_range(key: &str) -> bool {
+    key.parse::<usize>().is_ok()
+        || super::config::parse_monitor_range(key).is_some_and(|(start, end)| start <= end)
+}
+
+fn check_days(path: &str, value: &Value, errors: &mut Vec<ConfigError>) {
+    const EXPECTED: &str = "a weekday (\"mon\" .. \"sun\"), \"weekdays\", \"weekends\" or \"daily\"";
+
+    let entries: Vec<(String, &Value)> = match value {
+        Value::Sequence(list) => list
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (format!("{path}[{i}]"), entry))
+            .collect(),
+        other => vec![(path.to_string(), other)],
+    };
+
+    for (child, entry) in entries {
+        let valid = entry
+            .as_str()
+            .map(|v| super::config::parse_day_entry(v).is_some())
+            .unwrap_or(false);
+        if !valid {
+            errors.push(type_error(&child, EXPECTED));
+        }
+    }
+}
+
+fn check_section(
+    path: &str,
+    value: &Value,
+    fields: &[(&'static str, Rule)],
+    errors: &mut Vec<ConfigError>,
+) {
+    let Some(map) = value.as_mapping() else {
+        errors.push(type_error(path, "a mapping"));
+        return;
+    };
+    check_mapping(path, map, fields, errors);
+}
+
+fn check_mapping(
+    path: &str,
+    map: &Mapping,
+    fields: &[(&'static str, Rule)],
+    errors: &mut Vec<ConfigError>,
+) {
+    for (key, value) in map {
+        let Some(key) = key.as_str() else {
+            errors.push(non_string_key(path));
+            continue;
+        };
+        let child = format!("{path}.{key}");
+        match fields.iter().find(|(name, _)| *name == key) {
+            Some((_, rule)) => check_rule(&child, value, *rule, errors),
+            None => errors.push(unknown_key(&child)),
+        }
+    }
+}
+
+fn unknown_key(path: &str) -> ConfigError {
+    ConfigError {
+        path: path.to_string(),
+        message: "unknown key".to_string(),
+    }
+}
+
+fn non_string_key(path: &str) -> ConfigError {
+    ConfigError {
+        path: path.to_string(),
+        message: "contains a non-string key".to_string(),
+    }
+}
+
+fn type_error(path: &str, expected: &str) -> ConfigError {
+    ConfigError {
+        path: path.to_string(),
+        message: format!("expected {expected}"),
+    }
+}
+
+fn range_error<T: fmt::Display>(path: &str, value: &str, min: T, max: T) -> ConfigError {
+    ConfigError {
+        path: path.to_string(),
+        message: format!("{value} is out of range ({min}..={max})"),
+    }
+}
@@ -1,11 +1,12 @@
 use std::{
+    collections::VecDeque,
     fs::OpenOptions,
     io::Write,
     path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{self, Sender},
-        OnceLock,
+        Mutex, OnceLock,
     },
     thread,
 };
@@ -20,6 +21,12 @@ static ENABLED: AtomicBool = AtomicBool::new(false);
 static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
 static LOG_TX: OnceLock<Sender<String>> = OnceLock::new();
 static LOG_LEVEL: OnceLock<String> = OnceLock::new();
+/// Set by `pin_level` (`--log-level`): `LOG_LEVEL` alone decides what is
+/// logged and the config's `debug` flag is ignored.
+static LEVEL_PINNED: AtomicBool = AtomicBool::new(false);
+/// Most recent log lines, newest last, for `get_recent_logs`.
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static RECENT_CAPACITY: AtomicUsize = AtomicUsize::new(500);
 
 /* =========================
    PUBLIC API
@@ -49,18 +56,72 @@ pub fn init(debug: bool, level: &str) {
     });
 }
 
+/// Levels from most to least verbose.
+const LEVELS: &[&str] = &["info", "warn", "error"];
+
+/// Normalise a `--log-level` value; `None` for anything unrecognised.
+pub fn parse_level(level: &str) -> Option<&'static str> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "trace" | "debug" | "info" => Some("info"),
+        "warn" | "warning" => Some("warn"),
+        "error" => Some("error"),
+        _ => None,
+    }
+}
+
+/// Keep the level passed to `init` for the rest of the session, whatever
+/// `set_debug` is later told by the config.
+pub fn pin_level() {
+    LEVEL_PINNED.store(true, Ordering::Relaxed);
+}
+
+fn level_rank(level: &str) -> usize {
+    LEVELS
+        .iter()
+        .position(|known| known.eq_ignore_ascii_case(level))
+        .unwrap_or(0)
+}
+
 #[inline]
 pub fn should_log(level: &str) -> bool {
+    if LEVEL_PINNED.load(Ordering::Relaxed) {
+        let minimum = LOG_LEVEL.get().map_or("info", String::as_str);
+        return level_rank(level) >= level_rank(minimum);
+    }
     if !ENABLED.load(Ordering::Relaxed) {
         return level == "WARN" || level == "ERROR";
     }
     true
 }
 
+/// Switch info-level logging on or off; called at startup and after every
+/// config reload, so `debug` takes effect without a restart.  No-op once
+/// `pin_level` has been called.
 pub fn set_debug(debug: bool) {
+    if LEVEL_PINNED.load(Ordering::Relaxed) {
+        return;
+    }
     ENABLED.store(debug, Ordering::Relaxed);
 }
 
+/// Resize the in-memory tail kept for `recent_lines` (0 disables it).
+pub fn set_recent_capacity(capacity: usize) {
+    RECENT_CAPACITY.store(capacity, Ordering::Relaxed);
+    if let Ok(mut recent) = RECENT.lock() {
+        let excess = recent.len().saturating_sub(capacity);
+        recent.drain(..excess);
+    }
+}
+
+/// Up to `limit` of the most recently logged lines, oldest first.
+pub fn recent_lines(limit: usize) -> Vec<String> {
+    let Ok(recent) = RECENT.lock() else {
+        return Vec::new();
+    };
+    let skip = recent.len().saturating_sub(limit);
+    recent.iter().skip(skip).cloned().collect()
+}
+
 /* =========================
    INTERNAL
    ========================= */
@@ -69,7 +130,22 @@ pub fn set_debug(debug: bool) {
 pub fn enqueue(level: &str, msg: String) {
     if let Some(tx) = LOG_TX.get() {
         let ts = timestamp();
-        let _ = tx.send(format!("{ts} [{level}] {msg}"));
+        let line = format!("{ts} [{level}] {msg}");
+        remember(&line);
+        let _ = tx.send(line);
+    }
+}
+
+fn remember(line: &str) {
+    let capacity = RECENT_CAPACITY.load(Ordering::Relaxed);
+    if capacity == 0 {
+        return;
+    }
+    if let Ok(mut recent) = RECENT.lock() {
+        while recent.len() >= capacity {
+            recent.pop_front();
+        }
+        recent.push_back(line.to_string());
     }
 }
 
@@ -97,10 +173,12 @@ macro_rules! info {
 #[macro_export]
 macro_rules! warn {
     ($($arg:tt)*) => {{
-        $crate::logging::enqueue(
-            "WARN",
-            format!($($arg)*)
-        );
+        if $crate::logging::should_log("WARN") {
+            $crate::logging::enqueue(
+                "WARN",
+                format!($($arg)*)
+            );
+        }
     }};
 }
 
@@ -125,3 +203,19 @@ fn log_path() -> &'static PathBuf {
         logs_dir.join("sentinel.wallpaper.log")
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_debug_switches_info_logging() {
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        set_debug(true);
+        assert!(should_log("INFO"));
+        set_debug(false);
+        assert!(!should_log("INFO"));
+        assert!(should_log("WARN") && should_log("ERROR"));
+        ENABLED.store(enabled, Ordering::Relaxed);
+    }
+}
@@ -1,6 +1,8 @@
 #![windows_subsystem = "windows"]
 
+mod audio;
 mod bootstrap;
+mod commands;
 mod data_loaders;
 mod ipc_connector;
 mod logging;
@@ -15,32 +17,385 @@ use std::{
 	thread,
 	time::{Duration, Instant, SystemTime},
 };
+use chrono::{Datelike, Local};
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
 use windows::Win32::UI::HiDpi::{
 	SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
 };
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+	RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_NOREPEAT,
+};
+use windows::core::{w, PCWSTR};
 use windows::Win32::UI::WindowsAndMessaging::{
-	DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE, WM_QUIT,
+	MessageBoxW, IDYES, MB_DEFBUTTON2, MB_ICONQUESTION, MB_YESNO,
+	DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE, WM_HOTKEY, WM_QUIT,
+	WM_DISPLAYCHANGE, WM_SETTINGCHANGE, WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
 };
 
+use serde_json::Value;
+
 use crate::{
-	data_loaders::config::{AddonConfig, AddonSettings},
-	utility::{addon_root_dir, sentinel_addons_dir},
-	wallpaper_engine::WallpaperRuntime,
+	commands::AddonCommand,
+	data_loaders::config::{
+		config_content_hash, write_config_error_log, AddonConfig, AddonSettings, ConfigError, Hotkey,
+	},
+	utility::{addon_root_dir, sentinel_addons_dir, to_wstring, GlobSet},
+	wallpaper_engine::{ApplyReport, WallpaperRuntime},
 };
 
 pub const ADDON_NAME: &str = "wallpaper";
 pub const DEBUG_NAME: &str = "WALLPAPER";
 
+const TOGGLE_PAUSE_HOTKEY_ID: i32 = 1;
+
 fn addon_config_path() -> std::path::PathBuf {
+	addon_file_path("config.yaml")
+}
+
+fn addon_file_path(name: &str) -> std::path::PathBuf {
 	if let Some(root) = addon_root_dir() {
-		return root.join("config.yaml");
+		return root.join(name);
 	}
 
 	if let Some(addons_dir) = sentinel_addons_dir() {
-		return addons_dir.join(ADDON_NAME).join("config.yaml");
+		return addons_dir.join(ADDON_NAME).join(name);
+	}
+
+	std::path::PathBuf::from(name)
+}
+
+/// `--version`: print the installed addon.json version and the WebView2
+/// runtime version to the launching console.  Runs before bootstrap, so it
+/// never scaffolds, self-installs or relaunches.
+fn print_versions() {
+	// A windows-subsystem exe has no console; borrow the parent's if any.
+	let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+	let addon_json = addon_file_path("addon.json");
+	let addon_version = fs::read_to_string(&addon_json)
+		.ok()
+		.and_then(|content| serde_json::from_str::<Value>(&content).ok())
+		.and_then(|json| json.get("version").and_then(Value::as_str).map(str::to_string))
+		.unwrap_or_else(|| format!("unknown ({} not found)", addon_json.display()));
+	let webview2_version = wallpaper_engine::webview2_runtime_version()
+		.unwrap_or_else(|e| format!("unavailable ({e})"));
+
+	println!("sentinel-wallpaper {}", env!("CARGO_PKG_VERSION"));
+	println!("addon.json version: {addon_version}");
+	println!("WebView2 runtime:   {webview2_version}");
+}
+
+/// `install` / `uninstall` subcommands.  Both replace the normal runtime and
+/// ask for confirmation in a message box unless `--yes` is passed.  Returns
+/// the process exit code.
+fn run_subcommand(subcommand: &str) -> i32 {
+	let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+	let assume_yes = std::env::args().skip(1).any(|arg| arg == "--yes" || arg == "-y");
+
+	let prompt = match subcommand {
+		"install" => "Install the wallpaper addon into ~/.Sentinel/Addons/wallpaper?",
+		_ => "Stop the wallpaper addon and remove ~/.Sentinel/Addons/wallpaper and the sentinel.default asset?",
+	};
+	if !assume_yes && !confirm(prompt) {
+		println!("Aborted (pass --yes to skip this prompt)");
+		return 1;
+	}
+
+	if subcommand == "install" {
+		return match bootstrap::install_addon() {
+			Ok(dir) => {
+				info!("[{}][CLI] Installed into {}", DEBUG_NAME, dir.display());
+				println!("Installed into {}", dir.display());
+				0
+			}
+			Err(e) => {
+				error!("[{}][CLI] Install failed: {}", DEBUG_NAME, e);
+				println!("Install failed: {e}");
+				1
+			}
+		};
 	}
 
-	std::path::PathBuf::from("config.yaml")
+	bootstrap::stop_running_instances();
+	// Leave the last frame on the desktop rather than a blank WorkerW.
+	if !wallpaper_engine::apply_latest_snapshot_wallpaper() {
+		println!("No saved snapshot found; the desktop wallpaper is left as is");
+	}
+	match bootstrap::remove_installed_files() {
+		Ok(removed) => {
+			for path in &removed {
+				info!("[{}][CLI] Removed {}", DEBUG_NAME, path.display());
+				println!("Removed {}", path.display());
+			}
+			0
+		}
+		Err(e) => {
+			error!("[{}][CLI] Uninstall failed: {}", DEBUG_NAME, e);
+			println!("Uninstall failed: {e}");
+			1
+		}
+	}
+}
+
+/// Ask a yes/no question in a message box; closing it counts as no.  The
+/// console can't be used: shells don't wait for a windows-subsystem exe, so
+/// a `[y/N]` prompt would race the shell's own prompt for input.
+fn confirm(prompt: &str) -> bool {
+	let text = to_wstring(prompt);
+	let answer = unsafe {
+		MessageBoxW(
+			None,
+			PCWSTR(text.as_ptr()),
+			w!("Sentinel Wallpaper"),
+			MB_YESNO | MB_ICONQUESTION | MB_DEFBUTTON2,
+		)
+	};
+	answer == IDYES
+}
+
+/// `--log-level <level>` from the command line, as written.
+fn log_level_arg() -> Option<String> {
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		if arg == "--log-level" {
+			return Some(args.next().unwrap_or_default());
+		}
+		if let Some(value) = arg.strip_prefix("--log-level=") {
+			return Some(value.to_string());
+		}
+	}
+	None
+}
+
+/// `--config <path>` from the command line.  `Err` when the flag is present
+/// but the path is missing or unreadable.
+fn config_path_override() -> Result<Option<std::path::PathBuf>, String> {
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		let value = if arg == "--config" {
+			args.next().ok_or("--config requires a path")?
+		} else if let Some(value) = arg.strip_prefix("--config=") {
+			value.to_string()
+		} else {
+			continue;
+		};
+
+		let path = std::path::PathBuf::from(value);
+		fs::File::open(&path).map_err(|e| format!("cannot read config {}: {}", path.display(), e))?;
+		return Ok(Some(path));
+	}
+	Ok(None)
+}
+
+/// Strictly load the config, logging each validation error and mirroring them
+/// to `config.errors.log`.  `Err` means the caller should keep what it has.
+fn load_validated_config(config_path: &Path) -> Result<AddonConfig, Vec<ConfigError>> {
+	match AddonConfig::load_validated(config_path) {
+		Ok(config) => {
+			write_config_error_log(config_path, &[]);
+			Ok(config)
+		}
+		Err(errors) => {
+			for e in &errors {
+				error!("[{}][CONFIG] {}", DEBUG_NAME, e);
+			}
+			write_config_error_log(config_path, &errors);
+			Err(errors)
+		}
+	}
+}
+
+/// Swap in a freshly loaded config for the `reapply` / `reload_config`
+/// commands and the config watcher: re-register the hotkey if it changed, rebuild the hosts (all of
+/// them with `rebuild_all`, else only those whose wallpaper changed) and
+/// restore the pause state.
+fn apply_new_config(
+	runtime: &mut WallpaperRuntime,
+	config: &mut AddonConfig,
+	new_config: AddonConfig,
+	rebuild_all: bool,
+) -> ApplyReport {
+	if new_config.settings.hotkeys.toggle_pause != config.settings.hotkeys.toggle_pause {
+		unregister_toggle_pause_hotkey();
+		register_toggle_pause_hotkey(new_config.settings.hotkeys.toggle_pause);
+	}
+	*config = new_config;
+	logging::set_debug(config.debug);
+	logging::set_recent_capacity(config.settings.diagnostics.recent_log_lines);
+
+	let all_paused_before = runtime.hosted_all_paused();
+	let report = if rebuild_all {
+		apply_config(runtime, config)
+	} else {
+		apply_config_changes(runtime, config)
+	};
+	if runtime.has_registry_snapshot() {
+		let _ = runtime.sync_pause_state_now(all_paused_before);
+	}
+	report
+}
+
+/// `runtime.apply` plus a one-line summary.  When errors left nothing hosted
+/// (no monitors, no WebView2, Remote Desktop, every section failing) the
+/// saved snapshot goes on the desktop instead of leaving it blank.
+fn apply_config(runtime: &mut WallpaperRuntime, config: &AddonConfig) -> ApplyReport {
+	let report = runtime.apply(config);
+	report_applied(runtime, report)
+}
+
+/// `apply_config` for an edited or rescheduled config: hosts that would come
+/// back identical are kept instead of rebuilt (`runtime.apply_changes`).
+fn apply_config_changes(runtime: &mut WallpaperRuntime, config: &AddonConfig) -> ApplyReport {
+	let report = runtime.apply_changes(config);
+	report_applied(runtime, report)
+}
+
+fn report_applied(runtime: &mut WallpaperRuntime, report: ApplyReport) -> ApplyReport {
+	warn!(
+		"[{}][APPLY] hosted={} skipped={} errors={}",
+		DEBUG_NAME,
+		report.hosted,
+		report.skipped,
+		report.errors.len()
+	);
+	if report.hosted == 0 && !report.errors.is_empty() {
+		runtime.apply_snapshot_as_wallpaper();
+	}
+	report
+}
+
+fn applied_config_hash_path() -> std::path::PathBuf {
+	addon_file_path("runtime").join("last_config.hash")
+}
+
+/// The config hash persisted by the last `record_applied_config`, possibly
+/// from a previous run.
+fn stored_config_hash() -> Option<u64> {
+	let text = fs::read_to_string(applied_config_hash_path()).ok()?;
+	u64::from_str_radix(text.trim(), 16).ok()
+}
+
+/// Hash the config that was just applied and persist it to
+/// `runtime/last_config.hash`, so later watcher hits can tell a real edit
+/// from a rewrite with the same content.
+fn record_applied_config(config_path: &Path) -> Option<u64> {
+	let hash = config_content_hash(config_path)?;
+	let path = applied_config_hash_path();
+	if let Some(dir) = path.parent() {
+		let _ = fs::create_dir_all(dir);
+	}
+	if let Err(e) = fs::write(&path, format!("{hash:016x}\n")) {
+		warn!("[{}][CONFIG] Failed to write {}: {}", DEBUG_NAME, path.display(), e);
+	}
+	Some(hash)
+}
+
+/// Execute one IPC command against the runtime.  The `Ok` value is sent back
+/// to the backend as the command's result data.
+fn handle_command(
+	runtime: &mut WallpaperRuntime,
+	config: &mut AddonConfig,
+	config_path: &Path,
+	command: &AddonCommand,
+) -> Result<Value, String> {
+	match command.cmd.as_str() {
+		"set_section_paused" => {
+			let section = command.str_arg("section").ok_or("missing 'section'")?;
+			let paused = command.bool_arg("paused").ok_or("missing 'paused'")?;
+			let hosts = runtime.set_section_paused(section, paused)?;
+			Ok(serde_json::json!({ "section": section, "paused": paused, "hosts": hosts }))
+		}
+		"clear_section_override" => {
+			let section = command.str_arg("section").ok_or("missing 'section'")?;
+			let cleared = runtime.clear_section_override(section);
+			Ok(serde_json::json!({ "section": section, "cleared": cleared }))
+		}
+		"get_status" => Ok(runtime.status()),
+		"list_monitors" => Ok(runtime.list_monitors()),
+		"get_recent_logs" => {
+			let limit = command
+				.u64_arg("limit")
+				.map(|v| v as usize)
+				.unwrap_or(config.settings.diagnostics.recent_log_lines);
+			Ok(serde_json::json!({ "lines": logging::recent_lines(limit) }))
+		}
+		"reload_all" => {
+			let hosts = runtime.reload_all_wallpapers();
+			Ok(serde_json::json!({ "hosts": hosts }))
+		}
+		"reapply" => {
+			let new_config = load_validated_config(config_path).map_err(|_| {
+				"config.yaml has errors (see config.errors.log); keeping previous config".to_string()
+			})?;
+			let report = apply_new_config(runtime, config, new_config, true);
+			Ok(serde_json::json!({
+				"hosts": runtime.hosted_count(),
+				"skipped": report.skipped,
+				"errors": report.errors,
+			}))
+		}
+		// Like `reapply`, but a config that fails to parse is a normal
+		// outcome (`parsed: false`) rather than a command error, so the
+		// options UI can show the problems right after its save.
+		"reload_config" => match load_validated_config(config_path) {
+			Ok(new_config) => {
+				let report = apply_new_config(runtime, config, new_config, false);
+				Ok(serde_json::json!({
+					"parsed": true,
+					"hosts": runtime.hosted_count(),
+					"skipped": report.skipped,
+					"errors": report.errors,
+				}))
+			}
+			Err(errors) => Ok(serde_json::json!({
+				"parsed": false,
+				"config_errors": errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+			})),
+		},
+		"validate_manifest" => {
+			let target = command
+				.str_arg("wallpaper_id")
+				.or_else(|| command.str_arg("path"))
+				.ok_or("missing 'wallpaper_id' or 'path'")?;
+			wallpaper_engine::validate_manifest(target)
+		}
+		"preview_wallpaper" => {
+			let wallpaper_id = command.str_arg("wallpaper_id").ok_or("missing 'wallpaper_id'")?;
+			let monitor_index = command.u64_arg("monitor_index").unwrap_or(0) as usize;
+			runtime.preview_wallpaper(wallpaper_id, monitor_index)
+		}
+		"clear_preview" => {
+			if !runtime.has_preview() {
+				return Ok(serde_json::json!({ "cleared": false, "hosts": runtime.hosted_count() }));
+			}
+			let all_paused_before = runtime.hosted_all_paused();
+			apply_config(runtime, config);
+			if runtime.has_registry_snapshot() {
+				let _ = runtime.sync_pause_state_now(all_paused_before);
+			}
+			Ok(serde_json::json!({ "cleared": true, "hosts": runtime.hosted_count() }))
+		}
+		other => Err(format!("Unknown command '{other}'")),
+	}
+}
+
+/// Register the toggle-pause hotkey on this thread's message queue, so
+/// `WM_HOTKEY` arrives in the main `PeekMessageW` loop.
+fn register_toggle_pause_hotkey(hotkey: Option<Hotkey>) {
+	let Some(hotkey) = hotkey else {
+		return;
+	};
+	let modifiers = HOT_KEY_MODIFIERS(hotkey.modifiers) | MOD_NOREPEAT;
+	if let Err(e) = unsafe { RegisterHotKey(None, TOGGLE_PAUSE_HOTKEY_ID, modifiers, hotkey.vk) } {
+		warn!(
+			"[{}][HOTKEY] Failed to register toggle-pause hotkey (already in use?): {:?}",
+			DEBUG_NAME, e
+		);
+	}
+}
+
+fn unregister_toggle_pause_hotkey() {
+	let _ = unsafe { UnregisterHotKey(None, TOGGLE_PAUSE_HOTKEY_ID) };
 }
 
 fn enable_per_monitor_dpi_awareness() {
@@ -54,7 +409,18 @@ fn enable_per_monitor_dpi_awareness() {
 	}
 }
 
-fn should_ignore_asset_reload_path(path: &Path) -> bool {
+/// Whether `path`, somewhere below the asset folder `root`, is matched by
+/// the configured `watcher.ignore_globs`.
+fn matches_ignore_globs(path: &Path, root: &Path, ignore_globs: &GlobSet) -> bool {
+	path.strip_prefix(root)
+		.is_ok_and(|relative| ignore_globs.is_match(relative))
+}
+
+fn should_ignore_asset_reload_path(path: &Path, root: &Path, ignore_globs: &GlobSet) -> bool {
+	if matches_ignore_globs(path, root, ignore_globs) {
+		return true;
+	}
+
 	let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
 		return false;
 	};
@@ -82,21 +448,24 @@ fn should_ignore_asset_reload_path(path: &Path) -> bool {
 	false
 }
 
-fn newest_file_modified_recursive(dir: &Path) -> Option<SystemTime> {
+fn newest_file_modified_recursive(dir: &Path, root: &Path, ignore_globs: &GlobSet) -> Option<SystemTime> {
 	let mut newest: Option<SystemTime> = None;
 	let entries = fs::read_dir(dir).ok()?;
 
 	for entry in entries.flatten() {
 		let path = entry.path();
 		if path.is_dir() {
-			if let Some(child_newest) = newest_file_modified_recursive(&path) {
+			if matches_ignore_globs(&path, root, ignore_globs) {
+				continue;
+			}
+			if let Some(child_newest) = newest_file_modified_recursive(&path, root, ignore_globs) {
 				newest = match newest {
 					Some(current) if current >= child_newest => Some(current),
 					_ => Some(child_newest),
 				};
 			}
 		} else {
-			if should_ignore_asset_reload_path(&path) {
+			if should_ignore_asset_reload_path(&path, root, ignore_globs) {
 				continue;
 			}
 
@@ -114,19 +483,135 @@ fn newest_file_modified_recursive(dir: &Path) -> Option<SystemTime> {
 	newest
 }
 
+/// Collect watched files under `dir` modified after `since`, so a reload
+/// knows what changed (see `hot_css_only`).
+fn collect_files_modified_after(
+	dir: &Path,
+	root: &Path,
+	ignore_globs: &GlobSet,
+	since: SystemTime,
+	out: &mut Vec<std::path::PathBuf>,
+) {
+	let Ok(entries) = fs::read_dir(dir) else {
+		return;
+	};
+
+	for entry in entries.flatten() {
+		let path = entry.path();
+		if path.is_dir() {
+			if !matches_ignore_globs(&path, root, ignore_globs) {
+				collect_files_modified_after(&path, root, ignore_globs, since, out);
+			}
+		} else if !should_ignore_asset_reload_path(&path, root, ignore_globs) {
+			let modified = fs::metadata(&path).and_then(|m| m.modified());
+			if matches!(modified, Ok(m) if m > since) && !out.contains(&path) {
+				out.push(path);
+			}
+		}
+	}
+}
+
+/// Newest file mtime of every hosted asset folder: the baseline the asset
+/// watcher compares against, retaken whenever the hosts are rebuilt.
+fn asset_mtime_baseline(
+	runtime: &WallpaperRuntime,
+	ignore_globs: &GlobSet,
+) -> HashMap<std::path::PathBuf, SystemTime> {
+	runtime
+		.active_asset_dirs()
+		.into_iter()
+		.filter_map(|dir| newest_file_modified_recursive(&dir, &dir, ignore_globs).map(|mtime| (dir, mtime)))
+		.collect()
+}
+
+/// Main loop timings and watcher settings, re-read from the config after
+/// every reload.
+struct LoopSettings {
+	tick_sleep: Duration,
+	idle_tick_sleep: Duration,
+	watcher_enabled: bool,
+	watcher_interval: Duration,
+	asset_ignore_globs: GlobSet,
+}
+
+impl LoopSettings {
+	fn from_config(config: &AddonConfig) -> Self {
+		Self {
+			tick_sleep: Duration::from_millis(config.settings.runtime.tick_sleep_ms.max(1)),
+			idle_tick_sleep: Duration::from_millis(config.settings.runtime.idle_tick_sleep_ms),
+			watcher_enabled: config.settings.performance.watcher.enabled,
+			watcher_interval: Duration::from_millis(config.settings.performance.watcher.interval_ms.max(100)),
+			asset_ignore_globs: GlobSet::new(&config.settings.performance.watcher.ignore_globs),
+		}
+	}
+}
+
 fn main() -> windows::core::Result<()> {
-	logging::init(true, "info");
-	bootstrap::bootstrap_addon();
-	enable_per_monitor_dpi_awareness();
+	if std::env::args().skip(1).any(|arg| arg == "--version") {
+		print_versions();
+		return Ok(());
+	}
+
+	// `--log-level` applies from the first line and outlasts the config's
+	// `debug` flag, so startup problems can be traced at any level.
+	let requested_level = log_level_arg();
+	let log_level = requested_level.as_deref().and_then(logging::parse_level);
+	logging::init(true, log_level.unwrap_or("info"));
+	if let Some(requested) = &requested_level {
+		logging::pin_level();
+		if log_level.is_none() {
+			warn!(
+				"[{}][LOG] Unknown --log-level '{}' (use info, warn or error); logging at info",
+				DEBUG_NAME,
+				requested
+			);
+		}
+	}
 
-	let config_path = addon_config_path();
-	let mut config = AddonConfig::load(&config_path).unwrap_or_else(|| AddonConfig {
-		debug: false,
-		settings: AddonSettings::default(),
-		wallpapers: Vec::new(),
-	});
+	if let Some(subcommand) = std::env::args()
+		.nth(1)
+		.filter(|arg| arg == "install" || arg == "uninstall")
+	{
+		std::process::exit(run_subcommand(&subcommand));
+	}
+
+	let config_path = match config_path_override() {
+		Ok(Some(path)) => path,
+		Ok(None) => addon_config_path(),
+		Err(e) => {
+			error!("[{}][CONFIG] {}", DEBUG_NAME, e);
+			std::process::exit(2);
+		}
+	};
+
+	// `--dry-run` logs how sections would be placed, then exits without
+	// touching the desktop.  Bootstrap is skipped too: it can scaffold files,
+	// copy the exe, start the backend and relaunch.
+	let dry_run = std::env::args().skip(1).any(|arg| arg == "--dry-run");
+	if !dry_run {
+		bootstrap::bootstrap_addon(&config_path);
+	}
+	enable_per_monitor_dpi_awareness();
+	// No previous good config exists at startup, so an invalid file falls back
+	// to the lenient parser (which keeps every recognisable setting).
+	let mut config = load_validated_config(&config_path)
+		.ok()
+		.or_else(|| {
+			warn!(
+				"[{}][CONFIG] {} has errors; starting with its recognisable settings",
+				DEBUG_NAME,
+				config_path.display()
+			);
+			AddonConfig::load(&config_path)
+		})
+		.unwrap_or_else(|| AddonConfig {
+			debug: false,
+			settings: AddonSettings::default(),
+			wallpapers: Vec::new(),
+		});
 
 	logging::set_debug(config.debug);
+	logging::set_recent_capacity(config.settings.diagnostics.recent_log_lines);
 	std::panic::set_hook(Box::new(|panic_info| {
 		error!("[{}] Panic: {}", DEBUG_NAME, panic_info);
 	}));
@@ -134,36 +619,66 @@ fn main() -> windows::core::Result<()> {
 	info!("!---------- [{}] Starting Wallpaper Addon ----------!", DEBUG_NAME);
 	info!("[{}] Config loaded from {}", DEBUG_NAME, config_path.display());
 
+	if dry_run {
+		info!("[{}] Dry run: resolving wallpaper plan only", DEBUG_NAME);
+		wallpaper_engine::log_dry_run_plan(&config);
+		return Ok(());
+	}
+
 	let mut runtime = WallpaperRuntime::new();
 
 	// Refresh Windows' wallpaper cache with the saved snapshot BMP BEFORE
 	// creating WorkerW children.  This ensures that if the process is later
 	// killed (Task Manager, crash) Windows shows a recent frame instead of
 	// whatever was cached from a previous session.
-	runtime.apply_snapshot_as_wallpaper();
+	if config.settings.runtime.manage_static_wallpaper {
+		runtime.apply_snapshot_as_wallpaper();
+	}
 
-	runtime.apply(&config);
+	apply_config(&mut runtime, &config);
 	if runtime.has_registry_snapshot() {
 		let _ = runtime.sync_pause_state_now(false);
 	}
-	let mut loop_sleep = Duration::from_millis(config.settings.runtime.tick_sleep_ms.max(1));
-	let mut watcher_enabled = config.settings.performance.watcher.enabled;
-	let mut watcher_interval =
-		Duration::from_millis(config.settings.performance.watcher.interval_ms.max(100));
+	let previous_config_hash = stored_config_hash();
+	let mut applied_config_hash = record_applied_config(&config_path);
+	if previous_config_hash.is_some() && previous_config_hash != applied_config_hash {
+		info!("[{}][CONFIG] config.yaml changed since the last run", DEBUG_NAME);
+	}
+	register_toggle_pause_hotkey(config.settings.hotkeys.toggle_pause);
+	let notification_window = wallpaper_engine::create_notification_window();
+	let mut loop_settings = LoopSettings::from_config(&config);
 	let mut last_watch_tick = Instant::now();
 	let mut last_config_modified: Option<SystemTime> = fs::metadata(&config_path)
 		.and_then(|m| m.modified())
 		.ok();
-	let mut watched_asset_mtime: HashMap<std::path::PathBuf, SystemTime> = runtime
-		.active_asset_dirs()
-		.into_iter()
-		.filter_map(|dir| newest_file_modified_recursive(&dir).map(|mtime| (dir, mtime)))
-		.collect();
+	let mut watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
 	let mut pending_asset_reload_since: HashMap<std::path::PathBuf, Instant> = HashMap::new();
+	let mut pending_asset_changes: HashMap<std::path::PathBuf, Vec<std::path::PathBuf>> = HashMap::new();
 	let watcher_debounce = Duration::from_millis(400);
 
 	let mut last_monitor_check = Instant::now();
 	let monitor_check_interval = Duration::from_secs(2);
+	// WM_DISPLAYCHANGE runs the monitor check early, once the burst of
+	// changes a resolution switch produces has settled.
+	let mut display_changed_at: Option<Instant> = None;
+	let display_change_debounce = Duration::from_millis(500);
+	// Re-enumerate now and then even without WM_DISPLAYCHANGE, in case one
+	// never arrives.
+	let mut last_monitor_rescan = Instant::now();
+	let monitor_rescan_interval = Duration::from_secs(30);
+	let mut reposition_pending = false;
+	// Explorer restarts destroy the WorkerW our hosts live in.  Wait for the
+	// new desktop to settle, and never reapply more often than the cooldown.
+	let mut desktop_lost_since: Option<Instant> = None;
+	let mut last_desktop_recovery: Option<Instant> = None;
+	let desktop_recovery_settle = Duration::from_secs(3);
+	let desktop_recovery_cooldown = Duration::from_secs(10);
+
+	let mut last_command_poll = Instant::now();
+	let command_poll_interval = Duration::from_millis(250);
+
+	// Sections with `days` switch on/off at local midnight.
+	let mut current_weekday = Local::now().weekday();
 
 	loop {
 		unsafe {
@@ -171,9 +686,38 @@ fn main() -> windows::core::Result<()> {
 			while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
 				if msg.message == WM_QUIT {
 					warn!("[{}] WM_QUIT received — applying shutdown snapshot", DEBUG_NAME);
+					unregister_toggle_pause_hotkey();
+					if let Some(hwnd) = notification_window {
+						wallpaper_engine::destroy_notification_window(hwnd);
+					}
 					runtime.shutdown_snapshot();
 					return Ok(());
 				}
+				if msg.message == WM_HOTKEY && msg.wParam.0 == TOGGLE_PAUSE_HOTKEY_ID as usize {
+					runtime.toggle_pause_all();
+					continue;
+				}
+				if msg.message == WM_DISPLAYCHANGE {
+					runtime.invalidate_monitors();
+					display_changed_at = Some(Instant::now());
+					continue;
+				}
+				if msg.message == WM_SETTINGCHANGE {
+					reposition_pending = true;
+					continue;
+				}
+				if msg.message == WM_WTSSESSION_CHANGE {
+					match msg.wParam.0 as u32 {
+						WTS_SESSION_LOCK if config.settings.runtime.pause_all_on_lock => {
+							runtime.set_session_locked(true);
+						}
+						// Unlock always lifts the pause, even if the toggle
+						// was switched off while locked.
+						WTS_SESSION_UNLOCK => runtime.set_session_locked(false),
+						_ => {}
+					}
+					continue;
+				}
 				let _ = TranslateMessage(&msg);
 				DispatchMessageW(&msg);
 			}
@@ -182,35 +726,129 @@ fn main() -> windows::core::Result<()> {
 		let unpaused_transition = runtime.tick_interactions();
 		if unpaused_transition && config.settings.runtime.reapply_on_pause_change {
 			let all_paused_before = runtime.hosted_all_paused();
-			runtime.apply(&config);
+			apply_config(&mut runtime, &config);
 			if runtime.has_registry_snapshot() {
 				let _ = runtime.sync_pause_state_now(all_paused_before);
 			}
 			warn!("[{}][PAUSE] Reapplied runtime after unpause transition", DEBUG_NAME);
 		}
 
+		// Commands queued by the options UI (via the backend) for this addon.
+		if last_command_poll.elapsed() >= command_poll_interval {
+			last_command_poll = Instant::now();
+			let mut reapplied = false;
+			for command in commands::poll() {
+				let result = handle_command(&mut runtime, &mut config, &config_path, &command);
+				if let Err(e) = &result {
+					warn!("[{}][COMMAND] '{}' failed: {}", DEBUG_NAME, command.cmd, e);
+				}
+				reapplied |= matches!(command.cmd.as_str(), "reapply" | "reload_config")
+					&& result
+						.as_ref()
+						.is_ok_and(|data| data["parsed"] != Value::Bool(false));
+				commands::respond(&command, result);
+			}
+
+			// A `reapply` / `reload_config` re-read the config, so refresh
+			// everything the loop derives from it, exactly as a watcher-driven
+			// reload would.  That also moves the mtime/hash baseline, so the
+			// watcher doesn't apply the same save a second time.
+			if reapplied {
+				loop_settings = LoopSettings::from_config(&config);
+				last_config_modified = fs::metadata(&config_path)
+					.and_then(|m| m.modified())
+					.ok();
+				applied_config_hash = record_applied_config(&config_path);
+				watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
+			}
+		}
+
+		if reposition_pending {
+			reposition_pending = false;
+			runtime.reposition_hosts();
+		}
+
 		// Detect monitor layout changes (rearranged, added, removed, resolution)
 		// and fully reapply so wallpaper windows land on the correct rects.
-		if last_monitor_check.elapsed() >= monitor_check_interval {
+		// The timer stays as a fallback for when no WM_DISPLAYCHANGE arrives.
+		let display_change_due = display_changed_at
+			.is_some_and(|at| at.elapsed() >= display_change_debounce);
+		if display_change_due || last_monitor_check.elapsed() >= monitor_check_interval {
 			last_monitor_check = Instant::now();
+			display_changed_at = None;
+			// The cached layout is only re-read after WM_DISPLAYCHANGE; without
+			// the notification window nothing else would invalidate it.
+			if notification_window.is_none() || last_monitor_rescan.elapsed() >= monitor_rescan_interval {
+				last_monitor_rescan = Instant::now();
+				runtime.invalidate_monitors();
+			}
 			if runtime.monitors_changed() {
 				let all_paused_before = runtime.hosted_all_paused();
-				runtime.apply(&config);
+				apply_config(&mut runtime, &config);
 				if runtime.has_registry_snapshot() {
 					let _ = runtime.sync_pause_state_now(all_paused_before);
 				}
 				warn!("[{}][MONITORS] Layout change detected — reapplied wallpapers", DEBUG_NAME);
 
 				// Refresh asset watcher baselines after full reapply
-				watched_asset_mtime = runtime
-					.active_asset_dirs()
-					.into_iter()
-					.filter_map(|dir| newest_file_modified_recursive(&dir).map(|mtime| (dir, mtime)))
-					.collect();
+				watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
+			}
+
+			if runtime.remote_session_changed() {
+				let all_paused_before = runtime.hosted_all_paused();
+				apply_config(&mut runtime, &config);
+				if runtime.has_registry_snapshot() {
+					let _ = runtime.sync_pause_state_now(all_paused_before);
+				}
+				warn!("[{}][RDP] Session switched local/remote — reapplied wallpapers", DEBUG_NAME);
+
+				watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
+			}
+
+			if runtime.desktop_host_lost() {
+				let lost_since = *desktop_lost_since.get_or_insert_with(Instant::now);
+				let cooled_down = last_desktop_recovery
+					.map(|t| t.elapsed() >= desktop_recovery_cooldown)
+					.unwrap_or(true);
+				if lost_since.elapsed() >= desktop_recovery_settle
+					&& cooled_down
+					&& wallpaper_engine::desktop_host_ready()
+				{
+					let all_paused_before = runtime.hosted_all_paused();
+					apply_config(&mut runtime, &config);
+					if runtime.has_registry_snapshot() {
+						let _ = runtime.sync_pause_state_now(all_paused_before);
+					}
+					warn!(
+						"[{}][RECOVERY] Desktop host disappeared (Explorer restart?) — reapplied wallpapers",
+						DEBUG_NAME
+					);
+					desktop_lost_since = None;
+					last_desktop_recovery = Some(Instant::now());
+
+					watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
+				}
+			} else {
+				desktop_lost_since = None;
+			}
+
+			let weekday = Local::now().weekday();
+			if weekday != current_weekday {
+				current_weekday = weekday;
+				if config.has_day_schedules() {
+					let all_paused_before = runtime.hosted_all_paused();
+					apply_config_changes(&mut runtime, &config);
+					if runtime.has_registry_snapshot() {
+						let _ = runtime.sync_pause_state_now(all_paused_before);
+					}
+					warn!("[{}][SCHEDULE] Day changed to {} — reapplied wallpapers", DEBUG_NAME, weekday);
+
+					watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
+				}
 			}
 		}
 
-		if watcher_enabled && last_watch_tick.elapsed() >= watcher_interval {
+		if loop_settings.watcher_enabled && last_watch_tick.elapsed() >= loop_settings.watcher_interval {
 			last_watch_tick = Instant::now();
 
 			let current_modified = fs::metadata(&config_path)
@@ -223,20 +861,29 @@ fn main() -> windows::core::Result<()> {
 				_ => false,
 			};
 
-			if changed {
-				match AddonConfig::load(&config_path) {
-					Some(new_config) => {
-						let all_paused_before = runtime.hosted_all_paused();
-						config = new_config;
-						runtime.apply(&config);
-						if runtime.has_registry_snapshot() {
-							let _ = runtime.sync_pause_state_now(all_paused_before);
-						}
-						loop_sleep = Duration::from_millis(config.settings.runtime.tick_sleep_ms.max(1));
-						watcher_enabled = config.settings.performance.watcher.enabled;
-						watcher_interval = Duration::from_millis(
-							config.settings.performance.watcher.interval_ms.max(100),
-						);
+			// Saves that leave the YAML data as it is (whitespace, comments,
+			// an options-UI rewrite of the running config) need no reapply.
+			let content_changed = changed && {
+				let hash = config_content_hash(&config_path);
+				hash.is_none() || hash != applied_config_hash
+			};
+			if changed && !content_changed {
+				if config.settings.diagnostics.log_watcher_reloads {
+					warn!(
+						"[{}][WATCHER] {} was saved without content changes; skipping reload",
+						DEBUG_NAME,
+						config_path.display()
+					);
+				}
+				last_config_modified = current_modified;
+			}
+
+			if content_changed {
+				match load_validated_config(&config_path) {
+					Ok(new_config) => {
+						apply_new_config(&mut runtime, &mut config, new_config, false);
+						applied_config_hash = record_applied_config(&config_path);
+						loop_settings = LoopSettings::from_config(&config);
 						if config.settings.diagnostics.log_watcher_reloads {
 							warn!(
 								"[{}][WATCHER] Reloaded config from {}",
@@ -244,15 +891,11 @@ fn main() -> windows::core::Result<()> {
 								config_path.display()
 							);
 						}
-						watched_asset_mtime = runtime
-							.active_asset_dirs()
-							.into_iter()
-							.filter_map(|dir| newest_file_modified_recursive(&dir).map(|mtime| (dir, mtime)))
-							.collect();
+						watched_asset_mtime = asset_mtime_baseline(&runtime, &loop_settings.asset_ignore_globs);
 					}
-					None => {
+					Err(_) => {
 						warn!(
-							"[{}][WATCHER] Detected config change but failed to parse {}; keeping previous config",
+							"[{}][WATCHER] Detected config change but {} is invalid (see config.errors.log); keeping previous config",
 							DEBUG_NAME,
 							config_path.display()
 						);
@@ -266,24 +909,29 @@ fn main() -> windows::core::Result<()> {
 			let active_set: std::collections::HashSet<_> = active_dirs.iter().cloned().collect();
 			watched_asset_mtime.retain(|dir, _| active_set.contains(dir));
 			pending_asset_reload_since.retain(|dir, _| active_set.contains(dir));
+			pending_asset_changes.retain(|dir, _| active_set.contains(dir));
 
 			for dir in active_dirs {
-				let Some(current_modified) = newest_file_modified_recursive(&dir) else {
+				let Some(current_modified) = newest_file_modified_recursive(&dir, &dir, &loop_settings.asset_ignore_globs) else {
 					continue;
 				};
 
-				let changed = match watched_asset_mtime.get(&dir) {
-					Some(prev) => current_modified > *prev,
-					None => false,
-				};
-
-				if changed {
+				let previous = watched_asset_mtime.get(&dir).copied();
+				if let Some(prev) = previous.filter(|prev| current_modified > *prev) {
 					pending_asset_reload_since.insert(dir.clone(), Instant::now());
+					collect_files_modified_after(
+						&dir,
+						&dir,
+						&loop_settings.asset_ignore_globs,
+						prev,
+						pending_asset_changes.entry(dir.clone()).or_default(),
+					);
 				}
 
 				if let Some(since) = pending_asset_reload_since.get(&dir).copied() {
 					if since.elapsed() >= watcher_debounce {
-						let reloaded = runtime.reload_wallpapers_for_asset_dir(&dir);
+						let changed_files = pending_asset_changes.remove(&dir).unwrap_or_default();
+						let reloaded = runtime.reload_wallpapers_for_asset_dir(&dir, &changed_files);
 						if reloaded > 0 && config.settings.diagnostics.log_watcher_reloads {
 							warn!(
 								"[{}][WATCHER] Debounced reload: {} hosted wallpaper instance(s) for asset dir {}",
@@ -300,7 +948,13 @@ fn main() -> windows::core::Result<()> {
 			}
 		}
 
-		thread::sleep(loop_sleep);
+		// Nothing animates while every host is paused (or none exist), so
+		// the loop can idle; an unpause drops straight back to the fast tick.
+		if runtime.is_idle() {
+			thread::sleep(loop_settings.idle_tick_sleep.max(loop_settings.tick_sleep));
+		} else {
+			thread::sleep(loop_settings.tick_sleep);
+		}
 	}
 }
 
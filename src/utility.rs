@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     env,
     ffi::OsStr,
     os::windows::ffi::OsStrExt,
@@ -12,6 +13,176 @@ pub fn to_wstring(s: &str) -> Vec<u16> {
         .collect()
 }
 
+/// Paths at or past this length need the `\\?\` prefix for Win32 calls
+/// that take a raw string (std's own file APIs already add it).
+const MAX_PATH_LEN: usize = 260;
+
+/// `path` with the `\\?\` long-path prefix when it is absolute and too long
+/// for `MAX_PATH`; shorter or relative paths come back unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if text.len() < MAX_PATH_LEN || !path.is_absolute() || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+
+    // Verbatim paths skip normalisation, so separators must already be `\`.
+    let text = text.replace('/', "\\");
+    match text.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{unc}")),
+        None => PathBuf::from(format!(r"\\?\{text}")),
+    }
+}
+
+/// `path` without a `\\?\` / `\\?\UNC\` prefix, for places (URLs, the
+/// registry) that expect the ordinary form.
+pub fn strip_long_path_prefix(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{unc}")
+    } else {
+        path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+    }
+}
+
+/// `value` with `%VAR%` and `${VAR}` tokens expanded from the process
+/// environment.  Unset variables stay as written and are returned by name so
+/// callers can report them; values without tokens come back borrowed.
+pub fn expand_env_vars(value: &str) -> (Cow<'_, str>, Vec<String>) {
+    if !value.contains('%') && !value.contains("${") {
+        return (Cow::Borrowed(value), Vec::new());
+    }
+
+    let mut expanded = String::with_capacity(value.len());
+    let mut unresolved = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find(['%', '$']) {
+        expanded.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let token = if let Some(inner) = tail.strip_prefix("${") {
+            inner.find('}').map(|end| (&inner[..end], end + 3))
+        } else if let Some(inner) = tail.strip_prefix('%') {
+            inner.find('%').map(|end| (&inner[..end], end + 2))
+        } else {
+            // A `$` without `{` is literal.
+            None
+        };
+        // Percent-encoded URLs (`%20`) never form a valid name.
+        match token.filter(|&(name, _)| is_env_var_name(name)) {
+            Some((name, len)) => {
+                match env::var(name) {
+                    Ok(var) => expanded.push_str(&var),
+                    Err(_) => {
+                        unresolved.push(name.to_string());
+                        expanded.push_str(&tail[..len]);
+                    }
+                }
+                rest = &tail[len..];
+            }
+            None => {
+                expanded.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    (Cow::Owned(expanded), unresolved)
+}
+
+fn is_env_var_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '(' | ')'))
+}
+
+/// Compiled `watcher.ignore_globs`.  Patterns are lowercased and split on
+/// `/` or `\`; one without a separator matches a path segment at any depth,
+/// one with a separator is anchored at the root.  Matching a folder covers
+/// everything below it.
+#[derive(Debug, Clone, Default)]
+pub struct GlobSet {
+    patterns: Vec<Vec<String>>,
+}
+
+impl GlobSet {
+    pub fn new(patterns: &[String]) -> Self {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| {
+                let pattern = pattern.trim().to_lowercase().replace('\\', "/");
+                let trimmed = pattern.trim_matches('/');
+                if trimmed.is_empty() {
+                    return None;
+                }
+                let mut segments: Vec<String> = trimmed
+                    .split('/')
+                    .filter(|segment| !segment.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if segments.len() == 1 {
+                    segments.insert(0, "**".to_string());
+                }
+                Some(segments)
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Whether `relative` (a path below the watched root) or one of the
+    /// folders it sits in matches any pattern.
+    pub fn is_match(&self, relative: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let components: Vec<String> = relative
+            .components()
+            .filter_map(|c| c.as_os_str().to_str())
+            .map(str::to_lowercase)
+            .collect();
+        self.patterns
+            .iter()
+            .any(|pattern| segments_match_prefix(pattern, &components))
+    }
+}
+
+/// Whether `pattern` matches the leading components of `components`; a
+/// `**` segment consumes any number of them.
+fn segments_match_prefix(pattern: &[String], components: &[String]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((first, rest)) if first == "**" => {
+            (0..=components.len()).any(|skip| segments_match_prefix(rest, &components[skip..]))
+        }
+        Some((first, rest)) => components.split_first().is_some_and(|(component, tail)| {
+            wildcard_match(first, component) && segments_match_prefix(rest, tail)
+        }),
+    }
+}
+
+/// Single-segment glob: `*` matches any run of characters, `?` exactly one.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Last `*` seen and the text position it is currently stretched to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            backtrack = Some((star, matched + 1));
+            p = star + 1;
+            t = matched + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 pub fn user_home_dir() -> Option<PathBuf> {
     env::var("USERPROFILE").map(PathBuf::from).ok()
 }
@@ -51,3 +222,106 @@ pub fn sentinel_assets_dir() -> Option<PathBuf> {
 pub fn sentinel_addons_dir() -> Option<PathBuf> {
     sentinel_root_dir().map(|p| p.join("Addons"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(value: &str) -> (String, Vec<String>) {
+        let (expanded, unresolved) = expand_env_vars(value);
+        (expanded.into_owned(), unresolved)
+    }
+
+    #[test]
+    fn percent_and_brace_tokens_expand() {
+        env::set_var("SENTINEL_TEST_EXPAND_DIR", r"C:\Wallpapers");
+        assert_eq!(
+            expand(r"%SENTINEL_TEST_EXPAND_DIR%\a.html"),
+            (r"C:\Wallpapers\a.html".to_string(), Vec::new())
+        );
+        assert_eq!(
+            expand("${SENTINEL_TEST_EXPAND_DIR}/b"),
+            (r"C:\Wallpapers/b".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn unresolved_tokens_stay_as_written() {
+        assert_eq!(
+            expand("%SENTINEL_TEST_UNSET%/${SENTINEL_TEST_UNSET}"),
+            (
+                "%SENTINEL_TEST_UNSET%/${SENTINEL_TEST_UNSET}".to_string(),
+                vec!["SENTINEL_TEST_UNSET".to_string(), "SENTINEL_TEST_UNSET".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn percent_encoded_urls_are_untouched() {
+        let url = "https://example.com/my%20wallpaper%2Fpage.html?q=100%25";
+        assert_eq!(expand(url), (url.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn bare_dollar_is_literal() {
+        env::set_var("SENTINEL_TEST_EXPAND_NAME", "name");
+        assert_eq!(expand("$5 $x 100%"), ("$5 $x 100%".to_string(), Vec::new()));
+        assert_eq!(
+            expand("$SENTINEL_TEST_EXPAND_NAME%SENTINEL_TEST_UNSET%"),
+            (
+                "$SENTINEL_TEST_EXPAND_NAME%SENTINEL_TEST_UNSET%".to_string(),
+                vec!["SENTINEL_TEST_UNSET".to_string()]
+            )
+        );
+        assert_eq!(
+            expand("cost $%SENTINEL_TEST_EXPAND_NAME%"),
+            ("cost $name".to_string(), Vec::new())
+        );
+    }
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        GlobSet::new(&[pattern.to_string()]).is_match(Path::new(path))
+    }
+
+    #[test]
+    fn bare_patterns_match_at_any_depth() {
+        assert!(matches("*.log", "app.log"));
+        assert!(matches("*.log", "logs/deep/app.log"));
+        assert!(!matches("*.log", "app.log.txt"));
+        assert!(!matches("*.log", "catalog"));
+        assert!(matches("frame-??.png", "frames/frame-01.png"));
+        assert!(!matches("frame-??.png", "frames/frame-1.png"));
+    }
+
+    #[test]
+    fn patterns_with_a_separator_are_anchored() {
+        assert!(matches("dist/.cache", "dist/.cache"));
+        assert!(!matches("dist/.cache", "src/dist/.cache"));
+        assert!(!matches("dist/.cache", "dist/.cached"));
+        assert!(matches("logs/**/*.txt", "logs/a.txt"));
+        assert!(matches("logs/**/*.txt", "logs/2024/05/a.txt"));
+        assert!(!matches("logs/**/*.txt", "logs/a.log"));
+        assert!(!matches("logs/**/*.txt", "other/logs/a.txt"));
+    }
+
+    #[test]
+    fn matched_folders_cover_their_children() {
+        assert!(matches("dist/.cache", "dist/.cache/chunks/0.bin"));
+        assert!(matches("node_modules", "vendor/node_modules/pkg/index.js"));
+        assert!(!matches("node_modules", "vendor/node_modules.js"));
+    }
+
+    #[test]
+    fn globs_ignore_case_and_separator_style() {
+        assert!(matches("*.LOG", "App.Log"));
+        assert!(matches(r"Dist\.Cache", "DIST/.cache/x"));
+        assert!(matches("/dist/.cache/", "dist/.cache"));
+    }
+
+    #[test]
+    fn empty_patterns_match_nothing() {
+        assert!(!GlobSet::new(&[]).is_match(Path::new("app.log")));
+        assert!(!matches("", "app.log"));
+        assert!(!matches("/", "app.log"));
+    }
+}
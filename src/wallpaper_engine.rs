@@ -1,54 +1,149 @@
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     collections::{HashMap, HashSet},
-    fs,
+    fmt, fs,
     mem,
     path::{Path, PathBuf},
     ptr,
-    sync::{mpsc, OnceLock},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        mpsc, Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError,
+    },
     thread,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use webview2_com::Microsoft::Web::WebView2::Win32::*;
-use image::{Rgba, RgbaImage};
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, Rgba, RgbaImage};
 use windows::{
-    core::{w, BOOL, PCWSTR},
+    core::{w, Interface, BOOL, HRESULT, PCWSTR, PWSTR},
     Win32::{
-        Foundation::{E_POINTER, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM},
+        Foundation::{
+            CloseHandle, COLORREF, ERROR_FILE_NOT_FOUND, ERROR_INVALID_STATE, ERROR_TIMEOUT, E_ABORT,
+            E_POINTER, WAIT_OBJECT_0, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM,
+        },
+        Graphics::Dxgi::{
+            Common::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020, CreateDXGIFactory1,
+            IDXGIFactory1, IDXGIOutput6,
+        },
         Graphics::Gdi::{
             BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject,
-            EnumDisplayMonitors, GetDC, GetDIBits, GetMonitorInfoW, HDC, HGDIOBJ, HMONITOR, MonitorFromWindow,
+            EnumDisplayMonitors, EnumDisplaySettingsW, DEVMODEW, ENUM_CURRENT_SETTINGS, GetDC, GetDIBits, GetMonitorInfoW, HDC, HGDIOBJ, HMONITOR, MonitorFromRect,
+            MonitorFromWindow,
             MONITORINFOEXW, MONITOR_DEFAULTTONEAREST, ReleaseDC, SelectObject, BI_RGB, BITMAPINFO, BITMAPINFOHEADER,
             DIB_RGB_COLORS, SRCCOPY,
         },
         Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS},
-        System::{Com::*, LibraryLoader::GetModuleHandleW},
+        System::{
+            Com::*,
+            LibraryLoader::GetModuleHandleW,
+            Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ},
+            RemoteDesktop::{
+                WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+                NOTIFY_FOR_THIS_SESSION,
+            },
+            Threading::{OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE},
+        },
+        UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+        UI::Input::KeyboardAndMouse::{
+            GetAsyncKeyState, GetLastInputInfo, LASTINPUTINFO, VIRTUAL_KEY, VK_LBUTTON, VK_MBUTTON,
+            VK_RBUTTON,
+        },
+        UI::Shell::{
+            SHQueryUserNotificationState, QUERY_USER_NOTIFICATION_STATE, QUNS_ACCEPTS_NOTIFICATIONS,
+            QUNS_APP, QUNS_BUSY, QUNS_NOT_PRESENT, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+            QUNS_RUNNING_D3D_FULL_SCREEN,
+        },
         UI::WindowsAndMessaging::{
             CreateWindowExW, DefWindowProcW, DestroyWindow, EnumWindows, FindWindowExW, FindWindowW,
-            GetClassNameW, GetForegroundWindow, GetWindowLongW, GetWindowRect, IsZoomed, RegisterClassW, SendMessageTimeoutW,
-            SetWindowLongW,
-            SetWindowPos, GWL_EXSTYLE, GWL_STYLE, HWND_BOTTOM, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST,
+            GetAncestor, GetClassNameW, GetSystemMetrics, GetWindowThreadProcessId, IsWindow,
+            PostQuitMessage, WM_APP, GA_PARENT, SM_CXVIRTUALSCREEN,
+            SM_CYVIRTUALSCREEN, SM_REMOTESESSION, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+            CallNextHookEx, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, HOOKPROC, KBDLLHOOKSTRUCT,
+            MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL, WINDOWS_HOOK_ID, WM_KEYDOWN, WM_KEYUP,
+            WM_MOUSEHWHEEL, WM_MOUSEWHEEL, WM_SYSKEYDOWN, WM_SYSKEYUP, GetCursorPos, GetForegroundWindow, GetWindowLongW, GetWindowRect, IsZoomed, RegisterClassW, SendMessageTimeoutW,
+            SetLayeredWindowAttributes, SetParent, SetWindowLongW, PostMessageW,
+            SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE, WDA_NONE,
+            WM_DISPLAYCHANGE, WM_SETTINGCHANGE, WM_WTSSESSION_CHANGE, SPI_SETWORKAREA, SWP_NOZORDER,
+            SetWindowPos, ShowWindow, SW_HIDE, SW_SHOWNA, GWL_EXSTYLE, GWL_STYLE, HWND_BOTTOM, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST,
             SMTO_NORMAL, SWP_FRAMECHANGED,
-            SWP_NOACTIVATE, SWP_SHOWWINDOW, WINDOW_EX_STYLE,
+            SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_SHOWWINDOW, WINDOW_EX_STYLE,
             WINDOW_STYLE, WNDCLASSW, WS_CAPTION, WS_CHILD, WS_CLIPCHILDREN, WS_CLIPSIBLINGS,
-            WS_EX_APPWINDOW, WS_EX_DLGMODALFRAME, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
-            WS_EX_WINDOWEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_SYSMENU, WS_THICKFRAME, WS_VISIBLE,
+            WS_EX_APPWINDOW, WS_EX_DLGMODALFRAME, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+            WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_EX_WINDOWEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX,
+            WS_POPUP, WS_SYSMENU, WS_THICKFRAME, WS_VISIBLE, LWA_ALPHA,
             SystemParametersInfoW, SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE,
+            DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE, WM_QUIT,
         },
     },
 };
 
 use crate::{
-    data_loaders::config::{AddonConfig, PauseMode, WallpaperConfig},
+    audio::{AudioFrame, AudioMonitor},
+    data_loaders::config::{
+        AddonConfig, AudioSettings, HostStrategy, IconLayer, InteractionSettings, PauseMode,
+        SnapshotFormat, WallpaperConfig, parse_monitor_range, process_image_key,
+    },
     error,
     ipc_connector::{request, request_quick},
-    utility::{sentinel_assets_dir, to_wstring},
+    utility::{
+        addon_root_dir, expand_env_vars, long_path, sentinel_assets_dir, strip_long_path_prefix,
+        to_wstring,
+    },
     warn,
 };
 
 const HOST_CLASS_NAME: PCWSTR = w!("SentinelWallpaperHostWindow");
+/// Class of the notification window, which another process looks for to
+/// find running instances.
+const NOTIFICATION_CLASS_NAME: PCWSTR = w!("SentinelWallpaperNotifyWindow");
+const NOTIFICATION_WINDOW_TITLE: PCWSTR = w!("SentinelWallpaperNotify");
+/// Posted to a running instance's notification window by
+/// `stop_other_instances`; the instance quits as it would on `WM_QUIT`.
+const WM_STOP_INSTANCE: u32 = WM_APP + 1;
+/// Virtual host the generated GIF / video player pages load their media
+/// from; mapped to the asset folder for those hosts only.
+const PLAYER_ASSET_HOST: &str = "sentinel-asset.example";
+/// Virtual host an asset folder is served from when its manifest sets
+/// `"virtual_host": true`.
+const VIRTUAL_ASSET_HOST: &str = "wallpaper.sentinel";
+/// Start of every generated GIF player URL.  The extra media-type parameter
+/// is ignored by WebView2 but marks the page as ours.
+const GIF_PLAYER_URL_PREFIX: &str = "data:text/html;charset=utf-8;player=gif,";
+/// Start of every generated video player URL (see `GIF_PLAYER_URL_PREFIX`).
+const VIDEO_PLAYER_URL_PREFIX: &str = "data:text/html;charset=utf-8;player=video,";
+/// Wheel deltas gathered by `scroll_hook_proc` and drained by `tick_scroll`.
+/// The hook runs on the main thread's message pump, same as the tick.
+static SCROLL_DELTA_Y: AtomicI32 = AtomicI32::new(0);
+static SCROLL_DELTA_X: AtomicI32 = AtomicI32::new(0);
+/// `(vk, down)` transitions seen by `key_hook_proc` while the desktop had
+/// focus, drained by `tick_keys`.
+static KEY_EVENTS: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+/// A host whose WebView2 process dies more than this many times within
+/// `CRASH_RESTART_WINDOW` is given up on.
+const CRASH_RESTART_LIMIT: usize = 3;
+const CRASH_RESTART_WINDOW: Duration = Duration::from_secs(60);
+/// A due periodic snapshot waits for a `snapshot_ready` signal younger than
+/// this from any host that has ever sent one.
+const SNAPSHOT_READY_WINDOW: Duration = Duration::from_secs(2);
+/// GPU usage has to stay over `gpu_pause_percent` this long before the GPU
+/// trigger pauses, and back under it minus `GPU_RESUME_MARGIN` this long
+/// before it lets go, so short spikes and dips don't flap the wallpaper.
+const GPU_PAUSE_SUSTAIN: Duration = Duration::from_secs(3);
+const GPU_RESUME_MARGIN: f64 = 10.0;
+/// Exponent applied to captures of HDR monitors with
+/// `snapshot.hdr_gamma_correct`; above 1 darkens the lifted mid-tones.
+const HDR_SNAPSHOT_GAMMA: f64 = 1.25;
+/// Threads `stitch_tiled_snapshot` may use; bounded so a background save
+/// never competes with the wallpapers for every core.
+const SNAPSHOT_WORKERS_MAX: usize = 4;
+/// A page's `request_reload` is ignored until its current document has been
+/// loaded this long, so a page that asks on every load cannot spin.
+const REQUEST_RELOAD_MIN_UPTIME: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Deserialize, Clone)]
 struct RegistryAsset {
@@ -61,26 +156,534 @@ struct RegistryAsset {
     path: PathBuf,
 }
 
+/// Outcome of one `WallpaperRuntime::apply`.
+#[derive(Debug, Default, Serialize)]
+pub struct ApplyReport {
+    /// Host windows running afterwards, error pages included.
+    pub hosted: usize,
+    /// Planned hosts that show an error page or failed to embed.
+    pub skipped: usize,
+    /// Why hosts were skipped, or why the whole apply bailed out early.
+    pub errors: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 struct MonitorArea {
     index: usize,
     primary: bool,
     rect: RECT,
+    /// GDI device name (e.g. `\\.\DISPLAY1`).
+    device_name: String,
+}
+
+/// One host `apply` would create.  `content` is the resolved asset and URL,
+/// or the reason to show the error wallpaper instead.
+struct PlannedHost<'a> {
+    profile: &'a WallpaperConfig,
+    wallpaper_id: String,
+    /// Target area; a synthesised bounding rect when spanning.
+    monitor: MonitorArea,
+    z_index: String,
+    /// Area the page is laid out against.  Equals `monitor.rect` except for
+    /// a span split across per-layer windows, where it is the whole span.
+    content_rect: RECT,
+    span_count: usize,
+    content: std::result::Result<(&'a RegistryAsset, String), String>,
+}
+
+impl PlannedHost<'_> {
+    /// `None` when the host has no resolved content to compare.
+    fn key(&self) -> Option<HostKey<'_>> {
+        let (asset, url) = self.content.as_ref().ok()?;
+        let mode = layout_mode(self.profile, url);
+        Some(HostKey {
+            section: &self.profile.section,
+            wallpaper_id: &self.wallpaper_id,
+            monitor_index: self.monitor.index,
+            monitor_rect: self.monitor.rect,
+            content_rect: self.content_rect,
+            z_index: &self.z_index,
+            mode,
+            source_url: Cow::Owned(mode_fragment_url(url, mode)),
+            asset_dir: &asset.path,
+            click_through: self.profile.click_through,
+            icon_layer: self.profile.icon_layer.map(IconLayer::name).unwrap_or("auto"),
+        })
+    }
+
+    fn describe_target(&self) -> String {
+        if self.span_count > 1 {
+            format!("span across {} monitor(s)", self.span_count)
+        } else if self.content_rect != self.monitor.rect {
+            format!("monitor {} (span slice)", self.monitor.index + 1)
+        } else if self.profile.per_monitor.is_empty() {
+            format!("monitor {}", self.monitor.index + 1)
+        } else {
+            format!("monitor {} (per_monitor)", self.monitor.index + 1)
+        }
+    }
+}
+
+/// Per-host WebView2 settings applied when its controller is created.
+#[derive(Debug, Clone, Copy)]
+struct WebViewOptions<'a> {
+    rasterization_scale: f64,
+    background: [u8; 3],
+    /// Manifest `allow_remote`; when false only `file:`/`data:` documents
+    /// may load.
+    allow_remote: bool,
+    /// Virtual host name and the folder served under it (GIF player pages
+    /// and `virtual_host` assets).
+    asset_host: Option<(&'static str, &'a Path)>,
+}
+
+/// Why a WebView2 environment or controller could not be created, so
+/// callers can recover (retry on a fresh environment, keep the snapshot)
+/// instead of only logging.
+#[derive(Debug)]
+enum WebViewError {
+    /// No usable WebView2 runtime (`ERROR_FILE_NOT_FOUND`).
+    EnvironmentMissing(String),
+    /// The browser process did not answer in time (`ERROR_TIMEOUT`).
+    ControllerTimeout(String),
+    /// The environment's browser process is gone (`ERROR_INVALID_STATE`,
+    /// `E_ABORT`); a fresh environment usually works.
+    BrowserExited(String),
+    /// `Navigate` rejected the wallpaper URL.
+    NavigationFailed(String),
+    /// A creation callback never delivered its result.
+    ChannelRecv(String),
+    /// Any other failure, e.g. a controller setting or the host window.
+    Other(String),
+}
+
+impl WebViewError {
+    /// Classify a failed asynchronous creation `step` by its HRESULT.
+    fn from_creation(step: &str, error: webview2_com::Error) -> Self {
+        let message = format!("{step} failed: {error:?}");
+        let webview2_com::Error::WindowsError(windows_error) = &error else {
+            return WebViewError::Other(message);
+        };
+        let code = windows_error.code();
+        if code == HRESULT::from_win32(ERROR_FILE_NOT_FOUND.0) {
+            WebViewError::EnvironmentMissing(message)
+        } else if code == HRESULT::from_win32(ERROR_TIMEOUT.0) {
+            WebViewError::ControllerTimeout(message)
+        } else if code == HRESULT::from_win32(ERROR_INVALID_STATE.0) || code == E_ABORT {
+            WebViewError::BrowserExited(message)
+        } else {
+            WebViewError::Other(message)
+        }
+    }
+
+    /// Worth one more attempt on a freshly created environment.
+    fn is_transient(&self) -> bool {
+        matches!(self, WebViewError::ControllerTimeout(_) | WebViewError::BrowserExited(_))
+    }
+}
+
+impl fmt::Display for WebViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebViewError::EnvironmentMissing(message)
+            | WebViewError::ControllerTimeout(message)
+            | WebViewError::BrowserExited(message)
+            | WebViewError::NavigationFailed(message)
+            | WebViewError::ChannelRecv(message)
+            | WebViewError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for WebViewError {
+    fn from(message: String) -> Self {
+        WebViewError::Other(message)
+    }
+}
+
+/// Where a host window goes and how its WebView is laid out inside it.
+struct HostLayout<'a> {
+    monitor: &'a MonitorArea,
+    z_index: &'a str,
+    content_rect: RECT,
+    /// Layout mode actually applied (see `effective_mode`).
+    mode: &'a str,
+}
+
+impl HostLayout<'_> {
+    fn webview_bounds(&self) -> RECT {
+        webview_bounds(self.monitor.rect, self.content_rect)
+    }
+}
+
+/// WebView bounds relative to the host window.  A span slice offsets the
+/// full-span page so the window shows only its own monitor's part.
+fn webview_bounds(window: RECT, content: RECT) -> RECT {
+    let left = content.left - window.left;
+    let top = content.top - window.top;
+    RECT {
+        left,
+        top,
+        right: left + (content.right - content.left),
+        bottom: top + (content.bottom - content.top),
+    }
+}
+
+/// What a host is launched with that an in-place update cannot change;
+/// `update_hosts_in_place` keeps a host only when its key matches the
+/// planned host's.
+#[derive(Debug, PartialEq)]
+struct HostKey<'a> {
+    section: &'a str,
+    wallpaper_id: &'a str,
+    monitor_index: usize,
+    monitor_rect: RECT,
+    content_rect: RECT,
+    z_index: &'a str,
+    mode: &'a str,
+    source_url: Cow<'a, str>,
+    asset_dir: &'a Path,
+    click_through: bool,
+    icon_layer: &'a str,
+}
+
+/// Runtime settings baked into host windows and controllers at launch; a
+/// change to any of them rebuilds every host.
+#[derive(Debug, PartialEq)]
+struct LaunchSettings {
+    host_strategy: HostStrategy,
+    fallback_color: Option<[u8; 3]>,
+    capture_protection: bool,
+    show_error_wallpaper: bool,
+}
+
+impl LaunchSettings {
+    fn of(config: &AddonConfig) -> Self {
+        Self {
+            host_strategy: config.settings.runtime.host_strategy,
+            fallback_color: config.settings.rendering.fallback_color,
+            capture_protection: config.settings.rendering.capture_protection,
+            show_error_wallpaper: config.settings.development.show_error_wallpaper,
+        }
+    }
 }
 
 struct HostedWallpaper {
+    /// Config section this host was launched from (e.g. `wallpaper2`).
+    section: String,
+    /// Asset id actually hosted (per-monitor sections differ per host).
+    wallpaper_id: String,
+    monitor_index: usize,
     hwnd: HWND,
+    /// WorkerW (or fallback) window `hwnd` is parented to; `None` for overlays.
+    desktop_parent: Option<HWND>,
+    /// Prebuilt `native_monitor_info` payload, posted after each successful
+    /// navigation so reloaded pages get it too.
+    monitor_info: String,
     controller: ICoreWebView2Controller,
     webview: ICoreWebView2,
     source_url: String,
     monitor_rect: RECT,
+    /// Page layout area in screen coordinates; wider than `monitor_rect`
+    /// for one slice of a split span.
+    content_rect: RECT,
     monitor_id: Option<String>,
     pause_focus_mode: PauseMode,
     pause_maximized_mode: PauseMode,
     pause_fullscreen_mode: PauseMode,
     pause_battery_mode: PauseMode,
+    pause_presenting_mode: PauseMode,
+    pause_gpu_mode: PauseMode,
+    pause_when_process: Vec<String>,
     paused: bool,
+    /// When a lifted pause takes effect; staggered by `unpause_delay`.
+    resume_at: Option<Instant>,
     asset_dir: PathBuf,
+    /// Manifest `webview_args` that picked this host's environment.
+    webview_args: Vec<String>,
+    /// Events queued by this host's WebView2 handlers, drained each tick.
+    events: HostEventQueue,
+    /// Consecutive failed navigations; reset on the next success.
+    navigation_failures: u32,
+    navigation_retry_at: Option<Instant>,
+    snapshot_policy: SnapshotPolicy,
+    /// Pixels from the first successful capture of a `once` host, shared
+    /// with every later periodic save instead of calling `PrintWindow` again.
+    cached_capture: Option<Arc<[u8]>>,
+    /// When the current document last finished loading; `None` while a
+    /// navigation is in flight.  Periodic snapshots wait for it plus warmup.
+    loaded_at: Option<Instant>,
+    /// Last `snapshot_ready` posted by the page.  Once set, periodic saves
+    /// wait for a fresh signal instead of firing on the interval alone.
+    snapshot_ready_at: Option<Instant>,
+    /// Manifest `heartbeat`: the page promises to post `heartbeat`
+    /// regularly, and going quiet counts as hung.
+    heartbeat: bool,
+    /// Last `heartbeat` (or page load, which starts the clock); `None`
+    /// while a navigation is in flight.
+    last_heartbeat: Option<Instant>,
+    /// Set once a silent host has been reported, so it is logged once.
+    heartbeat_stalled: bool,
+    /// Set when a page finishes loading; the next editable check sends it
+    /// the current CSS vars once, since anything posted earlier was lost.
+    css_vars_pending: bool,
+    /// Layout mode in effect, which differs from the section's `mode` when
+    /// `tile` fell back to `fill` for an HTML asset.
+    mode: String,
+    /// Manifest `allow_remote`, kept so a crash restart applies it again.
+    allow_remote: bool,
+    /// `behind` / `over` as set on the section, or `auto`.
+    icon_layer: &'static str,
+    /// Resolved layer for this monitor; orders overlapping hosts when the
+    /// snapshot is composited.
+    z_index: String,
+    /// Hosted by `preview_wallpaper` rather than the config; never
+    /// snapshotted and dropped by the next `apply`.
+    preview: bool,
+    /// Section `opacity`; re-applied to the page after every navigation
+    /// unless the window alpha already carries it.
+    opacity: f32,
+    /// Section `click_through`, baked into the window style at launch.
+    click_through: bool,
+    /// Section `volume`, posted as `native_volume` after every load and
+    /// resume.
+    volume: f32,
+    /// `WDA_EXCLUDEFROMCAPTURE` took effect on `hwnd`; snapshots lift it
+    /// around their own capture.
+    capture_protected: bool,
+    /// When the WebView2 process was last restarted after a crash, pruned to
+    /// `CRASH_RESTART_WINDOW` for the crash-loop guard.
+    crash_restarts: Vec<Instant>,
+}
+
+/// Notifications raised inside WebView2 event handlers.  Handlers only push
+/// onto the host's queue; the tick loop owns all reactions to them.
+#[derive(Debug, Clone)]
+enum HostEvent {
+    NavigationCompleted {
+        success: bool,
+        status: COREWEBVIEW2_WEB_ERROR_STATUS,
+    },
+    /// JSON posted by the page via `chrome.webview.postMessage`.
+    WebMessage(String),
+    ProcessFailed {
+        kind: COREWEBVIEW2_PROCESS_FAILED_KIND,
+        /// `None` on runtimes without `ICoreWebView2ProcessFailedEventArgs2`.
+        reason: Option<COREWEBVIEW2_PROCESS_FAILED_REASON>,
+    },
+}
+
+type HostEventQueue = Rc<RefCell<Vec<HostEvent>>>;
+
+/// Manifest `"snapshot"` policy for periodic BMP saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnapshotPolicy {
+    Periodic,
+    Once,
+    Never,
+}
+
+impl SnapshotPolicy {
+    fn from_manifest(asset_dir: &Path) -> Self {
+        if asset_dir.as_os_str().is_empty() {
+            return SnapshotPolicy::Periodic;
+        }
+
+        let policy = fs::read_to_string(asset_dir.join("manifest.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|manifest| {
+                manifest
+                    .get("snapshot")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.trim().to_ascii_lowercase())
+            });
+
+        match policy.as_deref() {
+            Some("once") => SnapshotPolicy::Once,
+            Some("never") => SnapshotPolicy::Never,
+            _ => SnapshotPolicy::Periodic,
+        }
+    }
+}
+
+/// Manifest `"autoplay"` policy, applied as Chromium's `--autoplay-policy`
+/// switch.  Muted media may autoplay under every policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AutoplayPolicy {
+    Allow,
+    BlockIfNoUserGesture,
+    Block,
+}
+
+impl AutoplayPolicy {
+    /// Wallpapers never receive a user gesture, so autoplay with sound is
+    /// allowed unless the asset opts out.
+    fn from_manifest(asset_dir: &Path) -> Self {
+        if asset_dir.as_os_str().is_empty() {
+            return AutoplayPolicy::Allow;
+        }
+
+        let policy = fs::read_to_string(asset_dir.join("manifest.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|manifest| {
+                manifest
+                    .get("autoplay")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.trim().to_ascii_lowercase().replace('-', "_"))
+            });
+
+        match policy.as_deref() {
+            None | Some("allow") => AutoplayPolicy::Allow,
+            Some("block_if_no_user_gesture") => AutoplayPolicy::BlockIfNoUserGesture,
+            Some("block") => AutoplayPolicy::Block,
+            Some(other) => {
+                warn!(
+                    "[WALLPAPER][WEBVIEW] unknown autoplay policy '{}' in {}; allowing autoplay",
+                    other,
+                    asset_dir.display()
+                );
+                AutoplayPolicy::Allow
+            }
+        }
+    }
+
+    fn browser_arg(self) -> &'static str {
+        match self {
+            AutoplayPolicy::Allow => "--autoplay-policy=no-user-gesture-required",
+            AutoplayPolicy::BlockIfNoUserGesture => "--autoplay-policy=user-gesture-required",
+            AutoplayPolicy::Block => "--autoplay-policy=document-user-activation-required",
+        }
+    }
+}
+
+impl HostedWallpaper {
+    fn key(&self) -> HostKey<'_> {
+        HostKey {
+            section: &self.section,
+            wallpaper_id: &self.wallpaper_id,
+            monitor_index: self.monitor_index,
+            monitor_rect: self.monitor_rect,
+            content_rect: self.content_rect,
+            z_index: &self.z_index,
+            mode: &self.mode,
+            source_url: Cow::Borrowed(&self.source_url),
+            asset_dir: &self.asset_dir,
+            click_through: self.click_through,
+            icon_layer: self.icon_layer,
+        }
+    }
+
+    /// Take over the section settings that apply without a new window or
+    /// page (see `WallpaperRuntime::apply_changes`).
+    fn update_from_profile(&mut self, profile: &WallpaperConfig) {
+        self.pause_focus_mode = profile.pause_focus_mode;
+        self.pause_maximized_mode = profile.pause_maximized_mode;
+        self.pause_fullscreen_mode = profile.pause_fullscreen_mode;
+        self.pause_battery_mode = profile.pause_battery_mode;
+        self.pause_presenting_mode = profile.pause_presenting_mode;
+        self.pause_gpu_mode = profile.pause_gpu_mode;
+        self.pause_when_process = profile.pause_when_process.clone();
+
+        if self.volume != profile.volume {
+            self.volume = profile.volume;
+            let _ = post_webview_json(&self.webview, &volume_payload(self.volume));
+        }
+
+        if self.opacity == profile.opacity {
+            return;
+        }
+        self.opacity = profile.opacity;
+        if self.desktop_parent.is_none() {
+            if let Err(e) = set_overlay_opacity(self.hwnd, self.opacity) {
+                warn!("[WALLPAPER][APPLY] {}", e);
+            }
+            return;
+        }
+        let wide = to_wstring(&page_opacity_script(self.opacity));
+        if let Err(e) = unsafe {
+            self.webview.ExecuteScript(
+                PCWSTR(wide.as_ptr()),
+                None::<&ICoreWebView2ExecuteScriptCompletedHandler>,
+            )
+        } {
+            warn!("[WALLPAPER][APPLY] Opacity script failed for '{}': {:?}", self.source_url, e);
+        }
+    }
+
+    /// Dispatch one `chrome.webview.postMessage` from the page by its
+    /// `type`.  Unknown types are ignored, since pages may post messages
+    /// meant for other hosts of the SDK.
+    fn handle_page_message(&mut self, json: &str) {
+        let message = serde_json::from_str::<Value>(json).unwrap_or(Value::Null);
+        match message.get("type").and_then(|t| t.as_str()) {
+            Some("player_ready") => self.loaded_at = Some(Instant::now()),
+            Some("snapshot_ready") => self.snapshot_ready_at = Some(Instant::now()),
+            Some("heartbeat") => {
+                if self.heartbeat_stalled {
+                    warn!(
+                        "[WALLPAPER][HEARTBEAT] '{}' on monitor {} is responding again",
+                        self.source_url,
+                        self.monitor_index + 1
+                    );
+                }
+                self.last_heartbeat = Some(Instant::now());
+                self.heartbeat_stalled = false;
+            }
+            Some("request_reload") => {
+                if self.loaded_at.is_none_or(|at| at.elapsed() < REQUEST_RELOAD_MIN_UPTIME) {
+                    warn!(
+                        "[WALLPAPER][NAV] Ignoring request_reload from '{}' within {}s of loading",
+                        self.source_url,
+                        REQUEST_RELOAD_MIN_UPTIME.as_secs()
+                    );
+                    return;
+                }
+                warn!(
+                    "[WALLPAPER][NAV] '{}' on monitor {} requested a reload",
+                    self.source_url,
+                    self.monitor_index + 1
+                );
+                if let Err(e) = self.reload_page() {
+                    warn!("[WALLPAPER][NAV] {}", e);
+                }
+            }
+            // The editable poll notices the rewritten manifest and
+            // rebroadcasts the schema and vars to every host of the asset.
+            Some("set_editable") => {
+                let variable = message.get("variable").and_then(|v| v.as_str()).unwrap_or("");
+                let value = message.get("value").unwrap_or(&Value::Null);
+                match set_manifest_editable(&self.asset_dir, variable, value) {
+                    Ok(()) => warn!(
+                        "[WALLPAPER][EDITABLE] '{}' set {} = {} in {}",
+                        self.source_url,
+                        variable,
+                        value,
+                        self.asset_dir.display()
+                    ),
+                    Err(e) => warn!(
+                        "[WALLPAPER][EDITABLE] Rejected set_editable from '{}': {}",
+                        self.source_url, e
+                    ),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Navigate back to `source_url`, bypassing the cache, and reset the
+    /// per-document state that the next load fills in again.
+    fn reload_page(&mut self) -> std::result::Result<(), String> {
+        let url = add_reload_nonce(&self.source_url);
+        let wide = to_wstring(&url);
+        unsafe { self.webview.Navigate(PCWSTR(wide.as_ptr())) }
+            .map_err(|e| format!("Reload failed for '{}': {:?}", self.source_url, e))?;
+        self.loaded_at = None;
+        self.snapshot_ready_at = None;
+        self.last_heartbeat = None;
+        Ok(())
+    }
 }
 
 impl Drop for HostedWallpaper {
@@ -94,11 +697,67 @@ impl Drop for HostedWallpaper {
 
 /// Data shipped to the snapshot background thread for stitching + disk save.
 struct SnapshotJob {
-    captures: Vec<(RECT, Vec<u8>)>,
-    virtual_width: i32,
-    virtual_height: i32,
-    min_left: i32,
-    min_top: i32,
+    captures: Vec<(RECT, Arc<[u8]>)>,
+    /// Virtual-desktop area the stitched image covers.
+    bounds: RECT,
+    max_dimension: u32,
+    format: SnapshotFormat,
+    jpeg_quality: u8,
+    keep_history: u32,
+    /// Colour for virtual-screen areas no capture covers.
+    fill: [u8; 3],
+    /// Captures at these rects get the HDR gamma pass.
+    hdr_rects: Vec<RECT>,
+}
+
+/// One-job mailbox between the tick loop and the snapshot worker.  `put`
+/// replaces a job the worker has not started yet, so a burst of captures
+/// ends with the newest frame on disk instead of an older one.
+#[derive(Default)]
+struct SnapshotMailbox {
+    slot: Mutex<MailboxSlot>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct MailboxSlot {
+    pending: Option<SnapshotJob>,
+    /// Set by `close`; the worker exits once the pending job is done.
+    closed: bool,
+}
+
+impl SnapshotMailbox {
+    /// The slot, recovered if a panicking worker poisoned the lock; it only
+    /// ever holds a whole job or none.
+    fn slot(&self) -> MutexGuard<'_, MailboxSlot> {
+        self.slot.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn put(&self, job: SnapshotJob) {
+        self.slot().pending = Some(job);
+        self.ready.notify_one();
+    }
+
+    /// Let the worker finish the pending job, if any, and exit.
+    fn close(&self) {
+        self.slot().closed = true;
+        self.ready.notify_all();
+    }
+
+    /// Block until a job is available and take it; `None` once the mailbox
+    /// is closed and empty.
+    fn take(&self) -> Option<SnapshotJob> {
+        let mut slot = self.slot();
+        loop {
+            if let Some(job) = slot.pending.take() {
+                return Some(job);
+            }
+            if slot.closed {
+                return None;
+            }
+            slot = self.ready.wait(slot).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
 }
 
 pub struct WallpaperRuntime {
@@ -108,22 +767,143 @@ pub struct WallpaperRuntime {
     last_pause_tick: Instant,
     pause_check_interval: Duration,
     idle_pause_after: Option<Duration>,
+    idle_unpause_grace: Duration,
+    /// Gap between successive hosts resuming after a pause lifts.
+    unpause_delay: Duration,
+    /// Whether the last pause evaluation treated the system as idle.
+    idle_paused: bool,
+    /// When input was first seen again while idle-paused (grace countdown).
+    idle_resume_candidate_since: Option<Instant>,
+    battery_pause_percent: u8,
+    gpu_pause_percent: u8,
+    /// Whether the last pause evaluation had the GPU trigger active.
+    gpu_paused: bool,
+    /// When usage first crossed the pause (or, while paused, the resume)
+    /// threshold; the trigger flips once this is `GPU_PAUSE_SUSTAIN` old.
+    gpu_crossed_since: Option<Instant>,
+    battery_fps_limit: u32,
+    /// Last `native_fps_limit` value posted; `None` until the first evaluation.
+    sent_fps_limit: Option<u32>,
+    navigation_retries: u32,
+    /// `settings.rendering.heartbeat_timeout_ms`.
+    heartbeat_timeout: Duration,
+    heartbeat_reload: bool,
+    /// `settings.rendering.max_preload_wait_ms`; zero disables preloading.
+    max_preload_wait: Duration,
+    /// Set while a rebuild launches hosts next to the old ones; new host
+    /// windows stay hidden until `swap_in_preloaded` shows them.
+    preloading: bool,
+    show_error_wallpaper: bool,
+    /// Solid color for unresolved sections without an error page, and the
+    /// WebView background behind transparent pages.
+    fallback_color: Option<[u8; 3]>,
+    /// `settings.rendering.capture_protection`, applied to each new host.
+    capture_protection: bool,
+    /// Swap changed stylesheets in place instead of navigating when a live
+    /// reload only touched `.css` files.
+    hot_css_only: bool,
+    /// Longest edge of saved snapshots (0 = full resolution).
+    snapshot_max_dimension: u32,
+    snapshot_format: SnapshotFormat,
+    snapshot_jpeg_quality: u8,
+    /// Stitch snapshots against the full virtual screen (see `snapshot_size`).
+    snapshot_full_virtual_screen: bool,
+    /// Rotated snapshot copies kept beside the active file.
+    snapshot_keep_history: u32,
+    snapshot_hdr_gamma_correct: bool,
     log_pause_state_changes: bool,
     last_pause_snapshot_path: Option<PathBuf>,
     cached_sysdata: Value,
     cached_appdata: Value,
     last_editable_tick: Instant,
+    editable_poll_interval: Duration,
     editable_cache: HashMap<PathBuf, String>,
     /// Whether the last registry IPC call succeeded.
     /// When false, ALL data delivery to webviews is suppressed.
     registry_connected: bool,
+    /// Consecutive failed registry snapshots; drives the reconnect backoff.
+    registry_failures: u32,
+    /// While set and in the future, the registry is not queried at all.
+    registry_retry_at: Option<Instant>,
     last_sent_demands: HashSet<String>,
     /// Snapshot of monitor RECTs from the last apply(), used to detect layout changes.
     last_monitor_rects: Vec<RECT>,
+    /// Cached `enumerate_monitors` result; see `monitors()`.
+    monitors: Vec<MonitorArea>,
+    /// Set by `invalidate_monitors` (on `WM_DISPLAYCHANGE`) so the next
+    /// `monitors()` call enumerates again.
+    monitors_stale: bool,
+    /// Desktop rects of monitors in HDR mode, queried on first use after
+    /// `invalidate_monitors` while `snapshot_hdr_gamma_correct` is on.
+    hdr_monitor_rects: Option<Vec<RECT>>,
     /// Timer for periodic BMP saves (no SPI call — just keeps the file fresh).
     last_snapshot_tick: Instant,
-    /// Channel to the background stitching/save thread.
-    snapshot_tx: Option<mpsc::SyncSender<SnapshotJob>>,
+    snapshot_interval: Duration,
+    /// Settle time after a host loads before it may be snapshotted.
+    snapshot_warmup: Duration,
+    /// Mailbox of the background stitching/save thread; `None` if it
+    /// could not be started.
+    snapshot_mailbox: Option<Arc<SnapshotMailbox>>,
+    interactions: InteractionSettings,
+    last_mouse_tick: Instant,
+    /// Last cursor position + button mask delivered as `native_mouse`.
+    last_mouse_sample: Option<(POINT, u32)>,
+    audio_settings: AudioSettings,
+    /// Loopback capture thread; `None` while audio is disabled.
+    audio: Option<AudioMonitor>,
+    last_audio_tick: Instant,
+    /// Last quantized frame delivered as `native_audio`.
+    last_audio_frame: Option<AudioFrame>,
+    /// Manual pause state per section set over IPC; wins over automatic
+    /// evaluation until cleared.  Survives `apply()`.
+    section_pause_overrides: HashMap<String, bool>,
+    /// Set by the toggle-pause hotkey; pauses every host, above section
+    /// overrides.  Survives `apply()`.
+    pause_all_override: bool,
+    /// Set while the workstation is locked (`pause_all_on_lock`); pauses
+    /// every host like `pause_all_override`.  Survives `apply()`.
+    session_locked: bool,
+    /// `SM_REMOTESESSION` as of the last `apply()`.
+    remote_session: bool,
+    /// `settings.runtime.host_strategy`, consulted by `ensure_desktop_host`.
+    host_strategy: HostStrategy,
+    /// `settings.runtime.manage_static_wallpaper`; when off, snapshots are
+    /// never saved or applied as the Windows wallpaper.
+    manage_static_wallpaper: bool,
+    /// `settings.runtime.idle_screensaver`: idle lifts hosts over every
+    /// window instead of pausing them.
+    idle_screensaver: bool,
+    /// `GetLastInputInfo` tick seen when the screensaver came up; `Some`
+    /// while it is showing, and any newer input dismisses it.
+    screensaver_input: Option<u32>,
+    /// When the screensaver was last dismissed.  The idle reading in
+    /// `sysdata` can lag behind that input, so it stays down for at least
+    /// `idle_pause_after` afterwards.
+    screensaver_dismissed_at: Option<Instant>,
+    /// Set once the missing-runtime error has been logged, so every later
+    /// `apply()` (monitor changes, reloads) stays quiet about it.
+    webview2_missing_reported: bool,
+    /// Low-level mouse hook feeding `native_scroll`, installed while
+    /// `interactions.send_scroll` is on.
+    scroll_hook: Option<HHOOK>,
+    /// Low-level keyboard hook feeding `native_key`, installed while
+    /// `interactions.send_keys` is on.
+    key_hook: Option<HHOOK>,
+    /// Keys currently reported down, so auto-repeat doesn't resend them.
+    held_keys: HashSet<u32>,
+    /// One environment per manifest `webview_args` set, keyed by the sorted
+    /// args (empty for the default).  Declared after `hosted` so it is
+    /// dropped after every controller.
+    webview_environments: HashMap<Vec<String>, ICoreWebView2Environment>,
+}
+
+impl Drop for WallpaperRuntime {
+    /// Ends the snapshot worker once it has saved the job already queued.
+    fn drop(&mut self) {
+        if let Some(mailbox) = &self.snapshot_mailbox {
+            mailbox.close();
+        }
+    }
 }
 
 impl WallpaperRuntime {
@@ -140,29 +920,103 @@ impl WallpaperRuntime {
             last_pause_tick: Instant::now(),
             pause_check_interval: Duration::from_millis(500),
             idle_pause_after: None,
+            idle_unpause_grace: Duration::ZERO,
+            unpause_delay: Duration::ZERO,
+            idle_paused: false,
+            idle_resume_candidate_since: None,
+            battery_pause_percent: 0,
+            gpu_pause_percent: 90,
+            gpu_paused: false,
+            gpu_crossed_since: None,
+            battery_fps_limit: 30,
+            sent_fps_limit: None,
+            navigation_retries: 0,
+            heartbeat_timeout: Duration::from_secs(10),
+            max_preload_wait: Duration::from_secs(2),
+            preloading: false,
+            heartbeat_reload: true,
+            show_error_wallpaper: false,
+            fallback_color: None,
+            capture_protection: false,
+            hot_css_only: false,
+            snapshot_max_dimension: 0,
+            snapshot_format: SnapshotFormat::Bmp,
+            snapshot_jpeg_quality: 85,
+            snapshot_full_virtual_screen: false,
+            snapshot_keep_history: 0,
+            snapshot_hdr_gamma_correct: false,
             log_pause_state_changes: true,
             last_pause_snapshot_path: None,
             cached_sysdata: Value::Null,
             cached_appdata: Value::Null,
             last_editable_tick: Instant::now(),
+            editable_poll_interval: Duration::from_millis(250),
             editable_cache: HashMap::new(),
             registry_connected: false,
+            registry_failures: 0,
+            registry_retry_at: None,
             last_sent_demands: HashSet::new(),
             last_monitor_rects: Vec::new(),
+            monitors: Vec::new(),
+            monitors_stale: true,
+            hdr_monitor_rects: None,
             last_snapshot_tick: Instant::now(),
-            snapshot_tx: {
-                let (tx, rx) = mpsc::sync_channel::<SnapshotJob>(1);
+            snapshot_interval: Duration::from_secs(5),
+            snapshot_warmup: Duration::from_millis(1500),
+            snapshot_mailbox: {
+                let mailbox = Arc::new(SnapshotMailbox::default());
+                let worker_mailbox = Arc::clone(&mailbox);
                 thread::Builder::new()
                     .name("snapshot-worker".into())
-                    .spawn(move || snapshot_worker(rx))
-                    .ok();
-                Some(tx)
+                    .spawn(move || snapshot_worker(&worker_mailbox))
+                    .ok()
+                    .map(|_| mailbox)
             },
+            interactions: InteractionSettings::default(),
+            last_mouse_tick: Instant::now(),
+            last_mouse_sample: None,
+            audio_settings: AudioSettings::default(),
+            audio: None,
+            last_audio_tick: Instant::now(),
+            last_audio_frame: None,
+            section_pause_overrides: HashMap::new(),
+            pause_all_override: false,
+            session_locked: false,
+            webview_environments: HashMap::new(),
+            remote_session: is_remote_session(),
+            host_strategy: HostStrategy::Auto,
+            manage_static_wallpaper: true,
+            idle_screensaver: false,
+            screensaver_input: None,
+            screensaver_dismissed_at: None,
+            webview2_missing_reported: false,
+            scroll_hook: None,
+            key_hook: None,
+            held_keys: HashSet::new(),
         }
     }
 
-    pub fn apply(&mut self, config: &AddonConfig) {
-        self.hosted.clear();
+    /// Tear down every host and launch the config's wallpapers again.
+    pub fn apply(&mut self, config: &AddonConfig) -> ApplyReport {
+        self.apply_with(config, false)
+    }
+
+    /// Like `apply` for an edited config: when every host would be rebuilt
+    /// exactly as it is (see `update_hosts_in_place`), the hosts are kept
+    /// and only take over their section's pause modes and opacity, so a
+    /// settings-only edit doesn't flash the desktop.
+    pub fn apply_changes(&mut self, config: &AddonConfig) -> ApplyReport {
+        self.apply_with(config, true)
+    }
+
+    fn apply_with(&mut self, config: &AddonConfig, keep_unchanged: bool) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        // Kept hosts go back to the desktop before anything is compared or
+        // rebuilt; the next idle stretch brings the screensaver back.
+        self.stop_screensaver();
+        let host_settings_changed = self.launch_settings() != LaunchSettings::of(config);
+        self.section_pause_overrides
+            .retain(|section, _| config.wallpapers.iter().any(|w| w.section == *section));
         self.last_registry_tick = Instant::now();
         self.last_registry_payload = None;
         self.last_pause_tick = Instant::now();
@@ -175,6 +1029,40 @@ impl WallpaperRuntime {
                 config.settings.performance.pausing.idle_timeout_ms,
             ))
         };
+        self.idle_unpause_grace =
+            Duration::from_millis(config.settings.performance.pausing.idle_unpause_grace_ms);
+        self.unpause_delay =
+            Duration::from_millis(config.settings.performance.pausing.unpause_delay_ms);
+        self.idle_paused = false;
+        self.idle_resume_candidate_since = None;
+        self.battery_pause_percent = config.settings.performance.pausing.battery_pause_percent;
+        self.gpu_pause_percent = config.settings.performance.pausing.gpu_pause_percent;
+        self.gpu_paused = false;
+        self.gpu_crossed_since = None;
+        self.battery_fps_limit = config.settings.performance.battery_fps_limit;
+        self.sent_fps_limit = None;
+        self.navigation_retries = config.settings.rendering.navigation_retries;
+        self.heartbeat_timeout =
+            Duration::from_millis(config.settings.rendering.heartbeat_timeout_ms);
+        self.heartbeat_reload = config.settings.rendering.heartbeat_reload;
+        self.max_preload_wait = Duration::from_millis(config.settings.rendering.max_preload_wait_ms);
+        self.host_strategy = config.settings.runtime.host_strategy;
+        self.manage_static_wallpaper = config.settings.runtime.manage_static_wallpaper;
+        self.idle_screensaver = config.settings.runtime.idle_screensaver;
+        self.show_error_wallpaper = config.settings.development.show_error_wallpaper;
+        self.fallback_color = config.settings.rendering.fallback_color;
+        self.capture_protection = config.settings.rendering.capture_protection;
+        self.hot_css_only = config.settings.development.hot_css_only;
+        self.snapshot_max_dimension = config.settings.performance.snapshot.max_dimension;
+        self.snapshot_format = config.settings.performance.snapshot.format;
+        self.snapshot_jpeg_quality = config.settings.performance.snapshot.jpeg_quality;
+        self.snapshot_full_virtual_screen = config.settings.performance.snapshot.full_virtual_screen;
+        self.snapshot_keep_history = config.settings.performance.snapshot.keep_history;
+        self.snapshot_hdr_gamma_correct = config.settings.performance.snapshot.hdr_gamma_correct;
+        self.snapshot_interval = Duration::from_millis(config.settings.performance.snapshot.interval_ms);
+        self.snapshot_warmup =
+            Duration::from_millis(config.settings.performance.snapshot.warmup_ms);
+        self.editable_poll_interval = Duration::from_millis(config.settings.performance.editable_poll_ms);
         self.log_pause_state_changes = config.settings.diagnostics.log_pause_state_changes;
         self.last_pause_snapshot_path = None;
         self.cached_sysdata = Value::Null;
@@ -183,11 +1071,55 @@ impl WallpaperRuntime {
         self.editable_cache.clear();
         self.registry_connected = false;
         self.last_sent_demands.clear();
-        warn!("[WALLPAPER][APPLY] Cleared previous hosted wallpapers");
+        self.interactions = config.settings.performance.interactions.clone();
+        self.last_mouse_tick = Instant::now();
+        self.last_mouse_sample = None;
+        let send_scroll = self.interactions.send_scroll;
+        if set_hook(&mut self.scroll_hook, send_scroll, WH_MOUSE_LL, Some(scroll_hook_proc)) {
+            SCROLL_DELTA_Y.store(0, Ordering::Relaxed);
+            SCROLL_DELTA_X.store(0, Ordering::Relaxed);
+        }
+        let send_keys = self.interactions.send_keys;
+        if set_hook(&mut self.key_hook, send_keys, WH_KEYBOARD_LL, Some(key_hook_proc)) {
+            KEY_EVENTS.lock().unwrap_or_else(PoisonError::into_inner).clear();
+            self.held_keys.clear();
+        }
+        self.audio_settings = config.settings.performance.audio.clone();
+        self.last_audio_tick = Instant::now();
+        self.last_audio_frame = None;
+        if !self.audio_settings.enabled {
+            self.audio = None;
+        } else if let Some(audio) = &self.audio {
+            audio.update_settings(&self.audio_settings);
+        } else {
+            self.audio = AudioMonitor::start(&self.audio_settings);
+        }
+
+        // WorkerW hosting is unreliable over RDP (blank desktop, failed
+        // captures), so by default a remote session only gets the snapshot.
+        self.remote_session = is_remote_session();
+        if self.remote_session {
+            if !config.settings.runtime.allow_remote_session {
+                warn!(
+                    "[WALLPAPER][RDP] Remote Desktop session detected; showing the snapshot wallpaper instead of hosting (set settings.runtime.allow_remote_session: true to host anyway)"
+                );
+                self.hosted.clear();
+                self.last_monitor_rects = self.monitors().iter().map(|m| m.rect).collect();
+                report
+                    .errors
+                    .push("Remote Desktop session (allow_remote_session is off)".to_string());
+                return report;
+            }
+            warn!(
+                "[WALLPAPER][RDP] Remote Desktop session detected; hosting anyway (allow_remote_session) desktop_host={:?}",
+                ensure_desktop_host(self.host_strategy)
+            );
+        }
 
         if config.wallpapers.is_empty() {
             warn!("[WALLPAPER] No wallpaper sections found in config");
-            return;
+            self.hosted.clear();
+            return report;
         }
 
         let assets = fetch_wallpaper_assets();
@@ -195,10 +1127,12 @@ impl WallpaperRuntime {
             warn!("[WALLPAPER] No wallpaper assets found from IPC or local Assets/wallpaper");
         }
 
-        let monitors = enumerate_monitors();
+        let monitors = self.monitors().to_vec();
         if monitors.is_empty() {
             error!("[WALLPAPER] No monitors detected, aborting runtime apply");
-            return;
+            self.hosted.clear();
+            report.errors.push("No monitors detected".to_string());
+            return report;
         }
         // Snapshot current layout so monitors_changed() can detect rearrangements
         self.last_monitor_rects = monitors.iter().map(|m| m.rect).collect();
@@ -209,148 +1143,417 @@ impl WallpaperRuntime {
             config.enabled_wallpapers().len()
         );
 
-        let mut assigned_monitors = HashSet::<usize>::new();
-        let enabled_profiles = config.enabled_wallpapers();
+        // Without the Evergreen runtime every host would fail the same way;
+        // say so once and keep the last snapshot on the desktop instead.
+        if let Err(e) = webview2_runtime_version() {
+            self.report_webview2_missing(&e);
+            self.hosted.clear();
+            report.errors.push(format!("WebView2 runtime not found: {e}"));
+            return report;
+        }
+        self.webview2_missing_reported = false;
 
-        for priority in [0u8, 1u8, 2u8] {
-            for profile in enabled_profiles.iter().copied() {
-                if profile_priority(profile) != priority {
-                    continue;
-                }
-                self.launch_profile(profile, &assets, &monitors, &mut assigned_monitors);
+        let plan = plan_hosts(config, &assets, &monitors);
+        if keep_unchanged && !host_settings_changed && self.update_hosts_in_place(&plan) {
+            report.hosted = self.hosted.len();
+            warn!(
+                "[WALLPAPER][APPLY] Wallpaper assignment unchanged; kept {} host(s)",
+                report.hosted
+            );
+            return report;
+        }
+        // With preloading the old hosts keep showing until the new ones
+        // have loaded behind them.
+        let preload = !self.hosted.is_empty() && !self.max_preload_wait.is_zero();
+        let previous = if preload {
+            mem::take(&mut self.hosted)
+        } else {
+            self.hosted.clear();
+            warn!("[WALLPAPER][APPLY] Cleared previous hosted wallpapers");
+            Vec::new()
+        };
+
+        let launch_started = Instant::now();
+        self.preloading = preload;
+        for host in plan {
+            self.launch_planned(host, &mut report);
+        }
+        self.preloading = false;
+        if preload {
+            self.swap_in_preloaded(previous);
+        }
+        report.hosted = self.hosted.len();
+        warn!(
+            "[WALLPAPER][APPLY] Launched {} host(s) in {} ms",
+            self.hosted.len(),
+            launch_started.elapsed().as_millis()
+        );
+        if self.remote_session {
+            for hosted in &self.hosted {
+                warn!(
+                    "[WALLPAPER][RDP] host section='{}' monitor={} hwnd={:?} parent={:?} visible_rect=[l={},t={},r={},b={}]",
+                    hosted.section,
+                    hosted.monitor_index + 1,
+                    hosted.hwnd,
+                    hosted.desktop_parent,
+                    hosted.monitor_rect.left,
+                    hosted.monitor_rect.top,
+                    hosted.monitor_rect.right,
+                    hosted.monitor_rect.bottom
+                );
             }
         }
+        report
     }
 
-    fn launch_profile(
-        &mut self,
-        profile: &WallpaperConfig,
-        assets: &[RegistryAsset],
-        monitors: &[MonitorArea],
-        assigned_monitors: &mut HashSet<usize>,
-    ) {
+    /// Show the hosts launched hidden by a preloading rebuild once their
+    /// first navigation has completed (or `max_preload_wait` ran out), then
+    /// drop `previous`, destroying the old windows.
+    fn swap_in_preloaded(&mut self, previous: Vec<HostedWallpaper>) {
+        let started = Instant::now();
+        let loaded = self.wait_for_first_load(started + self.max_preload_wait);
+        for hosted in &self.hosted {
+            unsafe {
+                let _ = ShowWindow(hosted.hwnd, SW_SHOWNA);
+            }
+        }
+        drop(previous);
         warn!(
-            "[WALLPAPER][PROFILE] section='{}' wallpaper_id='{}' monitor_index={:?} mode='{}' z_index='{}'",
-            profile.section,
-            profile.wallpaper_id,
-            profile.monitor_index,
-            profile.mode,
-            profile.z_index
+            "[WALLPAPER][APPLY] Swapped in {} preloaded host(s) after {} ms{}",
+            self.hosted.len(),
+            started.elapsed().as_millis(),
+            if loaded { "" } else { " (wait capped; some still loading)" }
         );
+    }
 
-        let Some(asset) = resolve_asset(assets, &profile.wallpaper_id) else {
-            warn!(
-                "[WALLPAPER] Section '{}' references missing wallpaper_id '{}'",
-                profile.section,
-                profile.wallpaper_id
-            );
-            return;
+    /// Pump window messages until every host has a queued navigation
+    /// result or `deadline` passes; `true` when all of them loaded.  The
+    /// results stay queued for `tick_navigation`.  Thread messages (the
+    /// pause hotkey, `WM_QUIT`) belong to the main loop and are re-posted
+    /// for it afterwards.
+    fn wait_for_first_load(&self, deadline: Instant) -> bool {
+        let all_loaded = || {
+            self.hosted.iter().all(|hosted| {
+                hosted
+                    .events
+                    .borrow()
+                    .iter()
+                    .any(|event| matches!(event, HostEvent::NavigationCompleted { .. }))
+            })
         };
 
-        let Some(url) = resolve_asset_url(asset) else {
-            warn!(
-                "[WALLPAPER] Asset '{}' has no 'url' and no local index.html",
-                asset.id
-            );
-            return;
-        };
+        let mut deferred = Vec::new();
+        let mut msg = MSG::default();
+        while !all_loaded() && Instant::now() < deadline {
+            unsafe {
+                while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                    if msg.hwnd.is_invalid() {
+                        deferred.push(msg);
+                        continue;
+                    }
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            }
+            if deferred.iter().any(|m| m.message == WM_QUIT) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
 
-        warn!(
-            "[WALLPAPER][PROFILE] asset='{}' resolved url='{}'",
-            asset.id,
-            url
-        );
+        // `WM_QUIT` must not be posted directly; `PostQuitMessage` sets the
+        // quit flag instead, so it still arrives after the other messages.
+        for m in deferred {
+            unsafe {
+                if m.message == WM_QUIT {
+                    PostQuitMessage(m.wParam.0 as i32);
+                } else {
+                    let _ = PostMessageW(None, m.message, m.wParam, m.lParam);
+                }
+            }
+        }
+        all_loaded()
+    }
 
-        let targets = resolve_target_monitors(monitors, &profile.monitor_index, assigned_monitors);
-        if targets.is_empty() {
-            warn!(
-                "[WALLPAPER] Section '{}' has no resolved monitor targets",
-                profile.section
-            );
-            return;
+    /// The `LaunchSettings` the current hosts were created with.
+    fn launch_settings(&self) -> LaunchSettings {
+        LaunchSettings {
+            host_strategy: self.host_strategy,
+            fallback_color: self.fallback_color,
+            capture_protection: self.capture_protection,
+            show_error_wallpaper: self.show_error_wallpaper,
         }
+    }
 
-        for target in &targets {
-            assigned_monitors.insert(target.index);
+    /// Keep the current hosts when `plan` would relaunch the same wallpaper
+    /// with the same layout in every window, handing each its section's
+    /// in-place settings.  `false` (and nothing touched) when any host
+    /// would differ, a planned host failed to resolve, or a preview is up.
+    fn update_hosts_in_place(&mut self, plan: &[PlannedHost]) -> bool {
+        if self.hosted.is_empty()
+            || self.hosted.len() != plan.len()
+            || self.hosted.iter().any(|h| h.preview)
+        {
+            return false;
         }
 
-        if profile.mode.eq_ignore_ascii_case("span") && targets.len() > 1 {
-            let span_target = make_span_monitor_area(&targets);
-            match self.launch_into_monitor(profile, &span_target, &url, &asset.path) {
-                Ok(()) => warn!(
-                    "[WALLPAPER] Embedded '{}' as span across {} monitor(s)",
-                    profile.wallpaper_id,
-                    targets.len(),
-                ),
-                Err(e) => warn!(
-                    "[WALLPAPER] Failed to embed span '{}' ({} monitor(s)): {}",
-                    profile.wallpaper_id,
-                    targets.len(),
-                    e
-                ),
-            }
-            return;
+        let unchanged = self
+            .hosted
+            .iter()
+            .zip(plan)
+            .all(|(hosted, host)| host.key().is_some_and(|key| key == hosted.key()));
+        if !unchanged {
+            return false;
         }
 
-        for monitor in targets {
-            match self.launch_into_monitor(profile, monitor, &url, &asset.path) {
-                Ok(()) => warn!(
-                    "[WALLPAPER] Embedded '{}' into desktop host on monitor {}",
-                    profile.wallpaper_id,
-                    monitor.index + 1,
-                ),
-                Err(e) => warn!(
-                    "[WALLPAPER] Failed to embed '{}' for monitor {}: {}",
-                    profile.wallpaper_id,
-                    monitor.index + 1,
+        for (hosted, host) in self.hosted.iter_mut().zip(plan) {
+            hosted.update_from_profile(host.profile);
+        }
+        true
+    }
+
+    fn launch_planned(&mut self, host: PlannedHost, report: &mut ApplyReport) {
+        let target = host.describe_target();
+        let (asset, url) = match host.content {
+            Ok(resolved) => resolved,
+            Err(reason) => {
+                self.launch_error_wallpaper(
+                    host.profile,
+                    &host.monitor,
+                    &host.wallpaper_id,
+                    &reason,
+                );
+                report.skipped += 1;
+                report.errors.push(format!("'{}' on {}: {}", host.wallpaper_id, target, reason));
+                return;
+            }
+        };
+
+        let layout = HostLayout {
+            monitor: &host.monitor,
+            z_index: &host.z_index,
+            content_rect: host.content_rect,
+            mode: effective_mode(host.profile, &url),
+        };
+        let url = mode_fragment_url(&url, layout.mode);
+        match self.launch_into_monitor(
+            host.profile,
+            &layout,
+            &host.wallpaper_id,
+            &url,
+            &asset.path,
+        ) {
+            Ok(()) => warn!(
+                "[WALLPAPER] Embedded '{}' into desktop host on {}",
+                host.wallpaper_id,
+                target
+            ),
+            Err(e) => {
+                warn!(
+                    "[WALLPAPER] Failed to embed '{}' for {}: {}",
+                    host.wallpaper_id,
+                    target,
                     e
-                ),
+                );
+                if matches!(e, WebViewError::EnvironmentMissing(_)) {
+                    // The runtime vanished after the startup check; with
+                    // nothing hosted `apply_config` keeps the snapshot up.
+                    self.report_webview2_missing(&e);
+                }
+                report.skipped += 1;
+                report.errors.push(format!("'{}' on {}: {}", host.wallpaper_id, target, e));
             }
         }
     }
 
-    fn launch_into_monitor(
+    /// Host the built-in error document (or, with error pages off, the solid
+    /// `fallback_color` document) on `monitor` in place of a wallpaper that
+    /// could not be resolved.  It is a regular hosted wallpaper, so pause and
+    /// snapshot handling treat it like any other.
+    fn launch_error_wallpaper(
         &mut self,
         profile: &WallpaperConfig,
         monitor: &MonitorArea,
-        url: &str,
-        asset_dir: &Path,
-    ) -> std::result::Result<(), String> {
-        warn!(
-            "[WALLPAPER][EMBED] monitor={} primary={} rect=[l={},t={},r={},b={}]",
-            monitor.index + 1,
-            monitor.primary,
-            monitor.rect.left,
-            monitor.rect.top,
-            monitor.rect.right,
-            monitor.rect.bottom
-        );
-
-        let desktop = ensure_desktop_host()
-            .ok_or_else(|| "Failed to locate WorkerW desktop host window".to_string())?;
-        warn!("[WALLPAPER][EMBED] parent desktop host resolved: {:?}", desktop);
-
-        let parent_rect = window_rect(desktop)
-            .ok_or_else(|| "Failed to read desktop host window rect".to_string())?;
+        wallpaper_id: &str,
+        reason: &str,
+    ) {
+        let url = match self.fallback_color {
+            Some(color) if !self.show_error_wallpaper => solid_color_url(color),
+            _ => error_wallpaper_url(wallpaper_id, reason),
+        };
+        let layout = HostLayout {
+            monitor,
+            z_index: z_index_for_monitor(profile, monitor, None),
+            content_rect: monitor.rect,
+            mode: "fill",
+        };
+        match self.launch_into_monitor(profile, &layout, wallpaper_id, &url, Path::new("")) {
+            Ok(()) => warn!(
+                "[WALLPAPER] Showing error wallpaper for '{}' on monitor {}",
+                wallpaper_id,
+                monitor.index + 1
+            ),
+            Err(e) => warn!(
+                "[WALLPAPER] Failed to embed error wallpaper on monitor {}: {}",
+                monitor.index + 1,
+                e
+            ),
+        }
+    }
+
+    /// WebView2 environment shared by every host with the same manifest
+    /// `webview_args`, created on first use.  Environment creation
+    /// negotiates a browser process, so reusing it is what keeps
+    /// multi-monitor launches fast.
+    fn webview_environment(
+        &mut self,
+        args: &[String],
+    ) -> std::result::Result<ICoreWebView2Environment, WebViewError> {
+        if let Some(environment) = self.webview_environments.get(args) {
+            return Ok(environment.clone());
+        }
+
+        if args.is_empty() {
+            warn!("[WALLPAPER][WEBVIEW] creating shared environment");
+        } else {
+            warn!("[WALLPAPER][WEBVIEW] creating environment with args: {}", args.join(" "));
+        }
+        let environment = create_webview_environment(args)?;
+        self.webview_environments.insert(args.to_vec(), environment.clone());
+        Ok(environment)
+    }
+
+    /// Log the missing-runtime hint once until a later `apply()` finds the
+    /// runtime again.
+    fn report_webview2_missing(&mut self, e: &dyn fmt::Display) {
+        if !self.webview2_missing_reported {
+            error!(
+                "[WALLPAPER][WEBVIEW] WebView2 runtime not found ({}). Install the Evergreen WebView2 Runtime from {} and restart the addon; showing the snapshot wallpaper meanwhile",
+                e,
+                WEBVIEW2_DOWNLOAD_URL
+            );
+            self.webview2_missing_reported = true;
+        }
+    }
+
+    fn launch_into_monitor(
+        &mut self,
+        profile: &WallpaperConfig,
+        layout: &HostLayout,
+        wallpaper_id: &str,
+        url: &str,
+        asset_dir: &Path,
+    ) -> std::result::Result<(), WebViewError> {
+        let monitor = layout.monitor;
         warn!(
-            "[WALLPAPER][EMBED] parent rect=[l={},t={},r={},b={}]",
-            parent_rect.left,
-            parent_rect.top,
-            parent_rect.right,
-            parent_rect.bottom
+            "[WALLPAPER][EMBED] monitor={} primary={} rect=[l={},t={},r={},b={}]",
+            monitor.index + 1,
+            monitor.primary,
+            monitor.rect.left,
+            monitor.rect.top,
+            monitor.rect.right,
+            monitor.rect.bottom
         );
 
-        let hwnd = create_desktop_child_window(desktop, parent_rect, monitor.rect)?;
-        warn!("[WALLPAPER][EMBED] desktop child created: {:?}", hwnd);
+        let (hwnd, desktop_parent) = if is_overlay_z_index(layout.z_index) {
+            // Overlay hosts are top-level popups above app windows rather than
+            // WorkerW children, so they skip the desktop host entirely.
+            let hwnd = create_overlay_window(monitor.rect)?;
+            warn!("[WALLPAPER][EMBED] overlay window created: {:?}", hwnd);
+            if profile.opacity < 1.0 {
+                if let Err(e) = set_overlay_opacity(hwnd, profile.opacity) {
+                    warn!("[WALLPAPER][EMBED] {}", e);
+                }
+            }
+            (hwnd, None)
+        } else {
+            let strategy = profile
+                .icon_layer
+                .map(IconLayer::host_strategy)
+                .unwrap_or(self.host_strategy);
+            let desktop = ensure_desktop_host(strategy)
+                .ok_or_else(|| "Failed to locate WorkerW desktop host window".to_string())?;
+            warn!("[WALLPAPER][EMBED] parent desktop host resolved: {:?}", desktop);
+
+            let parent_rect = window_rect(desktop)
+                .ok_or_else(|| "Failed to read desktop host window rect".to_string())?;
+            warn!(
+                "[WALLPAPER][EMBED] parent rect=[l={},t={},r={},b={}]",
+                parent_rect.left,
+                parent_rect.top,
+                parent_rect.right,
+                parent_rect.bottom
+            );
+
+            let hwnd = create_desktop_child_window(desktop, parent_rect, monitor.rect)?;
+            warn!("[WALLPAPER][EMBED] desktop child created: {:?}", hwnd);
+
+            apply_host_style(hwnd, layout.z_index, profile.click_through)?;
+            if profile.icon_layer == Some(IconLayer::Over) {
+                // Progman also parents the icon view; stay above it.
+                raise_above_siblings(hwnd);
+            }
+            warn!(
+                "[WALLPAPER][EMBED] host style applied: hwnd={:?} z_index='{}' click_through={}",
+                hwnd,
+                layout.z_index,
+                profile.click_through
+            );
+            (hwnd, Some(desktop))
+        };
+
+        if self.preloading {
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_HIDE);
+            }
+        }
+
+        let capture_protected = self.capture_protection && set_capture_excluded(hwnd, true);
 
-        apply_host_style(hwnd, &profile.z_index)?;
+        let events: HostEventQueue = Rc::new(RefCell::new(Vec::new()));
+        let scale = monitor_rasterization_scale(monitor.rect);
+        let options = WebViewOptions {
+            rasterization_scale: scale,
+            background: self.fallback_color.unwrap_or([0, 0, 0]),
+            allow_remote: manifest_allows_remote(asset_dir),
+            asset_host: asset_host_for(url, asset_dir),
+        };
+        let autoplay = AutoplayPolicy::from_manifest(asset_dir);
         warn!(
-            "[WALLPAPER][EMBED] host style applied: hwnd={:?} z_index='{}'",
-            hwnd,
-            profile.z_index
+            "[WALLPAPER][WEBVIEW] monitor={} autoplay={:?}",
+            monitor.index, autoplay
         );
-
-        let controller = create_webview_controller(hwnd, monitor.rect, url)?;
+        let mut webview_args = manifest_webview_args(asset_dir);
+        webview_args.push(autoplay.browser_arg().to_string());
+        webview_args.sort();
+        let mut retried = false;
+        let (controller, url) = loop {
+            let created = self.webview_environment(&webview_args).and_then(|environment| {
+                create_webview_controller(
+                    &environment,
+                    hwnd,
+                    layout.webview_bounds(),
+                    options,
+                    url,
+                    &events,
+                )
+            });
+            match created {
+                Ok(created) => break created,
+                // A dead browser process poisons the shared environment;
+                // drop it and start fresh, once here and otherwise on the
+                // next launch.
+                Err(e) if e.is_transient() && !retried => {
+                    warn!("[WALLPAPER][WEBVIEW] {}; retrying on a fresh environment", e);
+                    self.webview_environments.remove(&webview_args);
+                    retried = true;
+                }
+                Err(e) => {
+                    self.webview_environments.remove(&webview_args);
+                    return Err(e);
+                }
+            }
+        };
         warn!("[WALLPAPER][EMBED] WebView2 controller attached to hwnd={:?}", hwnd);
 
         let webview = unsafe {
@@ -360,18 +1563,50 @@ impl WallpaperRuntime {
         };
 
         self.hosted.push(HostedWallpaper {
+            section: profile.section.clone(),
+            wallpaper_id: wallpaper_id.to_string(),
+            monitor_index: monitor.index,
             hwnd,
+            desktop_parent,
+            monitor_info: monitor_info_payload(monitor, scale),
             controller,
             webview,
-            source_url: url.to_string(),
+            source_url: url,
             monitor_rect: monitor.rect,
+            content_rect: layout.content_rect,
             monitor_id: None,
             pause_focus_mode: profile.pause_focus_mode,
             pause_maximized_mode: profile.pause_maximized_mode,
             pause_fullscreen_mode: profile.pause_fullscreen_mode,
             pause_battery_mode: profile.pause_battery_mode,
+            pause_presenting_mode: profile.pause_presenting_mode,
+            pause_gpu_mode: profile.pause_gpu_mode,
+            pause_when_process: profile.pause_when_process.clone(),
             paused: false,
+            resume_at: None,
             asset_dir: asset_dir.to_path_buf(),
+            webview_args,
+            events,
+            navigation_failures: 0,
+            navigation_retry_at: None,
+            snapshot_policy: SnapshotPolicy::from_manifest(asset_dir),
+            cached_capture: None,
+            loaded_at: None,
+            snapshot_ready_at: None,
+            heartbeat: manifest_heartbeat(asset_dir),
+            last_heartbeat: None,
+            heartbeat_stalled: false,
+            css_vars_pending: false,
+            mode: layout.mode.to_string(),
+            allow_remote: options.allow_remote,
+            icon_layer: profile.icon_layer.map(IconLayer::name).unwrap_or("auto"),
+            z_index: layout.z_index.to_string(),
+            preview: false,
+            opacity: profile.opacity,
+            click_through: profile.click_through,
+            volume: profile.volume,
+            capture_protected,
+            crash_restarts: Vec::new(),
         });
         warn!("[WALLPAPER][EMBED] host committed into runtime state");
         Ok(())
@@ -379,11 +1614,23 @@ impl WallpaperRuntime {
 
     pub fn tick_interactions(&mut self) -> bool {
         if self.hosted.is_empty() {
+            self.set_audio_active(false);
             return false;
         }
 
+        self.tick_navigation();
+        self.check_heartbeats();
+        self.check_screensaver_input();
+
         let mut unpaused_transition = false;
 
+        // Staggered resumes run on their own schedule, not the pause check
+        // interval.
+        if self.hosted.iter().any(|h| h.resume_at.is_some()) {
+            let all_paused_before = self.hosted.iter().all(|h| h.paused);
+            unpaused_transition = self.fire_due_resumes() && all_paused_before;
+        }
+
         let all_paused = self.hosted.iter().all(|h| h.paused);
 
         let demanded_sections = self.current_demanded_sections();
@@ -393,13 +1640,30 @@ impl WallpaperRuntime {
         }
 
         // ── Registry snapshot (determines connectivity) ─────────────
-        self.last_registry_tick = Instant::now();
+        // While reconnecting, only query once the backoff has elapsed.
+        let registry_due = self
+            .registry_retry_at
+            .map(|at| Instant::now() >= at)
+            .unwrap_or(true);
+        let snapshot = if registry_due {
+            self.last_registry_tick = Instant::now();
+            build_registry_snapshot_and_payload(&demanded_sections)
+        } else {
+            None
+        };
 
-        if let Some((sysdata, appdata, payload)) = build_registry_snapshot_and_payload(&demanded_sections) {
-            if !self.registry_connected {
+        if let Some((sysdata, appdata, payload)) = snapshot {
+            if !self.registry_connected && self.registry_failures > 0 {
+                warn!(
+                    "[WALLPAPER][REGISTRY] Reconnected after {} failed attempt(s)",
+                    self.registry_failures
+                );
+            } else if !self.registry_connected {
                 warn!("[WALLPAPER][REGISTRY] Connection established");
             }
             self.registry_connected = true;
+            self.registry_failures = 0;
+            self.registry_retry_at = None;
             self.cached_sysdata = sysdata;
             self.cached_appdata = appdata;
             let has_active_hosts = self.hosted.iter().any(|h| !h.paused);
@@ -418,7 +1682,7 @@ impl WallpaperRuntime {
                     // Send per-monitor bounds BEFORE registry data so cursor
                     // → local coordinate mapping is already set when the
                     // wallpaper's mouse subscription fires.
-                    let r = hosted.monitor_rect;
+                    let r = hosted.content_rect;
                     let bounds_payload = serde_json::json!({
                         "type": "native_monitor_bounds",
                         "left": r.left,
@@ -430,36 +1694,45 @@ impl WallpaperRuntime {
                     let _ = post_webview_json(&hosted.webview, &payload);
                 }
             }
-        } else {
-            if self.registry_connected {
-                warn!("[WALLPAPER][REGISTRY] Connection lost — suppressing all data delivery");
+        } else if registry_due {
+            if self.registry_failures == 0 {
+                warn!("[WALLPAPER][REGISTRY] Connection lost — suppressing all data delivery and reconnecting with backoff");
             }
             self.registry_connected = false;
+            self.registry_failures = self.registry_failures.saturating_add(1);
+            self.registry_retry_at = Some(Instant::now() + registry_retry_backoff(self.registry_failures));
         }
 
         // ── All interaction data gated behind registry connection ───
         if !self.registry_connected {
-            // Still evaluate pausing even without registry,
-            // but skip mouse/keyboard/audio delivery.
+            // Still evaluate pausing (local triggers and overrides work on
+            // the last cached registry data), but skip mouse/keyboard/audio
+            // delivery.
+            self.set_audio_active(false);
             if self.last_pause_tick.elapsed() >= self.pause_check_interval {
                 self.last_pause_tick = Instant::now();
+                unpaused_transition |= self.sync_pause_state_now(all_paused);
             }
-            return false;
+            return unpaused_transition;
         }
 
+        self.set_audio_active(!all_paused);
         if !all_paused {
-            // Addons do not generate independent runtime telemetry.
+            self.tick_mouse();
+            self.tick_scroll();
+            self.tick_keys();
+            self.tick_audio();
         }
 
         // ── Live editable CSS var updates (manifest.json watch) ──
-        if self.last_editable_tick.elapsed() >= Duration::from_millis(250) {
+        if self.last_editable_tick.elapsed() >= self.editable_poll_interval {
             self.last_editable_tick = Instant::now();
             self.check_editable_updates();
         }
 
         if self.last_pause_tick.elapsed() >= self.pause_check_interval {
             self.last_pause_tick = Instant::now();
-            unpaused_transition = self.sync_pause_state_now(all_paused);
+            unpaused_transition |= self.sync_pause_state_now(all_paused);
         }
 
         // ── Periodic BMP save (no SPI call) ────────────────────────
@@ -469,7 +1742,7 @@ impl WallpaperRuntime {
         // Uses PrintWindow on wallpaper HWNDs (correct content, no app
         // windows) and ships pixel buffers to a background thread for
         // the expensive stitching + disk write.
-        if !all_paused && self.last_snapshot_tick.elapsed() >= Duration::from_secs(5) {
+        if self.manage_static_wallpaper && !all_paused && self.snapshot_due() {
             self.last_snapshot_tick = Instant::now();
             self.save_snapshot_to_disk();
         }
@@ -477,1196 +1750,4657 @@ impl WallpaperRuntime {
         unpaused_transition
     }
 
-    pub fn hosted_all_paused(&self) -> bool {
-        self.hosted.iter().all(|h| h.paused)
-    }
+    /// Sample the cursor at `poll_interval_ms` and post `native_mouse` to the
+    /// unpaused host whose monitor contains it.  Moves below
+    /// `move_threshold_px` are dropped unless the button mask changed.
+    fn tick_mouse(&mut self) {
+        let settings = &self.interactions;
+        if !settings.send_move && !settings.send_click {
+            return;
+        }
+        if self.last_mouse_tick.elapsed() < Duration::from_millis(settings.poll_interval_ms.max(1)) {
+            return;
+        }
+        self.last_mouse_tick = Instant::now();
 
-    /// Capture each hosted wallpaper via `PrintWindow` on the main thread,
-    /// then ship the raw pixel buffers to a background thread for stitching
-    /// + BMP save.  Does NOT call `SPI_SETDESKWALLPAPER`.
-    ///
-    /// The main-thread work is only `PrintWindow` + `GetDIBits` per monitor
-    /// (fast GDI calls).  Skips silently if the worker is still busy.
-    pub fn save_snapshot_to_disk(&mut self) {
-        if self.hosted.is_empty() || self.hosted.iter().all(|h| h.paused) {
+        let mut cursor = POINT::default();
+        if unsafe { GetCursorPos(&mut cursor) }.is_err() {
             return;
         }
 
-        let min_left = self.hosted.iter().map(|h| h.monitor_rect.left).min().unwrap_or(0);
-        let min_top = self.hosted.iter().map(|h| h.monitor_rect.top).min().unwrap_or(0);
-        let max_right = self.hosted.iter().map(|h| h.monitor_rect.right).max().unwrap_or(1);
-        let max_bottom = self.hosted.iter().map(|h| h.monitor_rect.bottom).max().unwrap_or(1);
+        let buttons = if settings.send_click { mouse_button_mask() } else { 0 };
+        let (moved, buttons_changed) = match self.last_mouse_sample {
+            Some((prev, prev_buttons)) => {
+                let dx = (cursor.x - prev.x) as f32;
+                let dy = (cursor.y - prev.y) as f32;
+                let distance = (dx * dx + dy * dy).sqrt();
+                (
+                    distance > 0.0 && distance >= settings.move_threshold_px,
+                    buttons != prev_buttons,
+                )
+            }
+            None => (true, buttons != 0),
+        };
 
-        let virtual_width = (max_right - min_left).max(1);
-        let virtual_height = (max_bottom - min_top).max(1);
+        let should_send = (moved && settings.send_move) || buttons_changed;
+        if !should_send {
+            return;
+        }
+        self.last_mouse_sample = Some((cursor, buttons));
 
-        let mut captures: Vec<(RECT, Vec<u8>)> = Vec::with_capacity(self.hosted.len());
         for hosted in &self.hosted {
-            let width = (hosted.monitor_rect.right - hosted.monitor_rect.left).max(1);
-            let height = (hosted.monitor_rect.bottom - hosted.monitor_rect.top).max(1);
-            match capture_window_bgra(hosted.hwnd, width, height) {
-                Ok(pixels) => captures.push((hosted.monitor_rect, pixels)),
-                Err(e) => {
-                    warn!("[WALLPAPER][SNAP] PrintWindow capture failed: {}", e);
-                }
+            if hosted.paused {
+                continue;
+            }
+            let hit = hosted.monitor_rect;
+            if cursor.x < hit.left || cursor.x >= hit.right || cursor.y < hit.top || cursor.y >= hit.bottom {
+                continue;
             }
-        }
-        if captures.is_empty() {
-            return;
-        }
 
-        let job = SnapshotJob { captures, virtual_width, virtual_height, min_left, min_top };
-        if let Some(tx) = &self.snapshot_tx {
-            let _ = tx.try_send(job);
+            let r = hosted.content_rect;
+            let width = (r.right - r.left).max(1) as f64;
+            let height = (r.bottom - r.top).max(1) as f64;
+            let x = cursor.x - r.left;
+            let y = cursor.y - r.top;
+            let payload = serde_json::json!({
+                "type": "native_mouse",
+                "x": x,
+                "y": y,
+                "nx": x as f64 / width,
+                "ny": y as f64 / height,
+                "buttons": buttons,
+            })
+            .to_string();
+            let _ = post_webview_json(&hosted.webview, &payload);
         }
     }
 
-    /// Capture + save + apply as Windows wallpaper.  For shutdown only.
-    pub fn shutdown_snapshot(&mut self) {
-        match self.capture_paused_wallpaper_snapshot(true) {
-            Ok(()) => {
-                warn!("[WALLPAPER][SHUTDOWN] Captured and applied shutdown snapshot");
+    /// Post key transitions gathered by the hook as `native_key` to every
+    /// unpaused host.  The hook only records key-downs while the desktop
+    /// itself is focused, and ups for keys not held here are dropped, so
+    /// typing into applications never reaches wallpapers.
+    fn tick_keys(&mut self) {
+        if self.key_hook.is_none() {
+            return;
+        }
+        let events = mem::take(&mut *KEY_EVENTS.lock().unwrap_or_else(PoisonError::into_inner));
+        for (vk, down) in events {
+            // Auto-repeat arrives as more key-downs; only send transitions.
+            // Ups of keys pressed in other windows are not transitions either.
+            let changed = if down { self.held_keys.insert(vk) } else { self.held_keys.remove(&vk) };
+            if !changed {
+                continue;
             }
-            Err(e) => {
-                warn!("[WALLPAPER][SHUTDOWN] Live capture failed ({}), falling back to saved BMP", e);
-                self.apply_snapshot_as_wallpaper();
+            let payload = serde_json::json!({
+                "type": "native_key",
+                "key": key_name(vk),
+                "vk": vk,
+                "state": if down { "down" } else { "up" },
+                "down": down,
+            })
+            .to_string();
+            for hosted in self.hosted.iter().filter(|h| !h.paused) {
+                let _ = post_webview_json(&hosted.webview, &payload);
             }
         }
     }
 
-    /// Apply the saved snapshot BMP as the Windows desktop wallpaper via
-    /// `SPI_SETDESKWALLPAPER`.  Safe to call before WorkerW children exist
-    /// (startup) or after they've been destroyed (shutdown).
-    pub fn apply_snapshot_as_wallpaper(&self) {
-        let snapshot_dir = sentinel_assets_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("wallpaper")
-            .join("snapshots");
-        let snapshot_path = snapshot_dir.join("paused_wallpaper_snapshot.bmp");
-        if snapshot_path.exists() {
-            match apply_windows_wallpaper(&snapshot_path) {
-                Ok(()) => {
-                    warn!(
-                        "[WALLPAPER][SHUTDOWN] Applied snapshot wallpaper: {}",
-                        snapshot_path.display()
-                    );
-                }
-                Err(e) => {
-                    warn!("[WALLPAPER][SHUTDOWN] Failed to apply snapshot wallpaper: {}", e);
-                }
-            }
+    /// Post the wheel movement gathered by the hook as `native_scroll` to the
+    /// unpaused host under the cursor, once it reaches `scroll_threshold`.
+    fn tick_scroll(&mut self) {
+        if self.scroll_hook.is_none() {
+            return;
         }
-    }
+        let threshold = self.interactions.scroll_threshold.max(1) as i32;
+        if SCROLL_DELTA_Y.load(Ordering::Relaxed).abs() < threshold
+            && SCROLL_DELTA_X.load(Ordering::Relaxed).abs() < threshold
+        {
+            return;
+        }
+        let delta = SCROLL_DELTA_Y.swap(0, Ordering::Relaxed);
+        let delta_x = SCROLL_DELTA_X.swap(0, Ordering::Relaxed);
 
-    /// Re-enumerate monitors and return `true` if the layout (count or any
-    /// RECT) has changed since the last `apply()`.  This is cheap to call
-    /// periodically (a single Win32 `EnumDisplayMonitors` round-trip).
-    pub fn monitors_changed(&self) -> bool {
-        let current = enumerate_monitors();
-        let current_rects: Vec<RECT> = current.iter().map(|m| m.rect).collect();
-        if current_rects.len() != self.last_monitor_rects.len() {
-            return true;
+        let mut cursor = POINT::default();
+        if unsafe { GetCursorPos(&mut cursor) }.is_err() {
+            return;
         }
-        current_rects.iter().zip(self.last_monitor_rects.iter()).any(|(a, b)| {
-            a.left != b.left || a.top != b.top || a.right != b.right || a.bottom != b.bottom
+        let payload = serde_json::json!({
+            "type": "native_scroll",
+            "delta": delta,
+            "delta_x": delta_x,
         })
-    }
-
-    pub fn active_asset_dirs(&self) -> Vec<PathBuf> {
-        let mut seen = HashSet::new();
-        let mut dirs = Vec::new();
+        .to_string();
         for hosted in &self.hosted {
-            if seen.insert(hosted.asset_dir.clone()) {
-                dirs.push(hosted.asset_dir.clone());
+            let hit = hosted.monitor_rect;
+            if hosted.paused
+                || cursor.x < hit.left
+                || cursor.x >= hit.right
+                || cursor.y < hit.top
+                || cursor.y >= hit.bottom
+            {
+                continue;
             }
+            let _ = post_webview_json(&hosted.webview, &payload);
         }
-        dirs
     }
 
-    pub fn reload_wallpapers_for_asset_dir(&mut self, asset_dir: &Path) -> usize {
-        let mut reloaded = 0usize;
-        for hosted in &mut self.hosted {
-            if hosted.asset_dir != asset_dir {
+    /// React to queued WebView2 events: failed navigations are retried with
+    /// exponential backoff up to `navigation_retries` times, and hosts whose
+    /// browser or renderer process exited get a fresh controller.
+    fn tick_navigation(&mut self) {
+        let max_retries = self.navigation_retries;
+        let show_error_wallpaper = self.show_error_wallpaper;
+        let fps_limit_payload = self
+            .sent_fps_limit
+            .filter(|&fps| fps > 0)
+            .map(fps_limit_payload);
+        let mut crashed = Vec::<(usize, bool)>::new();
+        for (index, hosted) in self.hosted.iter_mut().enumerate() {
+            let events: Vec<HostEvent> = hosted.events.borrow_mut().drain(..).collect();
+            for event in events {
+                match event {
+                    HostEvent::WebMessage(json) => hosted.handle_page_message(&json),
+                    HostEvent::ProcessFailed { kind, reason } => {
+                        let browser_exited = kind == COREWEBVIEW2_PROCESS_FAILED_KIND_BROWSER_PROCESS_EXITED;
+                        let restart = browser_exited
+                            || kind == COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_EXITED;
+                        error!(
+                            "[WALLPAPER][CRASH] WebView2 {} for '{}' on monitor {} (reason: {}){}",
+                            process_failed_kind_name(kind),
+                            hosted.source_url,
+                            hosted.monitor_index + 1,
+                            reason.map(process_failed_reason_name).unwrap_or("unknown"),
+                            if restart { "; restarting" } else { "" }
+                        );
+                        if restart && !crashed.iter().any(|&(i, _)| i == index) {
+                            crashed.push((index, browser_exited));
+                        }
+                    }
+                    HostEvent::NavigationCompleted { success: true, .. } => {
+                        if hosted.navigation_failures > 0 {
+                            warn!(
+                                "[WALLPAPER][NAV] '{}' loaded after {} retr{}",
+                                hosted.source_url,
+                                hosted.navigation_failures,
+                                if hosted.navigation_failures == 1 { "y" } else { "ies" }
+                            );
+                        }
+                        hosted.navigation_failures = 0;
+                        hosted.navigation_retry_at = None;
+                        // The video player reports `player_ready` once its
+                        // first frame decoded; until then it would capture black.
+                        let waits_for_ready =
+                            hosted.source_url.starts_with(VIDEO_PLAYER_URL_PREFIX);
+                        hosted.loaded_at = (!waits_for_ready).then(Instant::now);
+                        hosted.last_heartbeat = Some(Instant::now());
+                        hosted.css_vars_pending = true;
+                        // Anything posted before the page loaded was lost, so
+                        // (re)send static monitor info once the document is up.
+                        let _ = post_webview_json(&hosted.webview, &hosted.monitor_info);
+                        let _ = post_webview_json(&hosted.webview, &volume_payload(hosted.volume));
+                        if let Some(payload) = &fps_limit_payload {
+                            let _ = post_webview_json(&hosted.webview, payload);
+                        }
+                        // Overlay windows fade natively (layered alpha); other
+                        // hosts need it in the page, which each load resets.
+                        if hosted.opacity < 1.0 && hosted.desktop_parent.is_some() {
+                            let wide = to_wstring(&page_opacity_script(hosted.opacity));
+                            if let Err(e) = unsafe {
+                                hosted.webview.ExecuteScript(
+                                    PCWSTR(wide.as_ptr()),
+                                    None::<&ICoreWebView2ExecuteScriptCompletedHandler>,
+                                )
+                            } {
+                                warn!(
+                                    "[WALLPAPER][NAV] Opacity script failed for '{}': {:?}",
+                                    hosted.source_url, e
+                                );
+                            }
+                        }
+                        if hosted.mode == "tile" {
+                            let wide = to_wstring(TILE_IMAGE_SCRIPT);
+                            if let Err(e) = unsafe {
+                                hosted.webview.ExecuteScript(
+                                    PCWSTR(wide.as_ptr()),
+                                    None::<&ICoreWebView2ExecuteScriptCompletedHandler>,
+                                )
+                            } {
+                                warn!(
+                                    "[WALLPAPER][NAV] Tile shim failed for '{}': {:?}",
+                                    hosted.source_url, e
+                                );
+                            }
+                        }
+                    }
+                    // Superseded by a newer Navigate (reload, retry); not a failure.
+                    HostEvent::NavigationCompleted { status, .. }
+                        if status == COREWEBVIEW2_WEB_ERROR_STATUS_OPERATION_CANCELED => {}
+                    HostEvent::NavigationCompleted { status, .. } => {
+                        if hosted.navigation_failures >= max_retries {
+                            error!(
+                                "[WALLPAPER][NAV] Navigation to '{}' failed (status={}); giving up after {} retries",
+                                hosted.source_url, status.0, hosted.navigation_failures
+                            );
+                            if show_error_wallpaper {
+                                let reason = format!(
+                                    "Navigation failed (WebView2 error status {}) after {} retries.",
+                                    status.0, hosted.navigation_failures
+                                );
+                                let url = error_wallpaper_url(&hosted.source_url, &reason);
+                                let wide = to_wstring(&url);
+                                let _ = unsafe { hosted.webview.Navigate(PCWSTR(wide.as_ptr())) };
+                                hosted.loaded_at = None;
+                                hosted.snapshot_ready_at = None;
+                                hosted.last_heartbeat = None;
+                            }
+                            continue;
+                        }
+
+                        let backoff = navigation_retry_backoff(hosted.navigation_failures);
+                        hosted.navigation_failures += 1;
+                        hosted.navigation_retry_at = Some(Instant::now() + backoff);
+                        warn!(
+                            "[WALLPAPER][NAV] Navigation to '{}' failed (status={}); retry {}/{} in {}ms",
+                            hosted.source_url,
+                            status.0,
+                            hosted.navigation_failures,
+                            max_retries,
+                            backoff.as_millis()
+                        );
+                    }
+                }
+            }
+
+            let Some(retry_at) = hosted.navigation_retry_at else {
+                continue;
+            };
+            if Instant::now() < retry_at {
                 continue;
             }
+            hosted.navigation_retry_at = None;
 
             let url = add_reload_nonce(&hosted.source_url);
             let wide = to_wstring(&url);
-            let result = unsafe { hosted.webview.Navigate(PCWSTR(wide.as_ptr())) };
-            match result {
-                Ok(_) => {
-                    reloaded += 1;
-                }
-                Err(e) => {
-                    warn!(
-                        "[WALLPAPER][WATCHER] Failed to reload wallpaper for '{}' via '{}': {:?}",
-                        hosted.asset_dir.display(),
-                        hosted.source_url,
-                        e
-                    );
-                }
+            if let Err(e) = unsafe { hosted.webview.Navigate(PCWSTR(wide.as_ptr())) } {
+                warn!("[WALLPAPER][NAV] Retry Navigate failed for '{}': {:?}", hosted.source_url, e);
             }
+            hosted.loaded_at = None;
+            hosted.snapshot_ready_at = None;
+            hosted.last_heartbeat = None;
         }
 
-        reloaded
+        // Every host of an environment shares its browser process, so its
+        // exit invalidates the environment for all of them; drop it once
+        // before recreating any.
+        for &(index, browser_exited) in &crashed {
+            if browser_exited {
+                let args = self.hosted[index].webview_args.clone();
+                self.webview_environments.remove(&args);
+            }
+        }
+        for (index, _) in crashed {
+            self.restart_crashed_host(index);
+        }
     }
 
-    pub fn has_registry_snapshot(&self) -> bool {
-        !self.cached_sysdata.is_null() && !self.cached_appdata.is_null()
-    }
+    /// Recreate the WebView2 controller of a host whose process exited, in
+    /// the same window and bounds, and navigate it back to `source_url`.
+    /// After `CRASH_RESTART_LIMIT` restarts within `CRASH_RESTART_WINDOW`
+    /// the host shows the error wallpaper (or fallback colour) instead.
+    fn restart_crashed_host(&mut self, index: usize) {
+        let hosted = &mut self.hosted[index];
+        hosted.crash_restarts.retain(|at| at.elapsed() < CRASH_RESTART_WINDOW);
+        let crash_loop = hosted.crash_restarts.len() >= CRASH_RESTART_LIMIT;
+        hosted.crash_restarts.push(Instant::now());
+
+        let url = if !crash_loop {
+            add_reload_nonce(&hosted.source_url)
+        } else {
+            error!(
+                "[WALLPAPER][CRASH] '{}' crashed {} times within {}s; giving up",
+                hosted.source_url,
+                CRASH_RESTART_LIMIT + 1,
+                CRASH_RESTART_WINDOW.as_secs()
+            );
+            let reason = format!(
+                "The wallpaper's WebView2 process crashed {} times within {} seconds.",
+                CRASH_RESTART_LIMIT + 1,
+                CRASH_RESTART_WINDOW.as_secs()
+            );
+            if self.show_error_wallpaper {
+                error_wallpaper_url(&hosted.wallpaper_id, &reason)
+            } else if let Some(color) = self.fallback_color {
+                solid_color_url(color)
+            } else {
+                return;
+            }
+        };
 
-    fn current_demanded_sections(&self) -> HashSet<String> {
-        if !self.hosted.iter().any(|h| !h.paused) {
-            return HashSet::new();
+        let args = self.hosted[index].webview_args.clone();
+        let environment = match self.webview_environment(&args) {
+            Ok(environment) => environment,
+            Err(e) => {
+                error!("[WALLPAPER][CRASH] Cannot recreate WebView2 environment: {}", e);
+                if matches!(e, WebViewError::EnvironmentMissing(_)) {
+                    self.report_webview2_missing(&e);
+                }
+                return;
+            }
+        };
+        let background = self.fallback_color.unwrap_or([0, 0, 0]);
+        let hosted = &mut self.hosted[index];
+        unsafe {
+            let _ = hosted.controller.Close();
+        }
+        let options = WebViewOptions {
+            rasterization_scale: monitor_rasterization_scale(hosted.monitor_rect),
+            background,
+            allow_remote: hosted.allow_remote,
+            asset_host: asset_host_for(&hosted.source_url, &hosted.asset_dir),
+        };
+        let controller = create_webview_controller(
+            &environment,
+            hosted.hwnd,
+            webview_bounds(hosted.monitor_rect, hosted.content_rect),
+            options,
+            &url,
+            &hosted.events,
+        )
+        .and_then(|(controller, _)| {
+            let webview = unsafe { controller.CoreWebView2() }
+                .map_err(|e| format!("WebView2 CoreWebView2 unavailable: {e:?}"))?;
+            Ok((controller, webview))
+        });
+        match controller {
+            Ok((controller, webview)) => {
+                hosted.controller = controller;
+                hosted.webview = webview;
+                hosted.loaded_at = None;
+                hosted.snapshot_ready_at = None;
+                hosted.last_heartbeat = None;
+                hosted.navigation_failures = 0;
+                hosted.navigation_retry_at = None;
+                // The new page starts unpaused; let the next pause pass
+                // re-send `native_pause` if it should be paused.
+                hosted.paused = false;
+                hosted.resume_at = None;
+                warn!(
+                    "[WALLPAPER][CRASH] Restarted WebView2 for '{}' on monitor {}",
+                    hosted.source_url,
+                    hosted.monitor_index + 1
+                );
+            }
+            Err(e) => {
+                self.webview_environments.remove(&args);
+                error!(
+                    "[WALLPAPER][CRASH] Failed to restart WebView2 for '{}': {}",
+                    hosted.source_url, e
+                );
+            }
         }
-
-        [
-            "time", "cpu", "gpu", "ram", "storage", "displays", "network", "wifi",
-            "bluetooth", "audio", "keyboard", "mouse", "power", "idle", "system",
-            "processes", "appdata",
-        ]
-        .into_iter()
-        .map(|s| s.to_string())
-        .collect()
-    }
-
-    fn send_tracking_demands(&self, demanded_sections: &HashSet<String>) {
-        let mut sections: Vec<String> = demanded_sections.iter().cloned().collect();
-        sections.sort();
-        let args = serde_json::json!({ "sections": sections });
-        let _ = request_quick("backend", "set_tracking_demands", Some(args));
     }
 
-    pub fn sync_pause_state_now(&mut self, all_paused_before: bool) -> bool {
-        let paused_before: Vec<bool> = self.hosted.iter().map(|h| h.paused).collect();
-        let cached_sysdata = self.cached_sysdata.clone();
-        let cached_appdata = self.cached_appdata.clone();
-        let states_changed = self.evaluate_and_apply_pause(&cached_sysdata, &cached_appdata);
-        if !states_changed {
-            return false;
-        }
+    /// Report hosts with manifest `heartbeat` that have not posted one for
+    /// `heartbeat_timeout`, and reload them with `heartbeat_reload`.
+    /// Paused pages may legitimately stop their loops, so the clock only
+    /// runs while a host is unpaused.
+    fn check_heartbeats(&mut self) {
+        let timeout = self.heartbeat_timeout;
+        for hosted in &mut self.hosted {
+            if !hosted.heartbeat {
+                continue;
+            }
+            let Some(last) = hosted.last_heartbeat else {
+                continue;
+            };
+            if hosted.paused {
+                hosted.last_heartbeat = Some(Instant::now());
+                continue;
+            }
+            if hosted.heartbeat_stalled || last.elapsed() < timeout {
+                continue;
+            }
 
-        let any_new_paused = self
-            .hosted
-            .iter()
-            .zip(paused_before.iter())
-            .any(|(hosted, was_paused)| !*was_paused && hosted.paused);
-        let all_paused_now = self.hosted.iter().all(|h| h.paused);
-        if any_new_paused {
-            if let Err(e) = self.capture_paused_wallpaper_snapshot(all_paused_now) {
-                warn!("[WALLPAPER][PAUSE] Snapshot capture/apply failed: {}", e);
+            hosted.heartbeat_stalled = true;
+            warn!(
+                "[WALLPAPER][HEARTBEAT] '{}' on monitor {} sent no heartbeat for {}ms{}",
+                hosted.source_url,
+                hosted.monitor_index + 1,
+                last.elapsed().as_millis(),
+                if self.heartbeat_reload { "; reloading" } else { "" }
+            );
+            if self.heartbeat_reload {
+                if let Err(e) = hosted.reload_page() {
+                    warn!("[WALLPAPER][HEARTBEAT] {}", e);
+                }
             }
         }
-        self.apply_host_visibility();
-        all_paused_before && !all_paused_now
     }
 
-    fn evaluate_and_apply_pause(&mut self, sysdata: &Value, appdata: &Value) -> bool {
-        if self.hosted.is_empty() {
-            return false;
+    fn set_audio_active(&self, active: bool) {
+        if let Some(audio) = &self.audio {
+            audio.set_active(active);
         }
+    }
 
-        let mut states_changed = false;
-
-        for hosted in &mut self.hosted {
-            hosted.monitor_id = resolve_monitor_id_for_rect(sysdata, hosted.monitor_rect);
+    /// Every `sample_interval_ms`, post the latest loopback frame as
+    /// `native_audio` to unpaused hosts if it moved more than
+    /// `change_threshold` since the last delivery.
+    fn tick_audio(&mut self) {
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        let settings = &self.audio_settings;
+        if self.last_audio_tick.elapsed() < Duration::from_millis(settings.sample_interval_ms.max(1)) {
+            return;
         }
+        self.last_audio_tick = Instant::now();
 
-        let foreground_snapshot = foreground_window_snapshot();
-        let shell_foreground = is_shell_foreground_active();
-        let mut global_states = global_window_states(appdata).unwrap_or_default();
+        let Some(frame) = audio.latest() else {
+            return;
+        };
+        let frame = frame.quantized(settings.quantize_decimals);
+        let changed = match &self.last_audio_frame {
+            Some(prev) => frame.max_delta(prev) > settings.change_threshold,
+            None => true,
+        };
+        if !changed {
+            return;
+        }
 
-        if let Some(snapshot) = foreground_snapshot {
-            global_states.focused |= snapshot.states.focused;
-            global_states.maximized |= snapshot.states.maximized;
-            global_states.fullscreen |= snapshot.states.fullscreen;
+        let payload = serde_json::json!({
+            "type": "native_audio",
+            "rms": frame.rms,
+            "bands": frame.bands,
+        })
+        .to_string();
+        for hosted in &self.hosted {
+            if hosted.paused {
+                continue;
+            }
+            let _ = post_webview_json(&hosted.webview, &payload);
         }
+        self.last_audio_frame = Some(frame);
+    }
 
-        if shell_foreground {
-            global_states.focused = false;
+    /// Force every host of `section` paused or running until
+    /// `clear_section_override` is called.  Returns the number of hosts affected.
+    pub fn set_section_paused(&mut self, section: &str, paused: bool) -> std::result::Result<usize, String> {
+        let count = self.hosted.iter().filter(|h| h.section == section).count();
+        if count == 0 {
+            return Err(format!("No hosted wallpaper for section '{section}'"));
         }
 
-        let idle_triggered = self
-            .idle_pause_after
-            .and_then(|threshold| {
-                sysdata
-                    .get("idle")
-                    .and_then(|idle| idle.get("idle_ms"))
-                    .and_then(|value| value.as_u64())
-                    .map(|idle_ms| idle_ms >= threshold.as_millis() as u64)
-            })
-            .unwrap_or(false);
+        let all_paused_before = self.hosted_all_paused();
+        self.section_pause_overrides.insert(section.to_string(), paused);
+        warn!(
+            "[WALLPAPER][PAUSE] Manual override: section='{}' paused={} ({} host(s))",
+            section, paused, count
+        );
+        self.sync_pause_state_now(all_paused_before);
+        Ok(count)
+    }
 
-        let on_battery = power_on_battery(sysdata);
+    /// Drop a manual override and return `section` to automatic pausing.
+    /// Returns whether an override existed.
+    pub fn clear_section_override(&mut self, section: &str) -> bool {
+        let existed = self.section_pause_overrides.remove(section).is_some();
+        if existed {
+            warn!("[WALLPAPER][PAUSE] Manual override cleared: section='{}'", section);
+            let all_paused_before = self.hosted_all_paused();
+            self.sync_pause_state_now(all_paused_before);
+        }
+        existed
+    }
 
-        for hosted in &mut self.hosted {
-            let mut local_states = hosted
-                .monitor_id
-                .as_deref()
-                .map(|id| monitor_window_states(appdata, id))
-                .unwrap_or_default();
+    /// Flip the runtime-wide manual pause.  Returns the new state.
+    pub fn toggle_pause_all(&mut self) -> bool {
+        self.pause_all_override = !self.pause_all_override;
+        warn!(
+            "[WALLPAPER][PAUSE] Manual pause-all {}",
+            if self.pause_all_override { "on" } else { "off" }
+        );
+        let all_paused_before = self.hosted_all_paused();
+        self.sync_pause_state_now(all_paused_before);
+        self.pause_all_override
+    }
 
-            if let Some(snapshot) = foreground_snapshot {
-                if rect_matches_monitor(hosted.monitor_rect, snapshot.monitor_rect) {
-                    local_states.focused |= snapshot.states.focused;
-                    local_states.maximized |= snapshot.states.maximized;
-                    local_states.fullscreen |= snapshot.states.fullscreen;
-                }
-            }
+    /// Pause every host while the session is locked and hand them back to
+    /// automatic pausing on unlock.  Pausing captures the snapshot (and
+    /// applies it as the desktop wallpaper) before the pages stop.
+    pub fn set_session_locked(&mut self, locked: bool) {
+        if self.session_locked == locked {
+            return;
+        }
+        self.session_locked = locked;
+        warn!(
+            "[WALLPAPER][PAUSE] Session {} — {} wallpapers",
+            if locked { "locked" } else { "unlocked" },
+            if locked { "pausing" } else { "resuming" }
+        );
+        let all_paused_before = self.hosted_all_paused();
+        self.sync_pause_state_now(all_paused_before);
+    }
 
-            if shell_foreground {
-                local_states.focused = false;
-            }
+    /// One entry per hosted wallpaper for the options UI.  Only reads cached
+    /// state, so it is cheap enough to poll every second.
+    pub fn status(&self) -> Value {
+        let hosts = self
+            .hosted
+            .iter()
+            .map(|h| {
+                serde_json::json!({
+                    "section": h.section,
+                    "wallpaper_id": h.wallpaper_id,
+                    "monitor_index": h.monitor_index,
+                    "monitor_rect": {
+                        "left": h.monitor_rect.left,
+                        "top": h.monitor_rect.top,
+                        "right": h.monitor_rect.right,
+                        "bottom": h.monitor_rect.bottom,
+                    },
+                    "paused": h.paused,
+                    "mode": h.mode,
+                    "icon_layer": h.icon_layer,
+                    "preview": h.preview,
+                    "source_url": h.source_url,
+                    "heartbeat": h.heartbeat.then(|| serde_json::json!({
+                        "alive": !h.heartbeat_stalled,
+                        "last_ms_ago": h.last_heartbeat.map(|at| at.elapsed().as_millis() as u64),
+                    })),
+                    "registry_connected": self.registry_connected,
+                })
+            })
+            .collect();
+        Value::Array(hosts)
+    }
 
-            let should_pause = idle_triggered
-                || mode_triggered(
-                    hosted.pause_focus_mode,
-                    local_states.focused,
-                    global_states.focused,
-                )
-                || mode_triggered(
-                    hosted.pause_maximized_mode,
-                    local_states.maximized,
-                    global_states.maximized,
-                )
-                || mode_triggered(
-                    hosted.pause_fullscreen_mode,
-                    local_states.fullscreen,
-                    global_states.fullscreen,
+    /// Move every host window back onto its rect after a work-area or theme
+    /// change, without a full reapply.
+    pub fn reposition_hosts(&self) {
+        for hosted in &self.hosted {
+            let rect = hosted.monitor_rect;
+            // Desktop children are positioned relative to their parent.
+            let (x, y) = match hosted.desktop_parent.and_then(window_rect) {
+                Some(parent) => (rect.left - parent.left, rect.top - parent.top),
+                None => (rect.left, rect.top),
+            };
+            let moved = unsafe {
+                SetWindowPos(
+                    hosted.hwnd,
+                    None,
+                    x,
+                    y,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
                 )
-                || mode_triggered(
-                    hosted.pause_battery_mode,
-                    on_battery,
-                    on_battery,
+            };
+            if let Err(e) = moved {
+                warn!(
+                    "[WALLPAPER][HOST] Reposition failed for '{}' on monitor {}: {:?}",
+                    hosted.section, hosted.monitor_index, e
                 );
-
-            if should_pause != hosted.paused {
-                hosted.paused = should_pause;
-                states_changed = true;
-                let payload = format!("{{\"type\":\"native_pause\",\"paused\":{}}}", should_pause);
-                let _ = post_webview_json(&hosted.webview, &payload);
-                if self.log_pause_state_changes {
-                    warn!(
-                        "[WALLPAPER][PAUSE] monitor={:?} paused={} idle_triggered={} on_battery={} (local: focused={} maximized={} fullscreen={}; global: focused={} maximized={} fullscreen={})",
-                        hosted.monitor_id,
-                        should_pause,
-                        idle_triggered,
-                        on_battery,
-                        local_states.focused,
-                        local_states.maximized,
-                        local_states.fullscreen,
-                        global_states.focused,
-                        global_states.maximized,
-                        global_states.fullscreen
-                    );
-                }
             }
         }
+    }
 
-        states_changed
+    /// `list_monitors` command: the cached monitor model as JSON.
+    pub fn list_monitors(&mut self) -> Value {
+        monitors_json(self.monitors())
     }
 
-    fn apply_host_visibility(&mut self) {
-        for hosted in &mut self.hosted {
-            unsafe {
-                let _ = hosted.controller.SetIsVisible(!hosted.paused);
-            }
-        }
+    pub fn hosted_all_paused(&self) -> bool {
+        self.hosted.iter().all(|h| h.paused)
     }
 
-    /// Check each hosted wallpaper's manifest.json for editable changes.
-    /// When the editable section changes, push a `native_css_vars` message
-    /// containing all CSS variable updates to the affected WebView2 instances.
-    fn check_editable_updates(&mut self) {
-        // Collect unique asset dirs
-        let mut seen = HashSet::new();
-        let mut dirs: Vec<PathBuf> = Vec::new();
-        for h in &self.hosted {
-            if seen.insert(h.asset_dir.clone()) {
-                dirs.push(h.asset_dir.clone());
-            }
-        }
+    /// All hosts paused with no staggered resume pending (or no hosts at
+    /// all), so the main loop can sleep longer without delaying anything.
+    pub fn is_idle(&self) -> bool {
+        self.hosted.iter().all(|h| h.paused && h.resume_at.is_none())
+    }
 
-        for dir in &dirs {
-            let manifest_path = dir.join("manifest.json");
-            let content = match fs::read_to_string(&manifest_path) {
-                Ok(c) => c,
-                Err(_) => continue,
-            };
+    /// Capture each hosted wallpaper via `PrintWindow` on the main thread,
+    /// then ship the raw pixel buffers to a background thread for stitching
+    /// + BMP save.  Does NOT call `SPI_SETDESKWALLPAPER`.
+    ///
+    /// The main-thread work is only `PrintWindow` + `GetDIBits` per monitor
+    /// (fast GDI calls).  If the worker is still busy the job waits, and a
+    /// newer one replaces it.
+    ///
+    /// Hosts whose manifest declares `"snapshot": "never"` are left out, and
+    /// `"once"` hosts reuse their first capture; when no host needs a fresh
+    /// capture the save is skipped entirely.
+    pub fn save_snapshot_to_disk(&mut self) {
+        if self.hosted.is_empty() || self.hosted.iter().all(|h| h.paused) {
+            return;
+        }
 
-            let manifest: Value = match serde_json::from_str(&content) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+        let needs_fresh_capture = self.hosted.iter().any(|h| match h.snapshot_policy {
+            SnapshotPolicy::Periodic => true,
+            SnapshotPolicy::Once => h.cached_capture.is_none(),
+            SnapshotPolicy::Never => false,
+        });
+        if !needs_fresh_capture {
+            return;
+        }
 
-            let editable = match manifest.get("editable") {
-                Some(e) => e,
-                None => continue,
-            };
+        // A host that has not painted yet captures as black and gets
+        // rejected; wait until every host that would be captured has settled
+        // rather than saving a snapshot with holes in it.
+        let warmup = self.snapshot_warmup;
+        let settling = self.hosted.iter().any(|h| {
+            h.snapshot_policy != SnapshotPolicy::Never
+                && h.cached_capture.is_none()
+                && h.loaded_at.is_none_or(|at| at.elapsed() < warmup)
+        });
+        if settling {
+            return;
+        }
 
-            let editable_json = serde_json::to_string(editable).unwrap_or_default();
+        let bounds = self.snapshot_bounds();
 
-            // Cache latest editable JSON for change tracking diagnostics; we still
-            // rebroadcast vars every tick so late-loading WebViews do not miss
-            // updates and fall back to default values.
-            let unchanged = self
-                .editable_cache
-                .get(dir)
-                .map(|prev| *prev == editable_json)
-                .unwrap_or(false);
-            if !unchanged {
-                self.editable_cache.insert(dir.clone(), editable_json);
+        let mut captures: Vec<(RECT, Arc<[u8]>)> = Vec::with_capacity(self.hosted.len());
+        for index in self.hosts_in_stacking_order() {
+            let hosted = &mut self.hosted[index];
+            if hosted.snapshot_policy == SnapshotPolicy::Never {
+                continue;
             }
-
-            // Extract CSS variable → value pairs from the editable tree
-            let vars = extract_css_vars(editable);
-            if vars.is_empty() {
+            if let Some(pixels) = &hosted.cached_capture {
+                captures.push((hosted.monitor_rect, Arc::clone(pixels)));
                 continue;
             }
 
-            let vars_obj = Value::Object(vars);
-            let payload = format!(
-                "{{\"type\":\"native_css_vars\",\"vars\":{}}}",
-                serde_json::to_string(&vars_obj).unwrap_or_else(|_| "{}".to_string())
-            );
-
-            for hosted in &self.hosted {
-                if hosted.asset_dir == *dir {
-                    let _ = post_webview_json(&hosted.webview, &payload);
+            let width = (hosted.monitor_rect.right - hosted.monitor_rect.left).max(1);
+            let height = (hosted.monitor_rect.bottom - hosted.monitor_rect.top).max(1);
+            match capture_host_bgra(hosted.hwnd, hosted.capture_protected, width, height) {
+                Ok(pixels) => {
+                    if hosted.snapshot_policy == SnapshotPolicy::Once
+                        && pixels.chunks_exact(4).any(|px| px[0] != 0 || px[1] != 0 || px[2] != 0)
+                    {
+                        hosted.cached_capture = Some(Arc::clone(&pixels));
+                    }
+                    captures.push((hosted.monitor_rect, pixels));
+                }
+                Err(e) => {
+                    warn!("[WALLPAPER][SNAP] PrintWindow capture failed: {}", e);
                 }
             }
         }
-    }
+        if captures.is_empty() {
+            return;
+        }
 
-    fn capture_paused_wallpaper_snapshot(
-        &mut self,
-        apply_to_desktop: bool,
-    ) -> std::result::Result<(), String> {
-        if self.hosted.is_empty() {
-            return Ok(());
+        let job = SnapshotJob {
+            captures,
+            bounds,
+            max_dimension: self.snapshot_max_dimension,
+            format: self.snapshot_format,
+            jpeg_quality: self.snapshot_jpeg_quality,
+            keep_history: self.snapshot_keep_history,
+            fill: self.fallback_color.unwrap_or([0, 0, 0]),
+            hdr_rects: self.hdr_rects_for_snapshot(),
+        };
+        if let Some(mailbox) = &self.snapshot_mailbox {
+            mailbox.put(job);
         }
+    }
 
-        let min_left = self
-            .hosted
-            .iter()
-            .map(|h| h.monitor_rect.left)
-            .min()
-            .ok_or_else(|| "No hosted monitor bounds".to_string())?;
-        let min_top = self
-            .hosted
-            .iter()
-            .map(|h| h.monitor_rect.top)
-            .min()
-            .ok_or_else(|| "No hosted monitor bounds".to_string())?;
-        let max_right = self
-            .hosted
-            .iter()
-            .map(|h| h.monitor_rect.right)
-            .max()
-            .ok_or_else(|| "No hosted monitor bounds".to_string())?;
-        let max_bottom = self
+    /// Whether the periodic snapshot should be saved this tick.  Wallpapers
+    /// that post `snapshot_ready` are captured just after a signal once the
+    /// interval has passed; if none arrives the save still happens at twice
+    /// the interval so the file never goes stale.
+    fn snapshot_due(&self) -> bool {
+        let elapsed = self.last_snapshot_tick.elapsed();
+        if elapsed < self.snapshot_interval {
+            return false;
+        }
+        let mut cooperating = self
             .hosted
             .iter()
-            .map(|h| h.monitor_rect.bottom)
-            .max()
-            .ok_or_else(|| "No hosted monitor bounds".to_string())?;
-
-        let virtual_width = (max_right - min_left).max(1);
-        let virtual_height = (max_bottom - min_top).max(1);
-        let mut stitched = RgbaImage::from_pixel(virtual_width as u32, virtual_height as u32, Rgba([0, 0, 0, 255]));
-        let mut has_non_black_pixel = false;
-
-        for hosted in &self.hosted {
-            let width = (hosted.monitor_rect.right - hosted.monitor_rect.left).max(1);
-            let height = (hosted.monitor_rect.bottom - hosted.monitor_rect.top).max(1);
-            let pixels = capture_window_bgra(hosted.hwnd, width, height)?;
-            let offset_x = (hosted.monitor_rect.left - min_left).max(0);
-            let offset_y = (hosted.monitor_rect.top - min_top).max(0);
-
-            for y in 0..height {
-                for x in 0..width {
-                    let src = ((y * width + x) * 4) as usize;
-                    if src + 3 >= pixels.len() {
-                        continue;
-                    }
-                    let b = pixels[src];
-                    let g = pixels[src + 1];
-                    let r = pixels[src + 2];
-                    if r != 0 || g != 0 || b != 0 {
-                        has_non_black_pixel = true;
-                    }
-                    let dst_x = (offset_x + x) as u32;
-                    let dst_y = (offset_y + y) as u32;
-                    if dst_x < stitched.width() && dst_y < stitched.height() {
-                        stitched.put_pixel(dst_x, dst_y, Rgba([r, g, b, 255]));
-                    }
-                }
-            }
+            .filter(|h| !h.paused && h.snapshot_policy != SnapshotPolicy::Never)
+            .filter_map(|h| h.snapshot_ready_at)
+            .peekable();
+        if cooperating.peek().is_none() {
+            return true;
         }
+        cooperating.any(|at| at.elapsed() < SNAPSHOT_READY_WINDOW)
+            || elapsed >= self.snapshot_interval * 2
+    }
 
-        if !has_non_black_pixel {
-            return Err("Captured wallpaper frame is fully black; refusing to apply snapshot wallpaper".to_string());
+    /// Capture + save + apply as Windows wallpaper.  For shutdown only.
+    pub fn shutdown_snapshot(&mut self) {
+        if !self.manage_static_wallpaper {
+            return;
         }
-
-        let snapshot_dir = sentinel_assets_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("wallpaper")
-            .join("snapshots");
-        let _ = fs::create_dir_all(&snapshot_dir);
-        let snapshot_path = snapshot_dir.join("paused_wallpaper_snapshot.bmp");
-        stitched
-            .save(&snapshot_path)
-            .map_err(|e| format!("Failed to save snapshot bitmap: {e}"))?;
-
-        if apply_to_desktop {
-            apply_windows_wallpaper(&snapshot_path)?;
-            self.last_pause_snapshot_path = Some(snapshot_path.clone());
-            if self.log_pause_state_changes {
-                warn!(
-                    "[WALLPAPER][PAUSE] Applied snapshot wallpaper: {}",
-                    snapshot_path.display()
-                );
+        match self.capture_paused_wallpaper_snapshot(true) {
+            Ok(()) => {
+                warn!("[WALLPAPER][SHUTDOWN] Captured and applied shutdown snapshot");
+            }
+            Err(e) => {
+                warn!("[WALLPAPER][SHUTDOWN] Live capture failed ({}), falling back to saved BMP", e);
+                self.apply_snapshot_as_wallpaper();
             }
-        } else if self.log_pause_state_changes {
-            warn!(
-                "[WALLPAPER][PAUSE] Captured snapshot only (desktop unchanged): {}",
-                snapshot_path.display()
-            );
         }
-        Ok(())
     }
-}
 
-fn capture_window_bgra(hwnd: HWND, width: i32, height: i32) -> std::result::Result<Vec<u8>, String> {
-    unsafe {
-        let src_dc = GetDC(Some(hwnd));
-        if src_dc.0.is_null() {
-            return Err("GetDC failed".to_string());
+    /// Apply the saved snapshot as the Windows desktop wallpaper via
+    /// `SPI_SETDESKWALLPAPER`.  Safe to call before WorkerW children exist
+    /// (startup) or after they've been destroyed (shutdown).  No-op when
+    /// `manage_static_wallpaper` is off.
+    pub fn apply_snapshot_as_wallpaper(&self) {
+        if !self.manage_static_wallpaper {
+            return;
         }
+        apply_latest_snapshot_wallpaper();
+    }
 
-        let mem_dc = CreateCompatibleDC(Some(src_dc));
-        if mem_dc.0.is_null() {
-            let _ = ReleaseDC(Some(hwnd), src_dc);
-            return Err("CreateCompatibleDC failed".to_string());
+    /// The monitor model, enumerated again only after `invalidate_monitors`.
+    fn monitors(&mut self) -> &[MonitorArea] {
+        if self.monitors_stale {
+            self.monitors = enumerate_monitors();
+            self.monitors_stale = false;
         }
+        &self.monitors
+    }
 
-        let bitmap = CreateCompatibleBitmap(src_dc, width, height);
-        if bitmap.0.is_null() {
-            let _ = DeleteDC(mem_dc);
-            let _ = ReleaseDC(Some(hwnd), src_dc);
-            return Err("CreateCompatibleBitmap failed".to_string());
+    /// Mark the cached monitor model out of date; called for
+    /// `WM_DISPLAYCHANGE`.
+    pub fn invalidate_monitors(&mut self) {
+        self.monitors_stale = true;
+        self.hdr_monitor_rects = None;
+    }
+
+    /// Rects of the monitors whose captures need the HDR gamma pass; empty
+    /// while `snapshot.hdr_gamma_correct` is off.
+    fn hdr_rects_for_snapshot(&mut self) -> Vec<RECT> {
+        if !self.snapshot_hdr_gamma_correct {
+            return Vec::new();
         }
+        self.hdr_monitor_rects
+            .get_or_insert_with(hdr_monitor_rects)
+            .clone()
+    }
 
-        let old = SelectObject(mem_dc, HGDIOBJ(bitmap.0));
-        let printed = PrintWindow(hwnd, mem_dc, PRINT_WINDOW_FLAGS(2)).as_bool();
-        if !printed {
-            let _ = BitBlt(mem_dc, 0, 0, width, height, Some(src_dc), 0, 0, SRCCOPY)
-                .map_err(|e| format!("BitBlt fallback failed: {e:?}"));
+    /// Return `true` if the layout (count or any RECT) has changed since the
+    /// last `apply()`.  Free until `invalidate_monitors` was called; only
+    /// then does it cost an `EnumDisplayMonitors` round-trip.
+    pub fn monitors_changed(&mut self) -> bool {
+        if !self.monitors_stale {
+            return false;
         }
+        let last_rects = std::mem::take(&mut self.last_monitor_rects);
+        let changed = {
+            let current = self.monitors();
+            current.len() != last_rects.len()
+                || current.iter().zip(last_rects.iter()).any(|(m, b)| {
+                    let a = m.rect;
+                    a.left != b.left || a.top != b.top || a.right != b.right || a.bottom != b.bottom
+                })
+        };
+        self.last_monitor_rects = last_rects;
+        changed
+    }
 
-        let mut bmi = BITMAPINFO::default();
-        bmi.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as u32;
-        bmi.bmiHeader.biWidth = width;
-        bmi.bmiHeader.biHeight = -height;
+    /// Whether the session switched between local and Remote Desktop since
+    /// the last `apply()`, which decides whether wallpapers are hosted.
+    pub fn remote_session_changed(&self) -> bool {
+        is_remote_session() != self.remote_session
+    }
+
+    /// `true` when a desktop-parented host no longer sits under a live
+    /// desktop host window, which is what an Explorer restart leaves behind.
+    pub fn desktop_host_lost(&self) -> bool {
+        self.hosted.iter().any(|hosted| {
+            let Some(parent) = hosted.desktop_parent else {
+                return false;
+            };
+            unsafe {
+                !IsWindow(Some(parent)).as_bool()
+                    || !IsWindow(Some(hosted.hwnd)).as_bool()
+                    || GetAncestor(hosted.hwnd, GA_PARENT) != parent
+            }
+        })
+    }
+
+    pub fn active_asset_dirs(&self) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut dirs = Vec::new();
+        for hosted in &self.hosted {
+            // Error hosts have no asset directory to watch.
+            if hosted.asset_dir.as_os_str().is_empty() {
+                continue;
+            }
+            if seen.insert(hosted.asset_dir.clone()) {
+                dirs.push(hosted.asset_dir.clone());
+            }
+        }
+        dirs
+    }
+
+    /// Reload the hosts showing `asset_dir`.  `changed` lists the files that
+    /// triggered the reload (empty = unknown); when `hot_css_only` is set and
+    /// they are all stylesheets, the CSS is swapped in place so page state
+    /// survives.  Returns the number of hosts reloaded.
+    pub fn reload_wallpapers_for_asset_dir(&mut self, asset_dir: &Path, changed: &[PathBuf]) -> usize {
+        let css_script = if self.hot_css_only {
+            css_hot_reload_script(asset_dir, changed)
+        } else {
+            None
+        };
+
+        let mut reloaded = 0usize;
+        for hosted in &mut self.hosted {
+            if hosted.asset_dir != asset_dir {
+                continue;
+            }
+
+            if let Some(script) = &css_script {
+                let wide = to_wstring(script);
+                let result = unsafe {
+                    hosted.webview.ExecuteScript(
+                        PCWSTR(wide.as_ptr()),
+                        None::<&ICoreWebView2ExecuteScriptCompletedHandler>,
+                    )
+                };
+                match result {
+                    Ok(()) => {
+                        reloaded += 1;
+                        continue;
+                    }
+                    Err(e) => warn!(
+                        "[WALLPAPER][WATCHER] CSS hot reload failed for '{}', navigating instead: {:?}",
+                        hosted.asset_dir.display(),
+                        e
+                    ),
+                }
+            }
+
+            let url = add_reload_nonce(&hosted.source_url);
+            let wide = to_wstring(&url);
+            let result = unsafe { hosted.webview.Navigate(PCWSTR(wide.as_ptr())) };
+            match result {
+                Ok(_) => {
+                    hosted.snapshot_policy = SnapshotPolicy::from_manifest(&hosted.asset_dir);
+                    hosted.heartbeat = manifest_heartbeat(&hosted.asset_dir);
+                    hosted.cached_capture = None;
+                    hosted.loaded_at = None;
+                    hosted.snapshot_ready_at = None;
+                    hosted.last_heartbeat = None;
+                    reloaded += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "[WALLPAPER][WATCHER] Failed to reload wallpaper for '{}' via '{}': {:?}",
+                        hosted.asset_dir.display(),
+                        hosted.source_url,
+                        e
+                    );
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    /// Reload every hosted wallpaper that has an asset directory.  Returns the
+    /// number of hosts reloaded.
+    pub fn reload_all_wallpapers(&mut self) -> usize {
+        let dirs = self.active_asset_dirs();
+        dirs.iter()
+            .map(|dir| self.reload_wallpapers_for_asset_dir(dir, &[]))
+            .sum()
+    }
+
+    /// Host `wallpaper_id` on `monitor_index` outside the config, for the
+    /// options UI's live preview.  The monitor's configured hosts are hidden
+    /// (not destroyed) and an earlier preview there is replaced; the next
+    /// `apply` (e.g. `clear_preview`) restores the configured state.
+    pub fn preview_wallpaper(
+        &mut self,
+        wallpaper_id: &str,
+        monitor_index: usize,
+    ) -> std::result::Result<Value, String> {
+        let monitor = self
+            .monitors()
+            .iter()
+            .find(|m| m.index == monitor_index)
+            .cloned()
+            .ok_or_else(|| format!("No monitor with index {monitor_index}"))?;
+        let assets = fetch_wallpaper_assets();
+        let (asset, url) = resolve_profile_asset(&assets, "preview", wallpaper_id)?;
+
+        self.hosted
+            .retain(|h| !(h.preview && h.monitor_index == monitor_index));
+        let profile = WallpaperConfig::preview(&asset.id);
+        let host = PlannedHost {
+            profile: &profile,
+            wallpaper_id: asset.id.clone(),
+            z_index: profile.z_index.clone(),
+            content_rect: monitor.rect,
+            monitor,
+            span_count: 1,
+            content: Ok((asset, url)),
+        };
+        let hosted_before = self.hosted.len();
+        let mut report = ApplyReport::default();
+        self.launch_planned(host, &mut report);
+        if self.hosted.len() == hosted_before {
+            return Err(report.errors.join("; "));
+        }
+
+        for hosted in &mut self.hosted {
+            if hosted.monitor_index == monitor_index && !hosted.preview {
+                unsafe {
+                    let _ = ShowWindow(hosted.hwnd, SW_HIDE);
+                }
+            }
+        }
+        let preview = self.hosted.last_mut().expect("preview host was just pushed");
+        preview.preview = true;
+        preview.snapshot_policy = SnapshotPolicy::Never;
+        warn!(
+            "[WALLPAPER][PREVIEW] '{}' on monitor {}",
+            preview.wallpaper_id,
+            monitor_index + 1
+        );
+        Ok(serde_json::json!({
+            "wallpaper_id": preview.wallpaper_id,
+            "monitor_index": monitor_index,
+            "source_url": preview.source_url,
+        }))
+    }
+
+    pub fn has_preview(&self) -> bool {
+        self.hosted.iter().any(|h| h.preview)
+    }
+
+    pub fn hosted_count(&self) -> usize {
+        self.hosted.len()
+    }
+
+    pub fn has_registry_snapshot(&self) -> bool {
+        !self.cached_sysdata.is_null() && !self.cached_appdata.is_null()
+    }
+
+    fn current_demanded_sections(&self) -> HashSet<String> {
+        if !self.hosted.iter().any(|h| !h.paused) {
+            // Keep tracking GPU load and processes so those triggers can
+            // let go.
+            let gpu = self.hosted.iter().any(|h| h.pause_gpu_mode != PauseMode::Off);
+            let processes = self.hosted.iter().any(|h| !h.pause_when_process.is_empty());
+            return [(gpu, "gpu"), (processes, "processes")]
+                .into_iter()
+                .filter(|&(wanted, _)| wanted)
+                .map(|(_, section)| section.to_string())
+                .collect();
+        }
+
+        [
+            "time", "cpu", "gpu", "ram", "storage", "displays", "network", "wifi",
+            "bluetooth", "audio", "keyboard", "mouse", "power", "idle", "system",
+            "processes", "appdata",
+        ]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    fn send_tracking_demands(&self, demanded_sections: &HashSet<String>) {
+        let mut sections: Vec<String> = demanded_sections.iter().cloned().collect();
+        sections.sort();
+        let args = serde_json::json!({ "sections": sections });
+        let _ = request_quick("backend", "set_tracking_demands", Some(args));
+    }
+
+    pub fn sync_pause_state_now(&mut self, all_paused_before: bool) -> bool {
+        let paused_before: Vec<bool> = self.hosted.iter().map(|h| h.paused).collect();
+        let cached_sysdata = self.cached_sysdata.clone();
+        let cached_appdata = self.cached_appdata.clone();
+        let states_changed = self.evaluate_and_apply_pause(&cached_sysdata, &cached_appdata);
+        let resumed = self.fire_due_resumes();
+        if !states_changed && !resumed {
+            return false;
+        }
+
+        let any_new_paused = self
+            .hosted
+            .iter()
+            .zip(paused_before.iter())
+            .any(|(hosted, was_paused)| !*was_paused && hosted.paused);
+        let all_paused_now = self.hosted.iter().all(|h| h.paused);
+        if any_new_paused && self.manage_static_wallpaper {
+            if let Err(e) = self.capture_paused_wallpaper_snapshot(all_paused_now) {
+                warn!("[WALLPAPER][PAUSE] Snapshot capture/apply failed: {}", e);
+            }
+        }
+        self.apply_host_visibility();
+        all_paused_before && !all_paused_now
+    }
+
+    /// Post `native_fps_limit` to every host when the power source changes:
+    /// `battery_fps_limit` on battery, 0 (unlimited) on AC.
+    fn update_fps_limit(&mut self, on_battery: bool) {
+        let fps = if on_battery { self.battery_fps_limit } else { 0 };
+        if self.sent_fps_limit == Some(fps) {
+            return;
+        }
+        // Nothing to lift if no limit was ever sent.
+        if self.sent_fps_limit.is_none() && fps == 0 {
+            self.sent_fps_limit = Some(0);
+            return;
+        }
+
+        warn!("[WALLPAPER][POWER] on_battery={} -> fps limit {}", on_battery, fps);
+        let payload = fps_limit_payload(fps);
+        for hosted in &self.hosted {
+            let _ = post_webview_json(&hosted.webview, &payload);
+        }
+        self.sent_fps_limit = Some(fps);
+    }
+
+    fn evaluate_and_apply_pause(&mut self, sysdata: &Value, appdata: &Value) -> bool {
+        if self.hosted.is_empty() {
+            return false;
+        }
+
+        let mut states_changed = false;
+        // Resuming hosts take consecutive `unpause_delay` slots after any
+        // already scheduled; the first free slot is now.
+        let now = Instant::now();
+        let mut next_resume = self
+            .hosted
+            .iter()
+            .filter_map(|h| h.resume_at)
+            .max()
+            .map(|at| (at + self.unpause_delay).max(now))
+            .unwrap_or(now);
+
+        for hosted in &mut self.hosted {
+            hosted.monitor_id = resolve_monitor_id_for_rect(sysdata, hosted.monitor_rect);
+        }
+
+        let foreground_snapshot = foreground_window_snapshot();
+        let shell_foreground = is_shell_foreground_active();
+        let mut global_states = global_window_states(appdata).unwrap_or_default();
+
+        if let Some(snapshot) = foreground_snapshot {
+            global_states.focused |= snapshot.states.focused;
+            global_states.maximized |= snapshot.states.maximized;
+            global_states.fullscreen |= snapshot.states.fullscreen;
+        }
+
+        if shell_foreground {
+            global_states.focused = false;
+        }
+
+        let mut idle_over_threshold = self
+            .idle_pause_after
+            .and_then(|threshold| {
+                sysdata
+                    .get("idle")
+                    .and_then(|idle| idle.get("idle_ms"))
+                    .and_then(|value| value.as_u64())
+                    .map(|idle_ms| idle_ms >= threshold.as_millis() as u64)
+            })
+            .unwrap_or(false);
+
+        // As a screensaver, idle shows the hosts instead of pausing them.
+        if self.idle_screensaver {
+            let recently_dismissed = self
+                .screensaver_dismissed_at
+                .zip(self.idle_pause_after)
+                .is_some_and(|(at, threshold)| at.elapsed() < threshold);
+            if idle_over_threshold && !recently_dismissed && self.screensaver_input.is_none() {
+                self.start_screensaver();
+            }
+            idle_over_threshold = false;
+        }
+        let screensaver_active = self.screensaver_input.is_some();
+
+        // Pausing on idle is immediate; resuming waits until input has been
+        // seen continuously for `idle_unpause_grace`.
+        let idle_triggered = if idle_over_threshold {
+            self.idle_resume_candidate_since = None;
+            true
+        } else if self.idle_paused {
+            let since = *self
+                .idle_resume_candidate_since
+                .get_or_insert_with(Instant::now);
+            since.elapsed() < self.idle_unpause_grace
+        } else {
+            false
+        };
+        if !idle_triggered {
+            self.idle_resume_candidate_since = None;
+        }
+        self.idle_paused = idle_triggered;
+
+        let on_battery = power_on_battery(sysdata);
+        let battery_percent = power_battery_percent(sysdata);
+        let battery_triggered =
+            on_battery && battery_below_threshold(battery_percent, self.battery_pause_percent);
+        self.update_fps_limit(on_battery);
+        let gpu_usage = gpu_usage_percent(sysdata);
+        let gpu_triggered = self.update_gpu_trigger(gpu_usage);
+        let running_processes = if self.hosted.iter().any(|h| !h.pause_when_process.is_empty()) {
+            running_process_keys(sysdata)
+        } else {
+            HashSet::new()
+        };
+        let notification_state = user_notification_state();
+        // Only presentation mode: `QUNS_QUIET_TIME` is the hour after a
+        // Windows install or upgrade, and full-screen apps (`QUNS_BUSY`)
+        // already have their own trigger.
+        let presenting = notification_state == Some(QUNS_PRESENTATION_MODE);
+
+        for hosted in &mut self.hosted {
+            let mut local_states = hosted
+                .monitor_id
+                .as_deref()
+                .map(|id| monitor_window_states(appdata, id))
+                .unwrap_or_default();
+
+            if let Some(snapshot) = foreground_snapshot {
+                if rect_matches_monitor(hosted.monitor_rect, snapshot.monitor_rect) {
+                    local_states.focused |= snapshot.states.focused;
+                    local_states.maximized |= snapshot.states.maximized;
+                    local_states.fullscreen |= snapshot.states.fullscreen;
+                }
+            }
+
+            if shell_foreground {
+                local_states.focused = false;
+            }
+
+            let process_trigger = hosted
+                .pause_when_process
+                .iter()
+                .find(|name| running_processes.contains(*name));
+            let auto_pause = !screensaver_active
+                && (idle_triggered
+                    || process_trigger.is_some()
+                    || mode_triggered(
+                        hosted.pause_focus_mode,
+                        local_states.focused,
+                        global_states.focused,
+                    )
+                    || mode_triggered(
+                        hosted.pause_maximized_mode,
+                        local_states.maximized,
+                        global_states.maximized,
+                    )
+                    || mode_triggered(
+                        hosted.pause_fullscreen_mode,
+                        local_states.fullscreen,
+                        global_states.fullscreen,
+                    )
+                    || mode_triggered(
+                        hosted.pause_battery_mode,
+                        battery_triggered,
+                        battery_triggered,
+                    )
+                    // Presentation state and GPU load are global, so per-monitor
+                    // and all-monitors behave the same.
+                    || mode_triggered(hosted.pause_presenting_mode, presenting, presenting)
+                    || mode_triggered(hosted.pause_gpu_mode, gpu_triggered, gpu_triggered));
+            // Manual overrides (hotkey, then IPC) win over every automatic trigger.
+            let pause_override = if self.pause_all_override || self.session_locked {
+                Some(true)
+            } else {
+                self.section_pause_overrides.get(&hosted.section).copied()
+            };
+            let should_pause = pause_override.unwrap_or(auto_pause);
+
+            // Pausing is immediate and cancels a resume still waiting for
+            // its slot.
+            if should_pause {
+                hosted.resume_at = None;
+            }
+            if should_pause != hosted.paused && hosted.resume_at.is_none() {
+                states_changed = true;
+                if should_pause {
+                    hosted.paused = true;
+                    let _ = post_webview_json(&hosted.webview, &pause_payload(true));
+                    if let (None, Some(process)) = (pause_override, process_trigger) {
+                        warn!(
+                            "[WALLPAPER][PAUSE] section='{}' monitor={:?} paused: '{}' is running",
+                            hosted.section, hosted.monitor_id, process
+                        );
+                    }
+                } else {
+                    // Stays paused until `fire_due_resumes` reaches its slot.
+                    hosted.resume_at = Some(next_resume);
+                    next_resume += self.unpause_delay;
+                }
+                if self.log_pause_state_changes {
+                    warn!(
+                        "[WALLPAPER][PAUSE] section='{}' monitor={:?} paused={} override={:?} idle_triggered={} on_battery={} battery_percent={:?} gpu_triggered={} gpu_usage={:?} presenting={} ({}) (local: focused={} maximized={} fullscreen={}; global: focused={} maximized={} fullscreen={})",
+                        hosted.section,
+                        hosted.monitor_id,
+                        should_pause,
+                        pause_override,
+                        idle_triggered,
+                        on_battery,
+                        battery_percent,
+                        gpu_triggered,
+                        gpu_usage,
+                        presenting,
+                        notification_state.map(notification_state_name).unwrap_or("unknown"),
+                        local_states.focused,
+                        local_states.maximized,
+                        local_states.fullscreen,
+                        global_states.focused,
+                        global_states.maximized,
+                        global_states.fullscreen
+                    );
+                }
+            }
+        }
+
+        states_changed
+    }
+
+    /// Advance the GPU trigger with the latest usage sample: it turns on
+    /// after usage stays at or above `gpu_pause_percent` for
+    /// `GPU_PAUSE_SUSTAIN`, and off after it stays `GPU_RESUME_MARGIN`
+    /// below that for as long.  No sample leaves the trigger as it was.
+    fn update_gpu_trigger(&mut self, usage: Option<f64>) -> bool {
+        if !self.hosted.iter().any(|h| h.pause_gpu_mode != PauseMode::Off) {
+            self.gpu_paused = false;
+            self.gpu_crossed_since = None;
+            return false;
+        }
+        let Some(usage) = usage else {
+            return self.gpu_paused;
+        };
+
+        let threshold = self.gpu_pause_percent as f64;
+        let crossed = if self.gpu_paused {
+            usage < threshold - GPU_RESUME_MARGIN
+        } else {
+            usage >= threshold
+        };
+        if !crossed {
+            self.gpu_crossed_since = None;
+            return self.gpu_paused;
+        }
+
+        let since = *self.gpu_crossed_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= GPU_PAUSE_SUSTAIN {
+            self.gpu_paused = !self.gpu_paused;
+            self.gpu_crossed_since = None;
+            warn!(
+                "[WALLPAPER][PAUSE] GPU trigger {} at {:.0}% usage (threshold {}%)",
+                if self.gpu_paused { "on" } else { "off" },
+                usage,
+                self.gpu_pause_percent
+            );
+        }
+        self.gpu_paused
+    }
+
+    /// Resume every host whose scheduled `resume_at` has passed. Returns
+    /// true when at least one host was resumed.
+    fn fire_due_resumes(&mut self) -> bool {
+        let now = Instant::now();
+        let mut resumed = false;
+        for hosted in &mut self.hosted {
+            match hosted.resume_at {
+                Some(at) if at <= now => hosted.resume_at = None,
+                _ => continue,
+            }
+            hosted.paused = false;
+            resumed = true;
+            let _ = post_webview_json(&hosted.webview, &pause_payload(false));
+            // Pausing may have muted the page's media; restore the section
+            // volume rather than whatever the page falls back to.
+            let _ = post_webview_json(&hosted.webview, &volume_payload(hosted.volume));
+            unsafe {
+                let _ = hosted.controller.SetIsVisible(true);
+            }
+        }
+        resumed
+    }
+
+    fn apply_host_visibility(&mut self) {
+        for hosted in &mut self.hosted {
+            unsafe {
+                let _ = hosted.controller.SetIsVisible(!hosted.paused);
+            }
+        }
+    }
+
+    /// Check each hosted wallpaper's manifest.json for editable changes.
+    /// When the editable section changes, push the whole tree as
+    /// `native_editable_schema` and a `native_css_vars` message containing
+    /// all CSS variable updates to the affected WebView2 instances.
+    /// Freshly loaded pages get both once; otherwise nothing is sent while
+    /// the manifest is unchanged.
+    fn check_editable_updates(&mut self) {
+        let dirs = self.active_asset_dirs();
+
+        for dir in &dirs {
+            let manifest_path = dir.join("manifest.json");
+            let content = match fs::read_to_string(&manifest_path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+
+            let manifest: Value = match serde_json::from_str(&content) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            let editable = match manifest.get("editable") {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let editable_json = serde_json::to_string(editable).unwrap_or_default();
+            let unchanged = self
+                .editable_cache
+                .get(dir)
+                .map(|prev| *prev == editable_json)
+                .unwrap_or(false);
+            if !unchanged {
+                self.editable_cache.insert(dir.clone(), editable_json);
+            }
+
+            let any_pending = self
+                .hosted
+                .iter()
+                .any(|h| h.asset_dir == *dir && h.css_vars_pending);
+            if unchanged && !any_pending {
+                continue;
+            }
+
+            // The schema goes first so a page building its own controls has
+            // them before the values arrive.
+            let schema_payload = serde_json::json!({
+                "type": "native_editable_schema",
+                "editable": editable,
+            })
+            .to_string();
+
+            // Extract CSS variable → value pairs from the editable tree
+            let vars = extract_css_vars(editable);
+            let vars_payload = (!vars.is_empty()).then(|| {
+                format!(
+                    "{{\"type\":\"native_css_vars\",\"vars\":{}}}",
+                    serde_json::to_string(&Value::Object(vars)).unwrap_or_else(|_| "{}".to_string())
+                )
+            });
+
+            for hosted in &mut self.hosted {
+                if hosted.asset_dir == *dir && (!unchanged || hosted.css_vars_pending) {
+                    let _ = post_webview_json(&hosted.webview, &schema_payload);
+                    if let Some(payload) = &vars_payload {
+                        let _ = post_webview_json(&hosted.webview, payload);
+                    }
+                    hosted.css_vars_pending = false;
+                }
+            }
+        }
+
+        // Hosts without editable vars have nothing to catch up on.
+        for hosted in &mut self.hosted {
+            hosted.css_vars_pending = false;
+        }
+    }
+
+    /// Virtual-desktop area the stitched snapshot covers: the hosted
+    /// monitors' bounding box, or the whole virtual screen with
+    /// `snapshot.full_virtual_screen` so monitors without a host are covered
+    /// too and the tile period matches what Windows uses.  A snapshot that
+    /// `snapshot.max_dimension` downscales is spanned, and Windows spans over
+    /// the whole virtual screen, so it covers that too.
+    fn snapshot_bounds(&self) -> RECT {
+        if let Some(screen) = virtual_screen_rect() {
+            let (width, height) = (screen.right - screen.left, screen.bottom - screen.top);
+            if self.snapshot_full_virtual_screen
+                || snapshot_scale(width, height, self.snapshot_max_dimension) < 1.0
+            {
+                return screen;
+            }
+        }
+
+        let min_left = self.hosted.iter().map(|h| h.monitor_rect.left).min().unwrap_or(0);
+        let min_top = self.hosted.iter().map(|h| h.monitor_rect.top).min().unwrap_or(0);
+        let max_right = self.hosted.iter().map(|h| h.monitor_rect.right).max().unwrap_or(1);
+        let max_bottom = self.hosted.iter().map(|h| h.monitor_rect.bottom).max().unwrap_or(1);
+        RECT {
+            left: min_left,
+            top: min_top,
+            right: max_right.max(min_left + 1),
+            bottom: max_bottom.max(min_top + 1),
+        }
+    }
+
+    /// Lift every host out of the desktop into a topmost window over its
+    /// monitor.  Desktop children go up in stacking order, then overlays are
+    /// raised again so they keep covering the rest.
+    fn start_screensaver(&mut self) {
+        let Some(input_tick) = last_input_tick() else {
+            warn!("[WALLPAPER][SCREENSAVER] GetLastInputInfo failed; not starting");
+            return;
+        };
+        let order = self.hosts_in_stacking_order();
+        let (overlays, children): (Vec<usize>, Vec<usize>) = order
+            .into_iter()
+            .partition(|&index| self.hosted[index].desktop_parent.is_none());
+        for index in children.into_iter().chain(overlays) {
+            let hosted = &self.hosted[index];
+            let result = if hosted.desktop_parent.is_some() {
+                promote_to_screensaver(hosted.hwnd, hosted.monitor_rect)
+            } else {
+                let flags = SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE;
+                unsafe { SetWindowPos(hosted.hwnd, Some(HWND_TOPMOST), 0, 0, 0, 0, flags) }
+                    .map_err(|e| format!("SetWindowPos failed: {e}"))
+            };
+            match result {
+                Ok(()) => unsafe {
+                    let _ = hosted.controller.NotifyParentWindowPositionChanged();
+                },
+                Err(e) => warn!(
+                    "[WALLPAPER][SCREENSAVER] section='{}' monitor={} not lifted: {}",
+                    hosted.section, hosted.monitor_index, e
+                ),
+            }
+        }
+        self.screensaver_input = Some(input_tick);
+        warn!(
+            "[WALLPAPER][SCREENSAVER] idle; showing {} host(s) over every window",
+            self.hosted.len()
+        );
+    }
+
+    /// Put screensaver hosts back under their desktop window at their usual
+    /// position and layer.  No-op while the screensaver is down.
+    fn stop_screensaver(&mut self) {
+        if self.screensaver_input.take().is_none() {
+            return;
+        }
+        self.screensaver_dismissed_at = Some(Instant::now());
+        for index in self.hosts_in_stacking_order() {
+            let hosted = &self.hosted[index];
+            let Some(desktop) = hosted.desktop_parent else {
+                continue;
+            };
+            match return_to_desktop(hosted.hwnd, desktop, hosted.monitor_rect) {
+                Ok(()) => {
+                    if let Err(e) = apply_host_style(hosted.hwnd, &hosted.z_index, hosted.click_through) {
+                        warn!("[WALLPAPER][SCREENSAVER] host style not restored: {}", e);
+                    }
+                    if hosted.icon_layer == IconLayer::Over.name() {
+                        raise_above_siblings(hosted.hwnd);
+                    }
+                    unsafe {
+                        let _ = hosted.controller.NotifyParentWindowPositionChanged();
+                    }
+                }
+                Err(e) => warn!(
+                    "[WALLPAPER][SCREENSAVER] section='{}' monitor={} not restored: {}",
+                    hosted.section, hosted.monitor_index, e
+                ),
+            }
+        }
+        warn!("[WALLPAPER][SCREENSAVER] input detected; wallpapers back on the desktop");
+    }
+
+    /// Dismiss the screensaver on the first input since it came up.  Runs
+    /// every tick so the hosts drop back without waiting for a pause check.
+    fn check_screensaver_input(&mut self) {
+        let Some(shown_at) = self.screensaver_input else {
+            return;
+        };
+        if last_input_tick().is_some_and(|tick| tick != shown_at) {
+            self.stop_screensaver();
+        }
+    }
+
+    /// Host indexes bottom layer first (launch order within a layer), so
+    /// stitching captures in this order paints stacked hosts over the ones
+    /// beneath them.
+    fn hosts_in_stacking_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.hosted.len()).collect();
+        order.sort_by_key(|&index| z_index_rank(&self.hosted[index].z_index));
+        order
+    }
+
+    fn capture_paused_wallpaper_snapshot(
+        &mut self,
+        apply_to_desktop: bool,
+    ) -> std::result::Result<(), String> {
+        if self.hosted.is_empty() {
+            return Ok(());
+        }
+
+        let bounds = self.snapshot_bounds();
+        let hdr_rects = self.hdr_rects_for_snapshot();
+
+        let mut captures: Vec<(RECT, Arc<[u8]>)> = Vec::with_capacity(self.hosted.len());
+        for index in self.hosts_in_stacking_order() {
+            let hosted = &self.hosted[index];
+            if hosted.preview {
+                continue;
+            }
+            let width = (hosted.monitor_rect.right - hosted.monitor_rect.left).max(1);
+            let height = (hosted.monitor_rect.bottom - hosted.monitor_rect.top).max(1);
+            let pixels = capture_host_bgra(hosted.hwnd, hosted.capture_protected, width, height)?;
+            captures.push((hosted.monitor_rect, pixels));
+        }
+
+        let stitched = stitch_tiled_snapshot(
+            captures,
+            bounds,
+            self.snapshot_max_dimension,
+            self.fallback_color.unwrap_or([0, 0, 0]),
+            &hdr_rects,
+        )
+        .ok_or_else(|| {
+            "Captured wallpaper frame is fully black; refusing to apply snapshot wallpaper".to_string()
+        })?;
+
+        let snapshot_path = snapshot_path(self.snapshot_format);
+        save_snapshot_image(
+            stitched,
+            &snapshot_path,
+            self.snapshot_jpeg_quality,
+            self.snapshot_keep_history,
+        )?;
+
+        if apply_to_desktop {
+            apply_windows_wallpaper(&snapshot_path)?;
+            self.last_pause_snapshot_path = Some(snapshot_path.clone());
+            if self.log_pause_state_changes {
+                warn!(
+                    "[WALLPAPER][PAUSE] Applied snapshot wallpaper: {}",
+                    snapshot_path.display()
+                );
+            }
+        } else if self.log_pause_state_changes {
+            warn!(
+                "[WALLPAPER][PAUSE] Captured snapshot only (desktop unchanged): {}",
+                snapshot_path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Exclude `hwnd` from (or return it to) screen capture.  Returns whether
+/// Windows accepted it; it refuses for child windows of another process,
+/// which is what WorkerW-embedded hosts are.
+fn set_capture_excluded(hwnd: HWND, excluded: bool) -> bool {
+    let affinity = if excluded { WDA_EXCLUDEFROMCAPTURE } else { WDA_NONE };
+    match unsafe { SetWindowDisplayAffinity(hwnd, affinity) } {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "[WALLPAPER][HOST] SetWindowDisplayAffinity({}) failed for {:?}: {:?}",
+                if excluded { "exclude" } else { "none" },
+                hwnd,
+                e
+            );
+            false
+        }
+    }
+}
+
+/// `capture_window_bgra` for a host.  A capture-protected window can come
+/// back black from `PrintWindow`, so the affinity is lifted for just this
+/// capture; `PrintWindow` only reads the host itself, so nothing on top of
+/// it (or on screen) ends up in the snapshot either way.
+fn capture_host_bgra(
+    hwnd: HWND,
+    capture_protected: bool,
+    width: i32,
+    height: i32,
+) -> std::result::Result<Arc<[u8]>, String> {
+    if !capture_protected {
+        return capture_window_bgra(hwnd, width, height);
+    }
+    set_capture_excluded(hwnd, false);
+    let pixels = capture_window_bgra(hwnd, width, height);
+    set_capture_excluded(hwnd, true);
+    pixels
+}
+
+fn capture_window_bgra(hwnd: HWND, width: i32, height: i32) -> std::result::Result<Arc<[u8]>, String> {
+    unsafe {
+        let src_dc = GetDC(Some(hwnd));
+        if src_dc.0.is_null() {
+            return Err("GetDC failed".to_string());
+        }
+
+        let mem_dc = CreateCompatibleDC(Some(src_dc));
+        if mem_dc.0.is_null() {
+            let _ = ReleaseDC(Some(hwnd), src_dc);
+            return Err("CreateCompatibleDC failed".to_string());
+        }
+
+        let bitmap = CreateCompatibleBitmap(src_dc, width, height);
+        if bitmap.0.is_null() {
+            let _ = DeleteDC(mem_dc);
+            let _ = ReleaseDC(Some(hwnd), src_dc);
+            return Err("CreateCompatibleBitmap failed".to_string());
+        }
+
+        let old = SelectObject(mem_dc, HGDIOBJ(bitmap.0));
+        let printed = PrintWindow(hwnd, mem_dc, PRINT_WINDOW_FLAGS(2)).as_bool();
+        if !printed {
+            let _ = BitBlt(mem_dc, 0, 0, width, height, Some(src_dc), 0, 0, SRCCOPY)
+                .map_err(|e| format!("BitBlt fallback failed: {e:?}"));
+        }
+
+        let mut bmi = BITMAPINFO::default();
+        bmi.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as u32;
+        bmi.bmiHeader.biWidth = width;
+        bmi.bmiHeader.biHeight = -height;
         bmi.bmiHeader.biPlanes = 1;
         bmi.bmiHeader.biBitCount = 32;
         bmi.bmiHeader.biCompression = BI_RGB.0;
 
-        let mut pixels = vec![0u8; (width * height * 4) as usize];
-        let lines = GetDIBits(
-            mem_dc,
-            bitmap,
+        // Allocated shared so snapshot jobs and `cached_capture` can hold
+        // the same buffer; nothing else has it until this returns.
+        let mut pixels: Arc<[u8]> = std::iter::repeat_n(0u8, (width * height * 4) as usize).collect();
+        let buffer = Arc::get_mut(&mut pixels).expect("freshly allocated capture buffer");
+        let lines = GetDIBits(
+            mem_dc,
+            bitmap,
+            0,
+            height as u32,
+            Some(buffer.as_mut_ptr() as *mut core::ffi::c_void),
+            &mut bmi,
+            DIB_RGB_COLORS,
+        );
+
+        let _ = SelectObject(mem_dc, old);
+        let _ = DeleteObject(HGDIOBJ(bitmap.0));
+        let _ = DeleteDC(mem_dc);
+        let _ = ReleaseDC(Some(hwnd), src_dc);
+
+        if lines == 0 {
+            return Err("GetDIBits failed".to_string());
+        }
+
+        Ok(pixels)
+    }
+}
+
+/// One capture converted to RGBA and scaled, placed at `left`/`top` in the
+/// stitched image.
+struct StitchTile {
+    left: i32,
+    top: i32,
+    image: RgbaImage,
+    non_black: bool,
+}
+
+/// Stitch per-monitor BGRA captures into a bitmap the size of `bounds`,
+/// laid out for `TileWallpaper=1`.  Windows anchors tiles at the primary
+/// monitor's origin (0,0), so each pixel lands at its virtual-desktop
+/// coordinate modulo the image size; for layouts with negative
+/// `min_left`/`min_top` the wrapped tile lines up exactly with every
+/// monitor.  Tiles are drawn 1:1, so when `max_dimension` is non-zero and
+/// the captures are downscaled to fit it the result is laid out for
+/// `WallpaperLayout::Span` instead: placed relative to the top-left of
+/// `bounds`, which must then be the virtual screen.  Areas no capture
+/// covers are `fill`.  Returns `None` when every captured pixel is black.
+fn stitch_tiled_snapshot(
+    captures: Vec<(RECT, Arc<[u8]>)>,
+    bounds: RECT,
+    max_dimension: u32,
+    fill: [u8; 3],
+    hdr_rects: &[RECT],
+) -> Option<RgbaImage> {
+    let virtual_width = (bounds.right - bounds.left).max(1);
+    let virtual_height = (bounds.bottom - bounds.top).max(1);
+    let scale = snapshot_scale(virtual_width, virtual_height, max_dimension);
+    let (origin_x, origin_y) = if scale < 1.0 { (bounds.left, bounds.top) } else { (0, 0) };
+    let scaled = |v: i32| (v as f64 * scale).round() as i32;
+    let out_width = scaled(virtual_width).max(1);
+    let out_height = scaled(virtual_height).max(1);
+
+    let mut stitched = RgbaImage::from_pixel(
+        out_width as u32,
+        out_height as u32,
+        Rgba([fill[0], fill[1], fill[2], 255]),
+    );
+
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(SNAPSHOT_WORKERS_MAX);
+
+    // Colour conversion and downscaling are per tile, so they run on up to
+    // `workers` threads pulling tiles from a shared queue.
+    let tile_count = captures.len();
+    let queue = Mutex::new(captures.into_iter().enumerate());
+    let prepared: Mutex<Vec<Option<StitchTile>>> =
+        Mutex::new((0..tile_count).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers.min(tile_count) {
+            scope.spawn(|| {
+                while let Some((index, (rect, capture))) =
+                    queue.lock().ok().and_then(|mut q| q.next())
+                {
+                    let width = (rect.right - rect.left).max(1) as u32;
+                    let height = (rect.bottom - rect.top).max(1) as u32;
+
+                    // Captures may be shared (`cached_capture`), so each
+                    // row is copied out and converted BGRA (GDI) -> RGBA
+                    // with opaque alpha in place.
+                    let row_bytes = width as usize * 4;
+                    let curve = hdr_rects.contains(&rect).then(hdr_gamma_curve);
+                    let mut pixels = vec![0u8; capture.len()];
+                    let mut non_black = false;
+                    for (row, src) in pixels.chunks_exact_mut(row_bytes).zip(capture.chunks_exact(row_bytes)) {
+                        row.copy_from_slice(src);
+                        for px in row.chunks_exact_mut(4) {
+                            px.swap(0, 2);
+                            if let Some(curve) = &curve {
+                                for channel in &mut px[..3] {
+                                    *channel = curve[*channel as usize];
+                                }
+                            }
+                            px[3] = 255;
+                            non_black |= px[0] | px[1] | px[2] != 0;
+                        }
+                    }
+                    drop(capture);
+                    let Some(mut tile) = RgbaImage::from_raw(width, height, pixels) else {
+                        continue;
+                    };
+                    if scale < 1.0 {
+                        // Scale both edges, so neighbouring tiles meet
+                        // without gaps or overlap.
+                        let tile_width =
+                            (scaled(rect.right - origin_x) - scaled(rect.left - origin_x)).max(1) as u32;
+                        let tile_height =
+                            (scaled(rect.bottom - origin_y) - scaled(rect.top - origin_y)).max(1) as u32;
+                        tile = image::imageops::resize(&tile, tile_width, tile_height, FilterType::Triangle);
+                    }
+                    if let Ok(mut prepared) = prepared.lock() {
+                        prepared[index] = Some(StitchTile {
+                            left: scaled(rect.left - origin_x),
+                            top: scaled(rect.top - origin_y),
+                            image: tile,
+                            non_black,
+                        });
+                    }
+                }
+            });
+        }
+    });
+    let tiles: Vec<StitchTile> = prepared
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    if !tiles.iter().any(|tile| tile.non_black) {
+        return None;
+    }
+
+    // Copy in horizontal bands of the output, one thread each.  Every band
+    // paints all tiles in stacking order, so overlapping (stacked) tiles
+    // still end with the top one and no two threads touch the same row.
+    let row_bytes = out_width as usize * 4;
+    let band_rows = (out_height as usize).div_ceil(workers);
+    let tiles = &tiles;
+    thread::scope(|scope| {
+        for (band, rows) in stitched.chunks_mut(band_rows * row_bytes).enumerate() {
+            scope.spawn(move || {
+                let first_row = (band * band_rows) as i32;
+                let row_count = (rows.len() / row_bytes) as i32;
+                for tile in tiles {
+                    let tile_row_bytes = tile.image.width() as usize * 4;
+                    let dst_x = tile.left.rem_euclid(out_width) as usize * 4;
+                    for (y, src_row) in tile.image.as_raw().chunks_exact(tile_row_bytes).enumerate() {
+                        let dst_y = (tile.top + y as i32).rem_euclid(out_height) - first_row;
+                        if !(0..row_count).contains(&dst_y) {
+                            continue;
+                        }
+                        let dst_row = &mut rows[dst_y as usize * row_bytes..][..row_bytes];
+                        blit_wrapped_row(dst_row, dst_x, src_row);
+                    }
+                }
+            });
+        }
+    });
+
+    Some(stitched)
+}
+
+/// Copy `src` into `dst` starting at byte `offset`, wrapping past the end
+/// of `dst` back to its start the way the tiled wallpaper does.  Each piece
+/// is one `copy_from_slice`.
+fn blit_wrapped_row(dst: &mut [u8], mut offset: usize, mut src: &[u8]) {
+    while !src.is_empty() {
+        let len = src.len().min(dst.len() - offset);
+        dst[offset..offset + len].copy_from_slice(&src[..len]);
+        src = &src[len..];
+        offset = 0;
+    }
+}
+
+/// Uniform downscale factor (<= 1.0) that fits the virtual desktop's longest
+/// edge into `max_dimension`; 0 means no limit.
+fn snapshot_scale(virtual_width: i32, virtual_height: i32, max_dimension: u32) -> f64 {
+    let longest = virtual_width.max(virtual_height).max(1) as f64;
+    if max_dimension == 0 || longest <= max_dimension as f64 {
+        return 1.0;
+    }
+    max_dimension as f64 / longest
+}
+
+/// Background thread that stitches raw pixel captures into an RgbaImage
+/// and saves it to disk.  No SPI call — just keeps the file fresh.
+fn snapshot_worker(mailbox: &SnapshotMailbox) {
+    run_mailbox(mailbox, save_snapshot_job);
+}
+
+/// Hand every job taken from `mailbox` to `process` until it is closed.
+fn run_mailbox(mailbox: &SnapshotMailbox, mut process: impl FnMut(SnapshotJob)) {
+    while let Some(job) = mailbox.take() {
+        process(job);
+    }
+}
+
+fn save_snapshot_job(job: SnapshotJob) {
+    let Some(stitched) = stitch_tiled_snapshot(
+        job.captures,
+        job.bounds,
+        job.max_dimension,
+        job.fill,
+        &job.hdr_rects,
+    ) else {
+        return;
+    };
+
+    let snapshot_path = snapshot_path(job.format);
+    if let Err(e) =
+        save_snapshot_image(stitched, &snapshot_path, job.jpeg_quality, job.keep_history)
+    {
+        warn!("[WALLPAPER][SNAP] {}", e);
+    }
+}
+
+/// Set the most recent saved snapshot as the Windows wallpaper.  Returns
+/// whether one was applied.
+pub fn apply_latest_snapshot_wallpaper() -> bool {
+    let Some(snapshot_path) = latest_snapshot_path() else {
+        return false;
+    };
+    match apply_windows_wallpaper(&snapshot_path) {
+        Ok(()) => {
+            warn!(
+                "[WALLPAPER][SHUTDOWN] Applied snapshot wallpaper: {}",
+                snapshot_path.display()
+            );
+            true
+        }
+        Err(e) => {
+            warn!("[WALLPAPER][SHUTDOWN] Failed to apply snapshot wallpaper: {}", e);
+            false
+        }
+    }
+}
+
+fn snapshot_dir() -> PathBuf {
+    sentinel_assets_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wallpaper")
+        .join("snapshots")
+}
+
+fn snapshot_path(format: SnapshotFormat) -> PathBuf {
+    snapshot_dir().join(format!("paused_wallpaper_snapshot.{}", format.extension()))
+}
+
+/// The most recently written snapshot in any format, so a format change in
+/// config still restores the last frame at startup/shutdown.
+fn latest_snapshot_path() -> Option<PathBuf> {
+    [SnapshotFormat::Bmp, SnapshotFormat::Jpeg]
+        .into_iter()
+        .map(snapshot_path)
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Encode `image` to `path`, choosing the format from its extension.  The
+/// image is written to a temp file and renamed over `path`, so a crash
+/// mid-write never leaves a torn active snapshot; with `keep_history` the
+/// previous file is first rotated into `<stem>.1.<ext>`..`<stem>.N.<ext>`.
+fn save_snapshot_image(
+    image: RgbaImage,
+    path: &Path,
+    jpeg_quality: u8,
+    keep_history: u32,
+) -> std::result::Result<(), String> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    let temp_path = snapshot_sibling_path(path, "tmp");
+    if let Err(e) = encode_snapshot_image(image, &temp_path, jpeg_quality) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if keep_history > 0 {
+        rotate_snapshot_history(path, keep_history);
+    }
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to replace snapshot {}: {e}", path.display())
+    })
+}
+
+/// JPEG is written through the encoder directly: alpha is dropped and no
+/// EXIF / orientation block is emitted, so Windows always shows it upright.
+fn encode_snapshot_image(image: RgbaImage, path: &Path, jpeg_quality: u8) -> std::result::Result<(), String> {
+    let is_jpeg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg"));
+    if !is_jpeg {
+        return image
+            .save(path)
+            .map_err(|e| format!("Failed to save snapshot bitmap: {e}"));
+    }
+
+    let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+    let file = fs::File::create(path)
+        .map_err(|e| format!("Failed to create snapshot file {}: {e}", path.display()))?;
+    JpegEncoder::new_with_quality(std::io::BufWriter::new(file), jpeg_quality.clamp(1, 100))
+        .encode_image(&rgb)
+        .map_err(|e| format!("Failed to encode snapshot JPEG: {e}"))
+}
+
+/// `paused_wallpaper_snapshot.<tag>.<ext>` next to the active snapshot.
+fn snapshot_sibling_path(path: &Path, tag: &str) -> PathBuf {
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("bmp");
+    path.with_extension(format!("{tag}.{ext}"))
+}
+
+/// Shift `<stem>.1..N-1.<ext>` up one slot (dropping `N`) and copy the
+/// active snapshot into slot 1.  The active file is copied rather than
+/// moved so it stays in place until the new image is renamed over it.
+fn rotate_snapshot_history(path: &Path, keep_history: u32) {
+    if !path.exists() {
+        return;
+    }
+
+    let _ = fs::remove_file(snapshot_sibling_path(path, &keep_history.to_string()));
+    for slot in (1..keep_history).rev() {
+        let from = snapshot_sibling_path(path, &slot.to_string());
+        if from.exists() {
+            let _ = fs::rename(&from, snapshot_sibling_path(path, &(slot + 1).to_string()));
+        }
+    }
+    if let Err(e) = fs::copy(path, snapshot_sibling_path(path, "1")) {
+        warn!("[WALLPAPER][SNAP] Failed to keep snapshot history: {}", e);
+    }
+}
+
+/// DOM-style `buttons` mask: 1 = left, 2 = right, 4 = middle.
+fn mouse_button_mask() -> u32 {
+    let down = |vk: VIRTUAL_KEY| unsafe { (GetAsyncKeyState(vk.0 as i32) as u16 & 0x8000) != 0 };
+    let mut mask = 0u32;
+    if down(VK_LBUTTON) {
+        mask |= 1;
+    }
+    if down(VK_RBUTTON) {
+        mask |= 2;
+    }
+    if down(VK_MBUTTON) {
+        mask |= 4;
+    }
+    mask
+}
+
+/// The virtual screen's bounding rectangle, which a spanned wallpaper
+/// covers.
+fn virtual_screen_rect() -> Option<RECT> {
+    let (left, top, width, height) = unsafe {
+        (
+            GetSystemMetrics(SM_XVIRTUALSCREEN),
+            GetSystemMetrics(SM_YVIRTUALSCREEN),
+            GetSystemMetrics(SM_CXVIRTUALSCREEN),
+            GetSystemMetrics(SM_CYVIRTUALSCREEN),
+        )
+    };
+    (width > 0 && height > 0).then_some(RECT { left, top, right: left + width, bottom: top + height })
+}
+
+/// How Windows places a stitched snapshot on the desktop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WallpaperLayout {
+    /// `WallpaperStyle=0` + `TileWallpaper=1`: repeated 1:1 from the
+    /// primary monitor's origin.
+    Tiled,
+    /// `WallpaperStyle=22`: scaled over the virtual screen's bounding box.
+    Span,
+}
+
+impl WallpaperLayout {
+    /// Layout of a snapshot `image_size` big on a virtual screen of
+    /// `screen_size`.  Only a downscaled stitch is the virtual screen
+    /// scaled to a shorter longest edge, and those are spanned.
+    fn for_snapshot(image_size: (u32, u32), screen_size: (i32, i32)) -> Self {
+        let (width, height) = screen_size;
+        let scale = snapshot_scale(width, height, image_size.0.max(image_size.1));
+        let scaled = |v: i32| ((v as f64 * scale).round() as i32).max(1) as u32;
+        if scale < 1.0 && (scaled(width), scaled(height)) == image_size {
+            Self::Span
+        } else {
+            Self::Tiled
+        }
+    }
+
+    /// `WallpaperStyle` and `TileWallpaper` under `HKCU\Control Panel\Desktop`.
+    fn registry_values(self) -> [(PCWSTR, &'static str); 2] {
+        match self {
+            Self::Tiled => [(w!("WallpaperStyle"), "0"), (w!("TileWallpaper"), "1")],
+            Self::Span => [(w!("WallpaperStyle"), "22"), (w!("TileWallpaper"), "0")],
+        }
+    }
+}
+
+/// Write `layout`'s wallpaper style under `HKCU\Control Panel\Desktop`.
+fn set_wallpaper_style(layout: WallpaperLayout) -> std::result::Result<(), String> {
+    for (name, value) in layout.registry_values() {
+        set_desktop_registry_value(name, value)?;
+    }
+    Ok(())
+}
+
+/// Write one `REG_SZ` value under `HKCU\Control Panel\Desktop`.
+fn set_desktop_registry_value(name: PCWSTR, value: &str) -> std::result::Result<(), String> {
+    let data = to_wstring(value);
+    let status = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            w!("Control Panel\\Desktop"),
+            name,
+            REG_SZ.0,
+            Some(data.as_ptr() as *const core::ffi::c_void),
+            (data.len() * mem::size_of::<u16>()) as u32,
+        )
+    };
+    if status.is_err() {
+        return Err(format!("RegSetKeyValueW failed: {status:?}"));
+    }
+    Ok(())
+}
+
+fn apply_windows_wallpaper(path: &Path) -> std::result::Result<(), String> {
+    let layout = match (image::image_dimensions(path), virtual_screen_rect()) {
+        (Ok(size), Some(screen)) => WallpaperLayout::for_snapshot(
+            size,
+            (screen.right - screen.left, screen.bottom - screen.top),
+        ),
+        _ => WallpaperLayout::Tiled,
+    };
+    if let Err(e) = set_wallpaper_style(layout) {
+        warn!("[WALLPAPER][SNAP] Failed to set {:?} wallpaper style: {}", layout, e);
+    }
+
+    // SPI_SETDESKWALLPAPER takes a raw string, so deep profiles (OneDrive
+    // redirection) need the long-path form.
+    let wide = to_wstring(long_path(path).to_string_lossy().as_ref());
+    let spi_error = match unsafe {
+        SystemParametersInfoW(
+            SPI_SETDESKWALLPAPER,
+            0,
+            Some(wide.as_ptr() as *mut core::ffi::c_void),
+            SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+        )
+    } {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+
+    // Some group policies reject SPI_SETDESKWALLPAPER with a path but still
+    // let the user's desktop keys be written; point the registry at the file
+    // and ask the shell to reload it from there.
+    warn!(
+        "[WALLPAPER][SNAP] SPI_SETDESKWALLPAPER failed ({:?}); falling back to registry write",
+        spi_error
+    );
+    set_desktop_registry_value(w!("Wallpaper"), &path.to_string_lossy())
+        .and_then(|_| set_wallpaper_style(layout))
+        .map_err(|e| {
+            format!(
+                "SystemParametersInfoW(SPI_SETDESKWALLPAPER) failed: {spi_error:?}; registry fallback failed: {e}"
+            )
+        })?;
+
+    unsafe { SystemParametersInfoW(SPI_SETDESKWALLPAPER, 0, None, SPIF_SENDCHANGE) }.map_err(|e| {
+        format!(
+            "SystemParametersInfoW(SPI_SETDESKWALLPAPER) failed: {spi_error:?}; registry updated but refresh failed: {e:?}"
+        )
+    })
+}
+
+#[derive(Default, Clone, Copy)]
+struct MonitorWindowStates {
+    focused: bool,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+#[derive(Clone, Copy)]
+struct ForegroundWindowSnapshot {
+    monitor_rect: RECT,
+    states: MonitorWindowStates,
+}
+
+fn mode_triggered(mode: PauseMode, local_triggered: bool, any_triggered: bool) -> bool {
+    match mode {
+        PauseMode::Off => false,
+        PauseMode::PerMonitor => local_triggered,
+        PauseMode::AllMonitors => any_triggered,
+    }
+}
+
+fn monitor_window_states(appdata: &Value, monitor_id: &str) -> MonitorWindowStates {
+    let windows = appdata
+        .get(monitor_id)
+        .and_then(|v| v.get("windows"))
+        .and_then(|v| v.as_array());
+
+    let mut states = MonitorWindowStates::default();
+    let Some(windows) = windows else {
+        return states;
+    };
+
+    for window in windows {
+        let (focused, maximized, fullscreen) = window_flags(window);
+
+        if focused {
+            states.focused = true;
+        }
+        if maximized {
+            states.maximized = true;
+        }
+        if fullscreen {
+            states.fullscreen = true;
+        }
+    }
+
+    states
+}
+
+fn foreground_window_snapshot() -> Option<ForegroundWindowSnapshot> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
+        }
+
+        if is_shell_foreground_window(hwnd) {
+            return None;
+        }
+
+        let mut states = MonitorWindowStates {
+            focused: true,
+            maximized: IsZoomed(hwnd).0 != 0,
+            fullscreen: false,
+        };
+
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).is_err() {
+            return Some(ForegroundWindowSnapshot {
+                monitor_rect: RECT::default(),
+                states,
+            });
+        }
+
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        if monitor.0.is_null() {
+            return Some(ForegroundWindowSnapshot {
+                monitor_rect: RECT::default(),
+                states,
+            });
+        }
+
+        let mut mi_ex: MONITORINFOEXW = std::mem::zeroed();
+        mi_ex.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, &mut mi_ex.monitorInfo).0 == 0 {
+            return Some(ForegroundWindowSnapshot {
+                monitor_rect: RECT::default(),
+                states,
+            });
+        }
+
+        let monitor_rc = mi_ex.monitorInfo.rcMonitor;
+        let epsilon = 1i32;
+        let covers_monitor = (rect.left - monitor_rc.left).abs() <= epsilon
+            && (rect.top - monitor_rc.top).abs() <= epsilon
+            && (rect.right - monitor_rc.right).abs() <= epsilon
+            && (rect.bottom - monitor_rc.bottom).abs() <= epsilon;
+
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let has_frame = (style & (WS_CAPTION.0 | WS_THICKFRAME.0)) != 0;
+        states.fullscreen = covers_monitor && !has_frame;
+
+        Some(ForegroundWindowSnapshot {
+            monitor_rect: monitor_rc,
+            states,
+        })
+    }
+}
+
+fn is_shell_foreground_window(hwnd: HWND) -> bool {
+    let mut class_buf = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut class_buf) };
+    if len <= 0 {
+        return false;
+    }
+
+    let class_name = String::from_utf16_lossy(&class_buf[..len as usize]).to_ascii_lowercase();
+    matches!(
+        class_name.as_str(),
+        "progman" | "workerw" | "shell_traywnd" | "shell_secondarytraywnd"
+    )
+}
+
+fn window_flags(window: &Value) -> (bool, bool, bool) {
+    let focused = window
+        .get("focused")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let state = window
+        .get("window_state")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let maximized = state.contains("maximized") || state.contains("maximised");
+    let fullscreen = state.contains("fullscreen") || state.contains("full screen");
+
+    (focused, maximized, fullscreen)
+}
+
+fn rect_matches_monitor(lhs: RECT, rhs: RECT) -> bool {
+    let epsilon = 1;
+    (lhs.left - rhs.left).abs() <= epsilon
+        && (lhs.top - rhs.top).abs() <= epsilon
+        && (lhs.right - rhs.right).abs() <= epsilon
+        && (lhs.bottom - rhs.bottom).abs() <= epsilon
+}
+
+fn resolve_monitor_id_for_rect(sysdata: &Value, rect: RECT) -> Option<String> {
+    let displays = sysdata.get("displays")?.as_array()?;
+
+    let mut best_overlap: i64 = -1;
+    let mut best_overlap_id: Option<String> = None;
+    let mut best_distance: i64 = i64::MAX;
+    let mut nearest_id: Option<String> = None;
+
+    for display in displays {
+        let metadata = display.get("metadata").unwrap_or(display);
+        let x = metadata.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+        let y = metadata.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+        let width = metadata.get("width").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+        let height = metadata.get("height").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+
+        if width <= 0 || height <= 0 {
+            continue;
+        }
+
+        let id = display
+            .get("id")
+            .and_then(|v| v.as_str())
+            .or_else(|| metadata.get("id").and_then(|v| v.as_str()))
+            .map(|v| v.to_string());
+
+        let Some(id) = id else {
+            continue;
+        };
+
+        let display_rect = RECT {
+            left: x,
+            top: y,
+            right: x + width,
+            bottom: y + height,
+        };
+
+        let overlap_left = rect.left.max(display_rect.left);
+        let overlap_top = rect.top.max(display_rect.top);
+        let overlap_right = rect.right.min(display_rect.right);
+        let overlap_bottom = rect.bottom.min(display_rect.bottom);
+        let overlap_w = (overlap_right - overlap_left).max(0) as i64;
+        let overlap_h = (overlap_bottom - overlap_top).max(0) as i64;
+        let overlap_area = overlap_w * overlap_h;
+
+        if overlap_area > best_overlap {
+            best_overlap = overlap_area;
+            best_overlap_id = Some(id.clone());
+        }
+
+        let rect_center_x = ((rect.left + rect.right) / 2) as i64;
+        let rect_center_y = ((rect.top + rect.bottom) / 2) as i64;
+        let display_center_x = ((display_rect.left + display_rect.right) / 2) as i64;
+        let display_center_y = ((display_rect.top + display_rect.bottom) / 2) as i64;
+        let distance = (rect_center_x - display_center_x).abs() + (rect_center_y - display_center_y).abs();
+        if distance < best_distance {
+            best_distance = distance;
+            nearest_id = Some(id);
+        }
+    }
+
+    if best_overlap > 0 {
+        return best_overlap_id;
+    }
+
+    best_overlap_id.or(nearest_id)
+}
+
+fn pause_payload(paused: bool) -> String {
+    serde_json::json!({
+        "type": "native_pause",
+        "paused": paused,
+    })
+    .to_string()
+}
+
+fn volume_payload(volume: f32) -> String {
+    serde_json::json!({
+        "type": "native_volume",
+        "volume": volume,
+    })
+    .to_string()
+}
+
+fn fps_limit_payload(fps: u32) -> String {
+    serde_json::json!({
+        "type": "native_fps_limit",
+        "fps": fps,
+    })
+    .to_string()
+}
+
+fn power_on_battery(sysdata: &Value) -> bool {
+    let power = match sysdata.get("power") {
+        Some(value) => value,
+        None => return false,
+    };
+
+    let ac_status = power
+        .get("ac_status")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if matches!(ac_status.as_str(), "online" | "ac" | "plugged" | "plugged_in") {
+        return false;
+    }
+
+    if matches!(ac_status.as_str(), "offline" | "battery" | "on_battery") {
+        return true;
+    }
+
+    power
+        .get("battery")
+        .and_then(|battery| battery.get("present"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        && !power
+            .get("battery")
+            .and_then(|battery| battery.get("charging"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+}
+
+fn user_notification_state() -> Option<QUERY_USER_NOTIFICATION_STATE> {
+    unsafe { SHQueryUserNotificationState().ok() }
+}
+
+fn notification_state_name(state: QUERY_USER_NOTIFICATION_STATE) -> &'static str {
+    match state {
+        QUNS_NOT_PRESENT => "not_present",
+        QUNS_BUSY => "busy",
+        QUNS_RUNNING_D3D_FULL_SCREEN => "d3d_fullscreen",
+        QUNS_PRESENTATION_MODE => "presentation_mode",
+        QUNS_ACCEPTS_NOTIFICATIONS => "accepts_notifications",
+        QUNS_QUIET_TIME => "quiet_time",
+        QUNS_APP => "app",
+        _ => "unknown",
+    }
+}
+
+fn power_battery_percent(sysdata: &Value) -> Option<f64> {
+    sysdata
+        .get("power")
+        .and_then(|power| power.get("battery"))
+        .and_then(|battery| battery.get("percent"))
+        .and_then(|v| v.as_f64())
+}
+
+/// Image names seen anywhere in `sysdata.processes` (its process list and
+/// the `top_cpu` / `top_memory` rankings), as `process_image_key`s.
+fn running_process_keys(sysdata: &Value) -> HashSet<String> {
+    let Some(processes) = sysdata.get("processes") else {
+        return HashSet::new();
+    };
+    let lists: Vec<&Vec<Value>> = match processes {
+        Value::Array(list) => vec![list],
+        Value::Object(map) => map.values().filter_map(|v| v.as_array()).collect(),
+        _ => Vec::new(),
+    };
+
+    lists
+        .into_iter()
+        .flatten()
+        .filter_map(|process| {
+            process
+                .get("name")
+                .or_else(|| process.get("image"))
+                .or_else(|| process.get("exe"))
+                .and_then(|v| v.as_str())
+        })
+        .map(process_image_key)
+        .collect()
+}
+
+/// Highest `usage_percent` (or `usage`) among `sysdata.gpu` and its
+/// `adapters`, so one busy GPU in a multi-GPU system is enough.
+fn gpu_usage_percent(sysdata: &Value) -> Option<f64> {
+    let gpu = sysdata.get("gpu")?;
+    let usage = |value: &Value| {
+        value
+            .get("usage_percent")
+            .or_else(|| value.get("usage"))
+            .and_then(|v| v.as_f64())
+    };
+
+    let adapters = gpu.get("adapters").and_then(|v| v.as_array());
+    std::iter::once(gpu)
+        .chain(adapters.into_iter().flatten())
+        .filter_map(usage)
+        .reduce(f64::max)
+}
+
+/// A threshold of 0, or a backend that doesn't report the charge, keeps the
+/// plain "on battery" behavior.
+fn battery_below_threshold(percent: Option<f64>, threshold: u8) -> bool {
+    match percent {
+        Some(percent) if threshold > 0 => percent < threshold as f64,
+        _ => true,
+    }
+}
+
+fn build_registry_snapshot_and_payload(sections: &HashSet<String>) -> Option<(Value, Value, String)> {
+    // Single IPC round-trip using the combined `snapshot` command.
+    // Uses request_quick (no retries) so the tick loop never blocks for seconds.
+    let mut section_list: Vec<String> = sections.iter().cloned().collect();
+    section_list.sort();
+    let args = serde_json::json!({ "sections": section_list });
+    let snapshot_raw = request_quick("registry", "snapshot", Some(args))?;
+    let snapshot: Value = serde_json::from_str(&snapshot_raw).ok()?;
+
+    let sysdata = snapshot.get("sysdata").cloned().unwrap_or(Value::Null);
+    let appdata = snapshot.get("appdata").cloned().unwrap_or(Value::Null);
+
+    let payload = serde_json::json!({
+        "type": "native_registry",
+        "sysdata": sysdata,
+        "appdata": appdata,
+    })
+    .to_string();
+
+    Some((sysdata, appdata, payload))
+}
+
+fn post_webview_json(webview: &ICoreWebView2, payload: &str) -> std::result::Result<(), String> {
+    let payload_wide = to_wstring(payload);
+    unsafe {
+        webview
+            .PostWebMessageAsJson(PCWSTR(payload_wide.as_ptr()))
+            .map_err(|e| format!("WebView2 PostWebMessageAsJson failed: {e:?}"))
+    }
+}
+
+/// Walk the editable tree from manifest.json and collect { "--css-var": "value" } pairs.
+fn extract_css_vars(editable: &Value) -> serde_json::Map<String, Value> {
+    let mut vars = serde_json::Map::new();
+    for_each_editable_entry(editable, |_path, entry| {
+        if let Some(variable) = entry.get("variable").and_then(|v| v.as_str()) {
+            if let Some(value) = entry.get("value") {
+                vars.insert(variable.to_string(), Value::String(value_to_css_string(value)));
+            }
+        }
+    });
+    vars
+}
+
+/// Call `visit` with the dotted path of every editable entry: top-level
+/// entries that declare a `variable`, and the object fields of the other
+/// (group) entries.  Non-object group fields like "name" or "description"
+/// are skipped.
+fn for_each_editable_entry(editable: &Value, mut visit: impl FnMut(&str, &Value)) {
+    let Some(obj) = editable.as_object() else {
+        return;
+    };
+
+    for (key, entry) in obj {
+        if entry.get("variable").is_some() {
+            visit(&format!("editable.{key}"), entry);
+        } else if let Some(sub_obj) = entry.as_object() {
+            for (sub_key, sub) in sub_obj {
+                if sub.is_object() {
+                    visit(&format!("editable.{key}.{sub_key}"), sub);
+                }
+            }
+        }
+    }
+}
+
+/// Write `value` as the `value` of the editable entry declaring `variable`
+/// in `asset_dir`'s manifest.json.  The file is rewritten through a temp
+/// file and rename, keeping key order, so a crash never leaves it torn.
+fn set_manifest_editable(
+    asset_dir: &Path,
+    variable: &str,
+    value: &Value,
+) -> std::result::Result<(), String> {
+    if asset_dir.as_os_str().is_empty() {
+        return Err("this page has no asset folder".to_string());
+    }
+    if !matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_)) {
+        return Err(format!("value for '{variable}' must be a string, number or bool"));
+    }
+
+    let manifest_path = asset_dir.join("manifest.json");
+    let content = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("cannot read {}: {e}", manifest_path.display()))?;
+    let mut manifest: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("invalid JSON in {}: {e}", manifest_path.display()))?;
+
+    let mut entry_path = None;
+    if let Some(editable) = manifest.get("editable") {
+        for_each_editable_entry(editable, |path, entry| {
+            if entry_path.is_none()
+                && entry.get("variable").and_then(|v| v.as_str()) == Some(variable)
+            {
+                entry_path = Some(path.to_string());
+            }
+        });
+    }
+    let entry_path = entry_path
+        .ok_or_else(|| format!("no editable entry declares variable '{variable}'"))?;
+
+    // `editable.group.entry` -> `/editable/group/entry`
+    let pointer: String = entry_path
+        .split('.')
+        .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+        .collect();
+    let entry = manifest
+        .pointer_mut(&pointer)
+        .and_then(|entry| entry.as_object_mut())
+        .ok_or_else(|| format!("editable entry '{entry_path}' is not an object"))?;
+    entry.insert("value".to_string(), value.clone());
+
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("cannot serialize manifest: {e}"))?;
+    let temp_path = asset_dir.join("manifest.json.tmp");
+    fs::write(&temp_path, json + "\n")
+        .map_err(|e| format!("cannot write {}: {e}", temp_path.display()))?;
+    fs::rename(&temp_path, &manifest_path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("cannot replace {}: {e}", manifest_path.display())
+    })
+}
+
+/// Manifest fields the addon reads, with the JSON type each must have.
+const MANIFEST_FIELD_TYPES: &[(&str, &str)] = &[
+    ("id", "string"),
+    ("name", "string"),
+    ("url", "string"),
+    ("allow_remote", "bool"),
+    ("virtual_host", "bool"),
+    ("muted", "bool"),
+    ("snapshot", "string"),
+    ("default_z_index", "string"),
+    ("webview_args", "array"),
+    ("autoplay", "string"),
+    ("heartbeat", "bool"),
+    ("editable", "object"),
+];
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Lint an asset's manifest.json: JSON syntax, the types of the fields the
+/// addon reads, and the shape of the `editable` tree that
+/// `extract_css_vars` would otherwise skip over silently.  `target` is an
+/// asset id, an asset folder or a path to the manifest itself.  Returns
+/// `{ manifest, valid, problems: [{ path, message }] }`.
+pub fn validate_manifest(target: &str) -> std::result::Result<Value, String> {
+    let target_path = Path::new(target);
+    let manifest_path = if target_path
+        .file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case("manifest.json"))
+    {
+        target_path.to_path_buf()
+    } else if target_path.is_dir() {
+        target_path.join("manifest.json")
+    } else {
+        let assets = fetch_wallpaper_assets();
+        let asset = resolve_asset(&assets, target)
+            .ok_or_else(|| format!("no asset or folder '{target}'"))?;
+        asset.path.join("manifest.json")
+    };
+
+    let content = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("cannot read {}: {e}", manifest_path.display()))?;
+
+    let mut problems = Vec::new();
+    let mut problem = |path: &str, message: String| {
+        problems.push(serde_json::json!({ "path": path, "message": message }));
+    };
+
+    match serde_json::from_str::<Value>(&content) {
+        Err(e) => problem("", format!("invalid JSON: {e}")),
+        Ok(manifest) if !manifest.is_object() => {
+            problem("", format!("expected an object, found {}", json_type_name(&manifest)))
+        }
+        Ok(manifest) => {
+            for &(field, expected) in MANIFEST_FIELD_TYPES {
+                if let Some(value) = manifest.get(field) {
+                    let found = json_type_name(value);
+                    if found != expected {
+                        problem(field, format!("expected {expected}, found {found}"));
+                    }
+                }
+            }
+            if let Some(args) = manifest.get("webview_args").and_then(|v| v.as_array()) {
+                for (index, arg) in args.iter().enumerate() {
+                    if !arg.is_string() {
+                        problem(&format!("webview_args[{index}]"), "expected string".to_string());
+                    }
+                }
+            }
+            if let Some(editable) = manifest.get("editable").filter(|v| v.is_object()) {
+                validate_editable(editable, &mut problem);
+            }
+        }
+    }
+
+    Ok(serde_json::json!({
+        "manifest": manifest_path.to_string_lossy(),
+        "valid": problems.is_empty(),
+        "problems": problems,
+    }))
+}
+
+fn validate_editable(editable: &Value, problem: &mut impl FnMut(&str, String)) {
+    if let Some(obj) = editable.as_object() {
+        for (key, entry) in obj {
+            if !entry.is_object() {
+                problem(
+                    &format!("editable.{key}"),
+                    format!("expected an entry or group object, found {}", json_type_name(entry)),
+                );
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for_each_editable_entry(editable, |path, entry| {
+        match entry.get("variable") {
+            None => problem(path, "missing 'variable' (CSS variable name)".to_string()),
+            Some(Value::String(variable)) if !variable.starts_with("--") => problem(
+                &format!("{path}.variable"),
+                format!("'{variable}' is not a CSS custom property (must start with --)"),
+            ),
+            Some(Value::String(variable)) => {
+                if !seen.insert(variable.clone()) {
+                    problem(
+                        &format!("{path}.variable"),
+                        format!("'{variable}' is declared more than once"),
+                    );
+                }
+            }
+            Some(other) => problem(
+                &format!("{path}.variable"),
+                format!("expected string, found {}", json_type_name(other)),
+            ),
+        }
+        match entry.get("value") {
+            None => problem(path, "missing 'value'; the variable is never sent".to_string()),
+            Some(Value::String(_) | Value::Number(_) | Value::Bool(_)) => {}
+            Some(other) => problem(
+                &format!("{path}.value"),
+                format!("expected string, number or bool, found {}", json_type_name(other)),
+            ),
+        }
+    });
+}
+
+/// Convert a serde_json Value to a CSS-appropriate string.
+fn value_to_css_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn ensure_host_class() -> std::result::Result<(), String> {
+    static CLASS_ONCE: OnceLock<bool> = OnceLock::new();
+    ensure_window_class(&CLASS_ONCE, HOST_CLASS_NAME, host_window_proc)
+}
+
+fn ensure_notification_class() -> std::result::Result<(), String> {
+    static CLASS_ONCE: OnceLock<bool> = OnceLock::new();
+    ensure_window_class(&CLASS_ONCE, NOTIFICATION_CLASS_NAME, notification_window_proc)
+}
+
+/// Register `class_name` with `window_proc` the first time `once` is seen.
+fn ensure_window_class(
+    once: &OnceLock<bool>,
+    class_name: PCWSTR,
+    window_proc: unsafe extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT,
+) -> std::result::Result<(), String> {
+    if once.get().is_some() {
+        return Ok(());
+    }
+
+    let hinstance = unsafe {
+        GetModuleHandleW(None)
+            .map(|h| HINSTANCE(h.0))
+            .map_err(|e| format!("GetModuleHandleW failed: {e:?}"))?
+    };
+
+    let wc = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: hinstance,
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+
+    unsafe {
+        let _ = RegisterClassW(&wc);
+    }
+
+    let _ = once.set(true);
+    Ok(())
+}
+
+unsafe extern "system" fn host_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+unsafe extern "system" fn notification_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    // Session and display changes are sent to the notification window;
+    // re-post them as thread messages so `main`'s loop handles them next to
+    // `WM_HOTKEY`.
+    if msg == WM_WTSSESSION_CHANGE || msg == WM_DISPLAYCHANGE {
+        let _ = PostMessageW(None, msg, wparam, lparam);
+        return LRESULT(0);
+    }
+    if msg == WM_STOP_INSTANCE {
+        PostQuitMessage(0);
+        return LRESULT(0);
+    }
+    // `lparam` points at a string that dies with this call, so only the
+    // changes that move hosts are forwarded, without it.
+    if msg == WM_SETTINGCHANGE && settings_change_moves_hosts(wparam, lparam) {
+        let _ = PostMessageW(None, msg, wparam, LPARAM(0));
+        return LRESULT(0);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// `WM_SETTINGCHANGE` for a work-area change (taskbar moved or resized) or
+/// a theme switch; Explorer may shift its desktop windows after either.
+unsafe fn settings_change_moves_hosts(wparam: WPARAM, lparam: LPARAM) -> bool {
+    if wparam.0 as u32 == SPI_SETWORKAREA.0 {
+        return true;
+    }
+    if lparam.0 == 0 {
+        return false;
+    }
+    let area = PCWSTR(lparam.0 as *const u16).to_string().unwrap_or_default();
+    matches!(area.as_str(), "ImmersiveColorSet" | "WindowsThemeElement")
+}
+
+/// Create a hidden top-level window that receives `WM_DISPLAYCHANGE` and
+/// this session's lock/unlock notifications, which then surface in the main
+/// message loop.  Not message-only: those never see broadcasts.  It is also
+/// how `stop_other_instances` finds and stops this process.  Pass the
+/// handle to `destroy_notification_window` on shutdown.
+pub fn create_notification_window() -> Option<HWND> {
+    if let Err(e) = ensure_notification_class() {
+        warn!("[WALLPAPER][SESSION] {}", e);
+        return None;
+    }
+    let hinstance = unsafe { GetModuleHandleW(None).ok().map(|h| HINSTANCE(h.0)) };
+    let hwnd = match unsafe {
+        CreateWindowExW(
+            WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            NOTIFICATION_CLASS_NAME,
+            NOTIFICATION_WINDOW_TITLE,
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            hinstance,
+            None,
+        )
+    } {
+        Ok(hwnd) => hwnd,
+        Err(e) => {
+            warn!("[WALLPAPER][SESSION] CreateWindowExW (notification window) failed: {:?}", e);
+            return None;
+        }
+    };
+    // Display changes still arrive without it; only lock pausing is lost.
+    if let Err(e) = unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } {
+        warn!("[WALLPAPER][SESSION] WTSRegisterSessionNotification failed: {:?}", e);
+    }
+    Some(hwnd)
+}
+
+pub fn destroy_notification_window(hwnd: HWND) {
+    unsafe {
+        let _ = WTSUnRegisterSessionNotification(hwnd);
+        let _ = DestroyWindow(hwnd);
+    }
+}
+
+/// Ask every other running instance to shut down through its notification
+/// window, so it saves its shutdown snapshot and releases the desktop, then
+/// wait up to `timeout` for those processes to exit.  Returns how many
+/// exited; instances without a notification window are not seen at all.
+pub fn stop_other_instances(timeout: Duration) -> usize {
+    let own_pid = std::process::id();
+    let mut processes = Vec::new();
+    let mut after = None;
+    while let Ok(hwnd) =
+        unsafe { FindWindowExW(None, after, NOTIFICATION_CLASS_NAME, NOTIFICATION_WINDOW_TITLE) }
+    {
+        after = Some(hwnd);
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+        if pid == 0 || pid == own_pid {
+            continue;
+        }
+        match unsafe { OpenProcess(PROCESS_SYNCHRONIZE, false, pid) } {
+            Ok(process) => {
+                let _ = unsafe { PostMessageW(Some(hwnd), WM_STOP_INSTANCE, WPARAM(0), LPARAM(0)) };
+                processes.push((pid, process));
+            }
+            Err(e) => warn!("[WALLPAPER][STOP] OpenProcess({}) failed: {:?}", pid, e),
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut exited = 0;
+    for (pid, process) in processes {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if unsafe { WaitForSingleObject(process, remaining.as_millis() as u32) } == WAIT_OBJECT_0 {
+            exited += 1;
+        } else {
+            warn!("[WALLPAPER][STOP] instance {} did not exit within {:?}", pid, timeout);
+        }
+        let _ = unsafe { CloseHandle(process) };
+    }
+    exited
+}
+
+fn create_desktop_child_window(worker: HWND, parent_rect: RECT, rect: RECT) -> std::result::Result<HWND, String> {
+    let x = rect.left - parent_rect.left;
+    let y = rect.top - parent_rect.top;
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    warn!(
+        "[WALLPAPER][HOST] creating child window parent={:?} pos=({}, {}) size={}x{}",
+        worker,
+        x,
+        y,
+        width,
+        height
+    );
+
+    let style = WINDOW_STYLE((WS_CHILD | WS_VISIBLE | WS_CLIPSIBLINGS | WS_CLIPCHILDREN).0);
+    let ex_style = WINDOW_EX_STYLE((WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE).0);
+
+    let hinstance = unsafe {
+        GetModuleHandleW(None)
+            .map(|h| HINSTANCE(h.0))
+            .map_err(|e| format!("GetModuleHandleW failed: {e:?}"))?
+    };
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            ex_style,
+            HOST_CLASS_NAME,
+            PCWSTR::null(),
+            style,
+            x,
+            y,
+            width,
+            height,
+            Some(worker),
+            None,
+            Some(hinstance),
+            Some(ptr::null()),
+        )
+    }
+    .map_err(|e| format!("CreateWindowExW failed: {e:?}"))?;
+
+    Ok(hwnd)
+}
+
+/// `dwTime` of the last keyboard or mouse input in this session.
+fn last_input_tick() -> Option<u32> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe { GetLastInputInfo(&mut info) }.as_bool().then_some(info.dwTime)
+}
+
+/// Detach a desktop child host and make it a topmost popup covering `rect`
+/// (screen coordinates), for the idle screensaver.
+fn promote_to_screensaver(hwnd: HWND, rect: RECT) -> std::result::Result<(), String> {
+    unsafe {
+        SetParent(hwnd, None).map_err(|e| format!("SetParent(None) failed: {e}"))?;
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let _ = SetWindowLongW(hwnd, GWL_STYLE, ((style & !WS_CHILD.0) | WS_POPUP.0) as i32);
+        SetWindowPos(
+            hwnd,
+            Some(HWND_TOPMOST),
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            SWP_NOACTIVATE | SWP_SHOWWINDOW | SWP_FRAMECHANGED,
+        )
+        .map_err(|e| format!("SetWindowPos(HWND_TOPMOST) failed: {e}"))
+    }
+}
+
+/// Undo `promote_to_screensaver`: parent `hwnd` to `desktop` again, placed
+/// over `rect` in screen coordinates.  Style and layer are left to
+/// `apply_host_style`.
+fn return_to_desktop(hwnd: HWND, desktop: HWND, rect: RECT) -> std::result::Result<(), String> {
+    let parent_rect = window_rect(desktop).ok_or_else(|| "Failed to read desktop host window rect".to_string())?;
+    unsafe {
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let _ = SetWindowLongW(hwnd, GWL_STYLE, ((style & !WS_POPUP.0) | WS_CHILD.0) as i32);
+        SetParent(hwnd, Some(desktop)).map_err(|e| format!("SetParent failed: {e}"))?;
+        SetWindowPos(
+            hwnd,
+            None,
+            rect.left - parent_rect.left,
+            rect.top - parent_rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+        )
+        .map_err(|e| format!("SetWindowPos failed: {e}"))
+    }
+}
+
+fn raise_above_siblings(hwnd: HWND) {
+    let flags = SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE;
+    if unsafe { SetWindowPos(hwnd, Some(HWND_TOP), 0, 0, 0, 0, flags) }.is_err() {
+        warn!("[WALLPAPER][HOST] SetWindowPos(HWND_TOP) failed for {:?}", hwnd);
+    }
+}
+
+fn is_overlay_z_index(z_index: &str) -> bool {
+    z_index.eq_ignore_ascii_case("overlay")
+}
+
+/// Create a top-level, click-through popup covering `rect` that stays above
+/// normal app windows.  Unlike WorkerW children this really is topmost, at the
+/// cost of sitting over desktop icons and every non-topmost window; input
+/// passes straight through (`WS_EX_TRANSPARENT | WS_EX_LAYERED`), so the page
+/// never receives native pointer events.
+fn create_overlay_window(rect: RECT) -> std::result::Result<HWND, String> {
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    warn!(
+        "[WALLPAPER][HOST] creating overlay window pos=({}, {}) size={}x{}",
+        rect.left,
+        rect.top,
+        width,
+        height
+    );
+
+    let style = WINDOW_STYLE((WS_POPUP | WS_VISIBLE | WS_CLIPCHILDREN).0);
+    let ex_style = WINDOW_EX_STYLE(
+        (WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_TOPMOST | WS_EX_LAYERED | WS_EX_TRANSPARENT).0,
+    );
+
+    let hinstance = unsafe {
+        GetModuleHandleW(None)
+            .map(|h| HINSTANCE(h.0))
+            .map_err(|e| format!("GetModuleHandleW failed: {e:?}"))?
+    };
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            ex_style,
+            HOST_CLASS_NAME,
+            PCWSTR::null(),
+            style,
+            rect.left,
+            rect.top,
+            width,
+            height,
+            None,
+            None,
+            Some(hinstance),
+            Some(ptr::null()),
+        )
+    }
+    .map_err(|e| format!("CreateWindowExW (overlay) failed: {e:?}"))?;
+
+    unsafe {
+        // Layered windows stay invisible until their attributes are set.
+        if let Err(e) = SetLayeredWindowAttributes(hwnd, COLORREF(0), 255, LWA_ALPHA) {
+            let _ = DestroyWindow(hwnd);
+            return Err(format!("SetLayeredWindowAttributes failed: {e:?}"));
+        }
+
+        if SetWindowPos(
+            hwnd,
+            Some(HWND_TOPMOST),
+            rect.left,
+            rect.top,
+            width,
+            height,
+            SWP_NOACTIVATE | SWP_SHOWWINDOW,
+        )
+        .is_err()
+        {
+            warn!("[WALLPAPER][HOST] SetWindowPos(HWND_TOPMOST) failed for overlay {:?}", hwnd);
+        }
+    }
+
+    Ok(hwnd)
+}
+
+/// Fade a (layered) overlay window.  WebView2 has no controller-level
+/// opacity, but the overlay popup is already layered, so its alpha blends
+/// the whole page with whatever is underneath.
+fn set_overlay_opacity(hwnd: HWND, opacity: f32) -> std::result::Result<(), String> {
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+    unsafe { SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA) }
+        .map_err(|e| format!("SetLayeredWindowAttributes(opacity) failed: {e:?}"))
+}
+
+fn window_rect(hwnd: HWND) -> Option<RECT> {
+    unsafe {
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).is_ok() {
+            Some(rect)
+        } else {
+            None
+        }
+    }
+}
+
+fn apply_host_style(hwnd: HWND, z_index: &str, click_through: bool) -> std::result::Result<(), String> {
+    unsafe {
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let mut new_style = style
+            & !(WS_CAPTION.0 | WS_THICKFRAME.0 | WS_MINIMIZEBOX.0 | WS_MAXIMIZEBOX.0 | WS_SYSMENU.0);
+        new_style |= WS_VISIBLE.0 | WS_CHILD.0;
+        let _ = SetWindowLongW(hwnd, GWL_STYLE, new_style as i32);
+
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        let mut new_ex = ex_style & !(WS_EX_APPWINDOW.0 | WS_EX_WINDOWEDGE.0 | WS_EX_DLGMODALFRAME.0);
+        new_ex |= WS_EX_TOOLWINDOW.0 | WS_EX_NOACTIVATE.0;
+        if click_through {
+            // Mouse input falls through to the real desktop (icons, double-clicks);
+            // the page then only sees the coordinates Sentinel forwards itself.
+            new_ex |= WS_EX_TRANSPARENT.0;
+        } else {
+            new_ex &= !WS_EX_TRANSPARENT.0;
+        }
+        let _ = SetWindowLongW(hwnd, GWL_EXSTYLE, new_ex as i32);
+
+        let insert_after = match z_index.to_lowercase().as_str() {
+            "desktop" => HWND_TOPMOST,
+            "bottom" => HWND_BOTTOM,
+            "normal" => HWND_NOTOPMOST,
+            "top" => HWND_TOP,
+            "topmost" => HWND_TOPMOST,
+            _ => HWND_BOTTOM,
+        };
+        warn!(
+            "[WALLPAPER][STYLE] hwnd={:?} old_style=0x{:X} new_style=0x{:X} old_ex=0x{:X} new_ex=0x{:X}",
+            hwnd,
+            style,
+            new_style,
+            ex_style,
+            new_ex
+        );
+        warn!("[WALLPAPER][STYLE] insert_after={:?}", insert_after);
+
+        if SetWindowPos(
+            hwnd,
+            Some(insert_after),
             0,
-            height as u32,
-            Some(pixels.as_mut_ptr() as *mut core::ffi::c_void),
-            &mut bmi,
-            DIB_RGB_COLORS,
-        );
+            0,
+            0,
+            0,
+            SWP_NOACTIVATE | SWP_SHOWWINDOW | SWP_FRAMECHANGED,
+        )
+        .is_err()
+        {
+            return Err("SetWindowPos failed for host style".to_string());
+        }
+    }
 
-        let _ = SelectObject(mem_dc, old);
-        let _ = DeleteObject(HGDIOBJ(bitmap.0));
-        let _ = DeleteDC(mem_dc);
-        let _ = ReleaseDC(Some(hwnd), src_dc);
+    Ok(())
+}
 
-        if lines == 0 {
-            return Err("GetDIBits failed".to_string());
-        }
+/// 50ms, 100ms, 200ms, ... capped at 2s.
+fn registry_retry_backoff(failures: u32) -> Duration {
+    Duration::from_millis((50u64 << failures.saturating_sub(1).min(6)).min(2000))
+}
 
-        Ok(pixels)
+fn process_failed_kind_name(kind: COREWEBVIEW2_PROCESS_FAILED_KIND) -> &'static str {
+    match kind {
+        COREWEBVIEW2_PROCESS_FAILED_KIND_BROWSER_PROCESS_EXITED => "browser process exited",
+        COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_EXITED => "render process exited",
+        COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_UNRESPONSIVE => "render process unresponsive",
+        COREWEBVIEW2_PROCESS_FAILED_KIND_FRAME_RENDER_PROCESS_EXITED => "frame render process exited",
+        COREWEBVIEW2_PROCESS_FAILED_KIND_GPU_PROCESS_EXITED => "GPU process exited",
+        _ => "helper process exited",
     }
 }
 
-/// Background thread that stitches raw pixel captures into an RgbaImage
-/// and saves the BMP to disk.  No SPI call — just keeps the file fresh.
-fn snapshot_worker(rx: mpsc::Receiver<SnapshotJob>) {
-    while let Ok(job) = rx.recv() {
-        let mut stitched = RgbaImage::from_pixel(
-            job.virtual_width as u32,
-            job.virtual_height as u32,
-            Rgba([0, 0, 0, 255]),
-        );
-        let mut has_non_black_pixel = false;
+fn process_failed_reason_name(reason: COREWEBVIEW2_PROCESS_FAILED_REASON) -> &'static str {
+    match reason {
+        COREWEBVIEW2_PROCESS_FAILED_REASON_CRASHED => "crashed",
+        COREWEBVIEW2_PROCESS_FAILED_REASON_LAUNCH_FAILED => "launch failed",
+        COREWEBVIEW2_PROCESS_FAILED_REASON_OUT_OF_MEMORY => "out of memory",
+        COREWEBVIEW2_PROCESS_FAILED_REASON_PROFILE_DELETED => "profile deleted",
+        COREWEBVIEW2_PROCESS_FAILED_REASON_TERMINATED => "terminated",
+        COREWEBVIEW2_PROCESS_FAILED_REASON_UNRESPONSIVE => "unresponsive",
+        _ => "unexpected",
+    }
+}
 
-        for (r, pixels) in &job.captures {
-            let width = (r.right - r.left).max(1);
-            let height = (r.bottom - r.top).max(1);
-            let offset_x = (r.left - job.min_left).max(0);
-            let offset_y = (r.top - job.min_top).max(0);
-
-            for y in 0..height {
-                for x in 0..width {
-                    let src = ((y * width + x) * 4) as usize;
-                    if src + 3 >= pixels.len() { continue; }
-                    let b = pixels[src];
-                    let g = pixels[src + 1];
-                    let r = pixels[src + 2];
-                    if r != 0 || g != 0 || b != 0 { has_non_black_pixel = true; }
-                    let dst_x = (offset_x + x) as u32;
-                    let dst_y = (offset_y + y) as u32;
-                    if dst_x < stitched.width() && dst_y < stitched.height() {
-                        stitched.put_pixel(dst_x, dst_y, Rgba([r, g, b, 255]));
-                    }
-                }
+/// Install or remove a low-level hook to match `enabled`.  Returns `true`
+/// when a hook was newly installed, so the caller can reset its state.
+fn set_hook(hook: &mut Option<HHOOK>, enabled: bool, id: WINDOWS_HOOK_ID, proc: HOOKPROC) -> bool {
+    match (enabled, *hook) {
+        (true, None) => match unsafe { SetWindowsHookExW(id, proc, None, 0) } {
+            Ok(installed) => {
+                *hook = Some(installed);
+                true
             }
+            Err(e) => {
+                warn!("[WALLPAPER][HOOK] SetWindowsHookExW({}) failed: {:?}", id.0, e);
+                false
+            }
+        },
+        (false, Some(installed)) => {
+            let _ = unsafe { UnhookWindowsHookEx(installed) };
+            *hook = None;
+            false
         }
+        _ => false,
+    }
+}
 
-        if !has_non_black_pixel {
-            continue;
-        }
-
-        let snapshot_dir = sentinel_assets_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("wallpaper")
-            .join("snapshots");
-        let _ = fs::create_dir_all(&snapshot_dir);
-        let snapshot_path = snapshot_dir.join("paused_wallpaper_snapshot.bmp");
-        if let Err(e) = stitched.save(&snapshot_path) {
-            warn!("[WALLPAPER][SNAP] Failed to save snapshot: {}", e);
+/// `WH_KEYBOARD_LL` callback: record key-downs while the desktop (not the
+/// taskbar or any app) is the foreground window.  Key-ups are recorded
+/// wherever focus is, so a key released after focus moved still ends; the
+/// tick drops ups for keys it never saw go down.
+unsafe extern "system" fn key_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let down = match wparam.0 as u32 {
+            WM_KEYDOWN | WM_SYSKEYDOWN => Some(true),
+            WM_KEYUP | WM_SYSKEYUP => Some(false),
+            _ => None,
+        };
+        if let Some(down) = down.filter(|&down| !down || is_desktop_foreground()) {
+            let info = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+            if let Ok(mut events) = KEY_EVENTS.lock() {
+                // Bounded in case the tick loop stalls.
+                if events.len() < 256 {
+                    events.push((info.vkCode, down));
+                }
+            }
         }
     }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
 }
 
-fn apply_windows_wallpaper(path: &Path) -> std::result::Result<(), String> {
-    let wide = to_wstring(path.to_string_lossy().as_ref());
-    unsafe {
-        SystemParametersInfoW(
-            SPI_SETDESKWALLPAPER,
-            0,
-            Some(wide.as_ptr() as *mut core::ffi::c_void),
-            SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+fn is_desktop_foreground() -> bool {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.0.is_null() {
+        return false;
+    }
+    let mut class_buf = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut class_buf) };
+    len > 0
+        && matches!(
+            String::from_utf16_lossy(&class_buf[..len as usize]).to_ascii_lowercase().as_str(),
+            "progman" | "workerw"
         )
-        .map_err(|e| format!("SystemParametersInfoW(SPI_SETDESKWALLPAPER) failed: {e:?}"))
+}
+
+/// DOM-style `KeyboardEvent.key`-like name for a virtual-key code.
+fn key_name(vk: u32) -> String {
+    match vk {
+        0x30..=0x39 | 0x41..=0x5A => char::from(vk as u8).to_string(),
+        0x70..=0x87 => format!("F{}", vk - 0x6F),
+        0x60..=0x69 => format!("Numpad{}", vk - 0x60),
+        0x08 => "Backspace".into(),
+        0x09 => "Tab".into(),
+        0x0D => "Enter".into(),
+        0x10 | 0xA0 | 0xA1 => "Shift".into(),
+        0x11 | 0xA2 | 0xA3 => "Control".into(),
+        0x12 | 0xA4 | 0xA5 => "Alt".into(),
+        0x5B | 0x5C => "Meta".into(),
+        0x14 => "CapsLock".into(),
+        0x1B => "Escape".into(),
+        0x20 => " ".into(),
+        0x21 => "PageUp".into(),
+        0x22 => "PageDown".into(),
+        0x23 => "End".into(),
+        0x24 => "Home".into(),
+        0x25 => "ArrowLeft".into(),
+        0x26 => "ArrowUp".into(),
+        0x27 => "ArrowRight".into(),
+        0x28 => "ArrowDown".into(),
+        0x2D => "Insert".into(),
+        0x2E => "Delete".into(),
+        _ => "Unidentified".into(),
     }
 }
 
-#[derive(Default, Clone, Copy)]
-struct MonitorWindowStates {
-    focused: bool,
-    maximized: bool,
-    fullscreen: bool,
+/// `WH_MOUSE_LL` callback: accumulate wheel deltas and pass every event on
+/// untouched.
+unsafe extern "system" fn scroll_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let message = wparam.0 as u32;
+        if message == WM_MOUSEWHEEL || message == WM_MOUSEHWHEEL {
+            let info = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+            let delta = (info.mouseData >> 16) as u16 as i16 as i32;
+            let total = if message == WM_MOUSEWHEEL { &SCROLL_DELTA_Y } else { &SCROLL_DELTA_X };
+            total.fetch_add(delta, Ordering::Relaxed);
+        }
+    }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
 }
 
-#[derive(Clone, Copy)]
-struct ForegroundWindowSnapshot {
-    monitor_rect: RECT,
-    states: MonitorWindowStates,
+fn is_remote_session() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
 }
 
-fn mode_triggered(mode: PauseMode, local_triggered: bool, any_triggered: bool) -> bool {
-    match mode {
-        PauseMode::Off => false,
-        PauseMode::PerMonitor => local_triggered,
-        PauseMode::AllMonitors => any_triggered,
-    }
+/// 500ms, 1s, 2s, ... capped at 8s.
+fn navigation_retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500u64.saturating_mul(1 << attempt.min(4)))
 }
 
-fn monitor_window_states(appdata: &Value, monitor_id: &str) -> MonitorWindowStates {
-    let windows = appdata
-        .get(monitor_id)
-        .and_then(|v| v.get("windows"))
-        .and_then(|v| v.as_array());
+/// `native_monitor_info` for a host: DPI scale, refresh rate, primary flag
+/// and device name.  Static for the host's lifetime (a layout change reapplies).
+fn monitor_info_payload(monitor: &MonitorArea, scale: f64) -> String {
+    serde_json::json!({
+        "type": "native_monitor_info",
+        "index": monitor.index,
+        "device_name": monitor.device_name,
+        "primary": monitor.primary,
+        "dpi_scale": scale,
+        "refresh_rate": monitor_refresh_rate(&monitor.device_name),
+    })
+    .to_string()
+}
 
-    let mut states = MonitorWindowStates::default();
-    let Some(windows) = windows else {
-        return states;
-    };
+/// Current refresh rate (Hz) of a display device, or `None` when unknown.
+fn monitor_refresh_rate(device_name: &str) -> Option<u32> {
+    if device_name.is_empty() {
+        return None;
+    }
 
-    for window in windows {
-        let (focused, maximized, fullscreen) = window_flags(window);
+    let wide = to_wstring(device_name);
+    let mut mode = DEVMODEW {
+        dmSize: mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    let ok = unsafe { EnumDisplaySettingsW(PCWSTR(wide.as_ptr()), ENUM_CURRENT_SETTINGS, &mut mode) };
+    // 0 and 1 both mean "hardware default" rather than a real rate.
+    (ok.as_bool() && mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency)
+}
 
-        if focused {
-            states.focused = true;
-        }
-        if maximized {
-            states.maximized = true;
-        }
-        if fullscreen {
-            states.fullscreen = true;
+/// Effective DPI of the monitor containing most of `rect`, as a WebView2
+/// rasterization scale.
+fn monitor_rasterization_scale(rect: RECT) -> f64 {
+    match monitor_effective_dpi(rect) {
+        Ok(dpi) => dpi_to_scale(dpi),
+        Err(e) => {
+            warn!("[WALLPAPER][EMBED] GetDpiForMonitor failed, assuming 96 DPI: {}", e);
+            1.0
         }
     }
-
-    states
 }
 
-fn foreground_window_snapshot() -> Option<ForegroundWindowSnapshot> {
+fn monitor_effective_dpi(rect: RECT) -> std::result::Result<u32, String> {
+    let mut dpi_x = 0u32;
+    let mut dpi_y = 0u32;
     unsafe {
-        let hwnd = GetForegroundWindow();
-        if hwnd.0.is_null() {
-            return None;
-        }
+        let monitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST);
+        GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y)
+            .map_err(|e| format!("{e:?}"))?;
+    }
+    Ok(dpi_x)
+}
 
-        if is_shell_foreground_window(hwnd) {
-            return None;
-        }
+/// 96 DPI is 100% scaling; 0 (unknown) is treated as 100%.
+fn dpi_to_scale(dpi: u32) -> f64 {
+    if dpi == 0 {
+        1.0
+    } else {
+        dpi as f64 / 96.0
+    }
+}
 
-        let mut states = MonitorWindowStates {
-            focused: true,
-            maximized: IsZoomed(hwnd).0 != 0,
-            fullscreen: false,
-        };
+const WEBVIEW2_DOWNLOAD_URL: &str = "https://go.microsoft.com/fwlink/p/?LinkId=2124703";
 
-        let mut rect = RECT::default();
-        if GetWindowRect(hwnd, &mut rect).is_err() {
-            return Some(ForegroundWindowSnapshot {
-                monitor_rect: RECT::default(),
-                states,
-            });
-        }
+/// Version of the installed Evergreen WebView2 runtime, e.g. `120.0.2210.91`.
+pub fn webview2_runtime_version() -> std::result::Result<String, String> {
+    let mut version = PWSTR::null();
+    unsafe { GetAvailableCoreWebView2BrowserVersionString(PCWSTR::null(), &mut version) }
+        .map_err(|e| format!("GetAvailableCoreWebView2BrowserVersionString failed: {e:?}"))?;
+    if version.is_null() {
+        return Err("no WebView2 runtime installed".to_string());
+    }
+    Ok(webview2_com::take_pwstr(version))
+}
 
-        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
-        if monitor.0.is_null() {
-            return Some(ForegroundWindowSnapshot {
-                monitor_rect: RECT::default(),
-                states,
-            });
-        }
+/// Create a WebView2 environment.  With `args` the browser gets them as
+/// additional command-line arguments and its own user data folder, since
+/// WebView2 refuses a second environment on one folder with other options.
+/// The default set (only the `allow` autoplay switch) keeps the default
+/// folder, so existing wallpapers keep their storage.
+fn create_webview_environment(
+    args: &[String],
+) -> std::result::Result<ICoreWebView2Environment, WebViewError> {
+    let (tx, rx) = mpsc::channel();
+    let args = args.to_vec();
+
+    webview2_com::CreateCoreWebView2EnvironmentCompletedHandler::wait_for_async_operation(
+        Box::new(move |handler| unsafe {
+            if args.is_empty() {
+                return CreateCoreWebView2Environment(&handler)
+                    .map_err(webview2_com::Error::WindowsError);
+            }
+            let options = webview2_com::CoreWebView2EnvironmentOptions::default();
+            options.set_additional_browser_arguments(args.join(" "));
+            let options: ICoreWebView2EnvironmentOptions = options.into();
+            let user_data = (args != [AutoplayPolicy::Allow.browser_arg()])
+                .then(|| to_wstring(&webview_args_data_dir(&args).to_string_lossy()));
+            CreateCoreWebView2EnvironmentWithOptions(
+                PCWSTR::null(),
+                user_data.as_ref().map_or(PCWSTR::null(), |dir| PCWSTR(dir.as_ptr())),
+                &options,
+                &handler,
+            )
+            .map_err(webview2_com::Error::WindowsError)
+        }),
+        Box::new(move |error_code, environment| {
+            error_code?;
+            tx.send(environment.ok_or_else(|| windows::core::Error::from(E_POINTER)))
+                .expect("send WebView2 environment");
+            Ok(())
+        }),
+    )
+    .map_err(|e| WebViewError::from_creation("CreateCoreWebView2Environment", e))?;
 
-        let mut mi_ex: MONITORINFOEXW = std::mem::zeroed();
-        mi_ex.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
-        if GetMonitorInfoW(monitor, &mut mi_ex.monitorInfo).0 == 0 {
-            return Some(ForegroundWindowSnapshot {
-                monitor_rect: RECT::default(),
-                states,
-            });
-        }
+    rx.recv()
+        .map_err(|_| WebViewError::ChannelRecv("Failed to receive WebView2 environment".to_string()))?
+        .map_err(|e| WebViewError::from_creation("WebView2 environment", e.into()))
+}
 
-        let monitor_rc = mi_ex.monitorInfo.rcMonitor;
-        let epsilon = 1i32;
-        let covers_monitor = (rect.left - monitor_rc.left).abs() <= epsilon
-            && (rect.top - monitor_rc.top).abs() <= epsilon
-            && (rect.right - monitor_rc.right).abs() <= epsilon
-            && (rect.bottom - monitor_rc.bottom).abs() <= epsilon;
+fn create_webview_controller(
+    environment: &ICoreWebView2Environment,
+    hwnd: HWND,
+    bounds: RECT,
+    options: WebViewOptions<'_>,
+    url: &str,
+    events: &HostEventQueue,
+) -> std::result::Result<(ICoreWebView2Controller, String), WebViewError> {
+    let mut url = url.to_string();
 
-        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
-        let has_frame = (style & (WS_CAPTION.0 | WS_THICKFRAME.0)) != 0;
-        states.fullscreen = covers_monitor && !has_frame;
+    let controller = {
+        let (tx, rx) = mpsc::channel();
+        let environment = environment.clone();
 
-        Some(ForegroundWindowSnapshot {
-            monitor_rect: monitor_rc,
-            states,
-        })
-    }
-}
+        webview2_com::CreateCoreWebView2ControllerCompletedHandler::wait_for_async_operation(
+            Box::new(move |handler| unsafe {
+                environment
+                    .CreateCoreWebView2Controller(hwnd, &handler)
+                    .map_err(webview2_com::Error::WindowsError)
+            }),
+            Box::new(move |error_code, controller| {
+                error_code?;
+                tx.send(controller.ok_or_else(|| windows::core::Error::from(E_POINTER)))
+                    .expect("send WebView2 controller");
+                Ok(())
+            }),
+        )
+        .map_err(|e| WebViewError::from_creation("CreateCoreWebView2Controller", e))?;
 
-fn is_shell_foreground_window(hwnd: HWND) -> bool {
-    let mut class_buf = [0u16; 256];
-    let len = unsafe { GetClassNameW(hwnd, &mut class_buf) };
-    if len <= 0 {
-        return false;
-    }
+        rx.recv()
+            .map_err(|_| WebViewError::ChannelRecv("Failed to receive WebView2 controller".to_string()))?
+            .map_err(|e| WebViewError::from_creation("WebView2 controller", e.into()))?
+    };
+    warn!("[WALLPAPER][WEBVIEW] controller ready for hwnd={:?}", hwnd);
 
-    let class_name = String::from_utf16_lossy(&class_buf[..len as usize]).to_ascii_lowercase();
-    matches!(
-        class_name.as_str(),
-        "progman" | "workerw" | "shell_traywnd" | "shell_secondarytraywnd"
-    )
-}
+    unsafe {
+        warn!(
+            "[WALLPAPER][WEBVIEW] setting bounds {}x{} at ({}, {}) and navigating to '{}'",
+            bounds.right - bounds.left,
+            bounds.bottom - bounds.top,
+            bounds.left,
+            bounds.top,
+            url
+        );
+        controller
+            .SetBounds(bounds)
+            .map_err(|e| format!("WebView2 SetBounds failed: {e:?}"))?;
 
-fn window_flags(window: &Value) -> (bool, bool, bool) {
-    let focused = window
-        .get("focused")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+        // Bounds are physical pixels; pin the scale to the target monitor's
+        // DPI instead of letting WebView2 pick it up from the parent window,
+        // which is wrong for WorkerW children on mixed-DPI setups.
+        match controller.cast::<ICoreWebView2Controller3>() {
+            Ok(controller3) => {
+                let _ = controller3.SetShouldDetectMonitorScaleChanges(false);
+                if let Err(e) = controller3.SetRasterizationScale(options.rasterization_scale) {
+                    warn!("[WALLPAPER][WEBVIEW] SetRasterizationScale failed: {:?}", e);
+                }
+            }
+            Err(e) => warn!(
+                "[WALLPAPER][WEBVIEW] ICoreWebView2Controller3 unavailable; using default scale: {:?}",
+                e
+            ),
+        }
 
-    let state = window
-        .get("window_state")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_lowercase();
+        // Shown before first paint and behind transparent pages; WebView2's
+        // own default is white, which flashes on a dark desktop.
+        match controller.cast::<ICoreWebView2Controller2>() {
+            Ok(controller2) => {
+                let color = COREWEBVIEW2_COLOR {
+                    A: 255,
+                    R: options.background[0],
+                    G: options.background[1],
+                    B: options.background[2],
+                };
+                if let Err(e) = controller2.SetDefaultBackgroundColor(color) {
+                    warn!("[WALLPAPER][WEBVIEW] SetDefaultBackgroundColor failed: {:?}", e);
+                }
+            }
+            Err(e) => warn!(
+                "[WALLPAPER][WEBVIEW] ICoreWebView2Controller2 unavailable; using default background: {:?}",
+                e
+            ),
+        }
+
+        controller
+            .SetIsVisible(true)
+            .map_err(|e| format!("WebView2 SetIsVisible failed: {e:?}"))?;
+
+        let webview = controller
+            .CoreWebView2()
+            .map_err(|e| format!("WebView2 CoreWebView2 unavailable: {e:?}"))?;
+
+        // Register before the first Navigate so its completion is observed.
+        let queue = Rc::clone(events);
+        let handler = webview2_com::NavigationCompletedEventHandler::create(Box::new(
+            move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let mut success = BOOL::default();
+                let mut status = COREWEBVIEW2_WEB_ERROR_STATUS::default();
+                args.IsSuccess(&mut success)?;
+                args.WebErrorStatus(&mut status)?;
+                queue.borrow_mut().push(HostEvent::NavigationCompleted {
+                    success: success.as_bool(),
+                    status,
+                });
+                Ok(())
+            },
+        ));
+        let mut token = 0i64;
+        webview
+            .add_NavigationCompleted(&handler, &mut token)
+            .map_err(|e| format!("WebView2 add_NavigationCompleted failed: {e:?}"))?;
+
+        let queue = Rc::clone(events);
+        let handler = webview2_com::ProcessFailedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else {
+                return Ok(());
+            };
+            let mut kind = COREWEBVIEW2_PROCESS_FAILED_KIND::default();
+            args.ProcessFailedKind(&mut kind)?;
+            let reason = args
+                .cast::<ICoreWebView2ProcessFailedEventArgs2>()
+                .ok()
+                .and_then(|args2| {
+                    let mut reason = COREWEBVIEW2_PROCESS_FAILED_REASON::default();
+                    args2.Reason(&mut reason).ok().map(|_| reason)
+                });
+            queue.borrow_mut().push(HostEvent::ProcessFailed { kind, reason });
+            Ok(())
+        }));
+        webview
+            .add_ProcessFailed(&handler, &mut token)
+            .map_err(|e| format!("WebView2 add_ProcessFailed failed: {e:?}"))?;
+
+        let queue = Rc::clone(events);
+        let handler = webview2_com::WebMessageReceivedEventHandler::create(Box::new(
+            move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let mut json = PWSTR::null();
+                args.WebMessageAsJson(&mut json)?;
+                queue
+                    .borrow_mut()
+                    .push(HostEvent::WebMessage(webview2_com::take_pwstr(json)));
+                Ok(())
+            },
+        ));
+        webview
+            .add_WebMessageReceived(&handler, &mut token)
+            .map_err(|e| format!("WebView2 add_WebMessageReceived failed: {e:?}"))?;
+
+        if let Some((host_name, dir)) = options.asset_host {
+            let host = to_wstring(host_name);
+            let folder = to_wstring(&dir.to_string_lossy());
+            // The GIF player page is a `data:` document, so it needs
+            // CORS-level access to draw (and pause) the GIF.
+            let mapped = webview
+                .cast::<ICoreWebView2_3>()
+                .map_err(|e| format!("WebView2 ICoreWebView2_3 unavailable: {e:?}"))
+                .and_then(|webview3| {
+                    webview3
+                        .SetVirtualHostNameToFolderMapping(
+                            PCWSTR(host.as_ptr()),
+                            PCWSTR(folder.as_ptr()),
+                            COREWEBVIEW2_HOST_RESOURCE_ACCESS_KIND_ALLOW,
+                        )
+                        .map_err(|e| {
+                            format!("WebView2 SetVirtualHostNameToFolderMapping failed: {e:?}")
+                        })
+                });
+            match mapped {
+                Ok(()) => {}
+                // `virtual_host` pages also exist on disk; the GIF player
+                // has nothing to fall back to.
+                Err(e) if host_name == VIRTUAL_ASSET_HOST => {
+                    url = virtual_host_fallback_url(&url, dir);
+                    warn!(
+                        "[WALLPAPER][WEBVIEW] {}; loading '{}' from file:// instead",
+                        e,
+                        url
+                    );
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-    let maximized = state.contains("maximized") || state.contains("maximised");
-    let fullscreen = state.contains("fullscreen") || state.contains("full screen");
+        if !options.allow_remote {
+            // Top-level and iframe navigations both go through here; the
+            // same handler cancels anything that isn't local content.
+            let handler = webview2_com::NavigationStartingEventHandler::create(Box::new(
+                move |_, args| {
+                    let Some(args) = args else {
+                        return Ok(());
+                    };
+                    let mut uri = PWSTR::null();
+                    args.Uri(&mut uri)?;
+                    let uri_text = webview2_com::take_pwstr(uri);
+                    if !is_local_content_url(&uri_text) {
+                        warn!(
+                            "[WALLPAPER][SANDBOX] Blocked navigation to '{}' (manifest allow_remote: false)",
+                            uri_text
+                        );
+                        args.SetCancel(true)?;
+                    }
+                    Ok(())
+                },
+            ));
+            webview
+                .add_NavigationStarting(&handler, &mut token)
+                .map_err(|e| format!("WebView2 add_NavigationStarting failed: {e:?}"))?;
+            webview
+                .add_FrameNavigationStarting(&handler, &mut token)
+                .map_err(|e| format!("WebView2 add_FrameNavigationStarting failed: {e:?}"))?;
+        }
 
-    (focused, maximized, fullscreen)
-}
+        let url_wide = to_wstring(&url);
+        webview
+            .Navigate(PCWSTR(url_wide.as_ptr()))
+            .map_err(|e| {
+                WebViewError::NavigationFailed(format!("WebView2 Navigate failed for '{}': {e:?}", url))
+            })?;
+    }
+    warn!("[WALLPAPER][WEBVIEW] navigation submitted successfully");
 
-fn rect_matches_monitor(lhs: RECT, rhs: RECT) -> bool {
-    let epsilon = 1;
-    (lhs.left - rhs.left).abs() <= epsilon
-        && (lhs.top - rhs.top).abs() <= epsilon
-        && (lhs.right - rhs.right).abs() <= epsilon
-        && (lhs.bottom - rhs.bottom).abs() <= epsilon
+    Ok((controller, url))
 }
 
-fn resolve_monitor_id_for_rect(sysdata: &Value, rect: RECT) -> Option<String> {
-    let displays = sysdata.get("displays")?.as_array()?;
+/// Registry wallpaper assets merged with a scan of the local
+/// `Assets/wallpaper` folder.  The registry wins on id collisions; the scan
+/// keeps installed wallpapers usable while the backend is unreachable.
+fn fetch_wallpaper_assets() -> Vec<RegistryAsset> {
+    let mut assets = fetch_registry_wallpaper_assets();
+    let Some(wallpaper_dir) = sentinel_assets_dir().map(|dir| dir.join("wallpaper")) else {
+        return assets;
+    };
 
-    let mut best_overlap: i64 = -1;
-    let mut best_overlap_id: Option<String> = None;
-    let mut best_distance: i64 = i64::MAX;
-    let mut nearest_id: Option<String> = None;
+    let local = scan_local_wallpaper_assets(&wallpaper_dir);
+    let local_only = merge_local_wallpaper_assets(&mut assets, local);
+    if local_only > 0 {
+        warn!(
+            "[WALLPAPER] Added {} local wallpaper asset(s) not listed by the registry",
+            local_only
+        );
+    }
 
-    for display in displays {
-        let metadata = display.get("metadata").unwrap_or(display);
-        let x = metadata.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        let y = metadata.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        let width = metadata.get("width").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        let height = metadata.get("height").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+    assets
+}
 
-        if width <= 0 || height <= 0 {
-            continue;
+/// Append the `local` assets whose id `assets` does not already list;
+/// returns how many were added.
+fn merge_local_wallpaper_assets(assets: &mut Vec<RegistryAsset>, local: Vec<RegistryAsset>) -> usize {
+    let mut added = 0usize;
+    for asset in local {
+        if !assets.iter().any(|a| a.id == asset.id) {
+            assets.push(asset);
+            added += 1;
         }
+    }
+    added
+}
 
-        let id = display
-            .get("id")
-            .and_then(|v| v.as_str())
-            .or_else(|| metadata.get("id").and_then(|v| v.as_str()))
-            .map(|v| v.to_string());
+/// One asset per subfolder of `wallpaper_dir` that has a readable
+/// `manifest.json`.  The id is the manifest `id`, falling back to the folder
+/// name; the whole manifest becomes `metadata` so `url`/`name` resolve.
+fn scan_local_wallpaper_assets(wallpaper_dir: &Path) -> Vec<RegistryAsset> {
+    let Ok(entries) = fs::read_dir(wallpaper_dir) else {
+        return Vec::new();
+    };
 
-        let Some(id) = id else {
-            continue;
-        };
+    let mut assets: Vec<RegistryAsset> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let manifest = fs::read_to_string(path.join("manifest.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok())?;
+            let id = manifest
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(|id| id.to_string())
+                .or_else(|| path.file_name()?.to_str().map(|name| name.to_string()))?;
+            Some(RegistryAsset {
+                id,
+                category: "wallpaper".to_string(),
+                metadata: manifest,
+                path,
+            })
+        })
+        .collect();
 
-        let display_rect = RECT {
-            left: x,
-            top: y,
-            right: x + width,
-            bottom: y + height,
-        };
+    assets.sort_by(|a, b| a.id.cmp(&b.id));
+    assets
+}
 
-        let overlap_left = rect.left.max(display_rect.left);
-        let overlap_top = rect.top.max(display_rect.top);
-        let overlap_right = rect.right.min(display_rect.right);
-        let overlap_bottom = rect.bottom.min(display_rect.bottom);
-        let overlap_w = (overlap_right - overlap_left).max(0) as i64;
-        let overlap_h = (overlap_bottom - overlap_top).max(0) as i64;
-        let overlap_area = overlap_w * overlap_h;
+fn fetch_registry_wallpaper_assets() -> Vec<RegistryAsset> {
+    if let Some(raw) = request("registry", "list_assets", None) {
+        if let Ok(entries) = serde_json::from_str::<Vec<RegistryAsset>>(&raw) {
+            let filtered: Vec<RegistryAsset> = entries
+                .into_iter()
+                .filter(|e| e.category.eq_ignore_ascii_case("wallpaper"))
+                .collect();
 
-        if overlap_area > best_overlap {
-            best_overlap = overlap_area;
-            best_overlap_id = Some(id.clone());
+            if !filtered.is_empty() {
+                return filtered;
+            }
+        } else if let Ok(grouped) = serde_json::from_str::<serde_json::Map<String, Value>>(&raw) {
+            let mut flattened = Vec::<RegistryAsset>::new();
+            for (category, arr) in grouped {
+                if !category.eq_ignore_ascii_case("wallpaper") {
+                    continue;
+                }
+                if let Some(items) = arr.as_array() {
+                    for item in items {
+                        if let Ok(mut asset) = serde_json::from_value::<RegistryAsset>(item.clone()) {
+                            if asset.category.is_empty() {
+                                asset.category = category.clone();
+                            }
+                            flattened.push(asset);
+                        }
+                    }
+                }
+            }
+            if !flattened.is_empty() {
+                return flattened;
+            }
+        } else {
+            warn!("[WALLPAPER] Failed to parse registry list_assets payload");
         }
+    } else {
+        warn!("[WALLPAPER] IPC list_assets request failed");
+    }
 
-        let rect_center_x = ((rect.left + rect.right) / 2) as i64;
-        let rect_center_y = ((rect.top + rect.bottom) / 2) as i64;
-        let display_center_x = ((display_rect.left + display_rect.right) / 2) as i64;
-        let display_center_y = ((display_rect.top + display_rect.bottom) / 2) as i64;
-        let distance = (rect_center_x - display_center_x).abs() + (rect_center_y - display_center_y).abs();
-        if distance < best_distance {
-            best_distance = distance;
-            nearest_id = Some(id);
+    Vec::new()
+}
+
+/// Log what `apply` would host for `config` without creating any windows.
+/// Backs the `--dry-run` flag.
+pub fn log_dry_run_plan(config: &AddonConfig) {
+    let assets = fetch_wallpaper_assets();
+    let monitors = enumerate_monitors();
+    warn!(
+        "[WALLPAPER][PLAN] {} asset(s), {} monitor(s), {} enabled profile(s)",
+        assets.len(),
+        monitors.len(),
+        config.enabled_wallpapers().len()
+    );
+    for monitor in &monitors {
+        warn!(
+            "[WALLPAPER][PLAN] monitor {} primary={} rect=[l={},t={},r={},b={}]",
+            monitor.index,
+            monitor.primary,
+            monitor.rect.left,
+            monitor.rect.top,
+            monitor.rect.right,
+            monitor.rect.bottom
+        );
+    }
+
+    let fallback = if config.settings.development.show_error_wallpaper {
+        "error wallpaper"
+    } else {
+        "solid fallback"
+    };
+    for host in plan_hosts(config, &assets, &monitors) {
+        let rect = host.monitor.rect;
+        match &host.content {
+            Ok((asset, url)) => warn!(
+                "[WALLPAPER][PLAN] {} -> {} -> {} -> {} z_index='{}' [l={},t={},r={},b={}]",
+                host.profile.section,
+                asset.id,
+                url,
+                host.describe_target(),
+                host.z_index,
+                rect.left,
+                rect.top,
+                rect.right,
+                rect.bottom
+            ),
+            Err(reason) => warn!(
+                "[WALLPAPER][PLAN] {} -> '{}' -> {} ({}) -> {} [l={},t={},r={},b={}]",
+                host.profile.section,
+                host.wallpaper_id,
+                fallback,
+                reason,
+                host.describe_target(),
+                rect.left,
+                rect.top,
+                rect.right,
+                rect.bottom
+            ),
         }
     }
+}
 
-    if best_overlap > 0 {
-        return best_overlap_id;
+/// Resolve every enabled section, in priority order, into the hosts `apply`
+/// should create.  Creates no windows, so it is shared with `--dry-run`.
+fn plan_hosts<'a>(
+    config: &'a AddonConfig,
+    assets: &'a [RegistryAsset],
+    monitors: &[MonitorArea],
+) -> Vec<PlannedHost<'a>> {
+    // Unresolved sections still get a host when it has something to show.
+    let keep_unresolved = config.settings.development.show_error_wallpaper
+        || config.settings.rendering.fallback_color.is_some();
+    let mut assigned_monitors = HashSet::<usize>::new();
+    let enabled_profiles = config.enabled_wallpapers();
+    let mut plan = Vec::new();
+
+    for priority in [0u8, 1u8, 2u8, 3u8] {
+        for profile in enabled_profiles.iter().copied() {
+            if profile_priority(profile) != priority {
+                continue;
+            }
+            // Stacked sections neither see nor make claims, so they land on
+            // top of whatever section owns the monitor.
+            let mut stacked_claims = HashSet::new();
+            let claims = if profile.stack {
+                &mut stacked_claims
+            } else {
+                &mut assigned_monitors
+            };
+            plan.extend(plan_profile(profile, assets, monitors, claims, keep_unresolved));
+        }
     }
 
-    best_overlap_id.or(nearest_id)
+    // A child window is created above its siblings, so launching stacked
+    // hosts last, lowest layer first, leaves them in z_index order.
+    plan.sort_by_key(|host| host.profile.stack.then(|| z_index_rank(&host.z_index)));
+    plan
 }
 
-fn power_on_battery(sysdata: &Value) -> bool {
-    let power = match sysdata.get("power") {
-        Some(value) => value,
-        None => return false,
-    };
+/// Stacking position of a `z_index` layer, lowest first.  Unknown values
+/// are inserted at the bottom, like `apply_host_style` does.
+fn z_index_rank(z_index: &str) -> u8 {
+    match z_index.to_lowercase().as_str() {
+        "desktop" => 1,
+        "normal" => 2,
+        "top" => 3,
+        "topmost" => 4,
+        "overlay" => 5,
+        _ => 0,
+    }
+}
 
-    let ac_status = power
-        .get("ac_status")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// Resolve one section's asset, URL and target monitors, claiming the
+/// monitors it takes in `assigned_monitors`.
+fn plan_profile<'a>(
+    profile: &'a WallpaperConfig,
+    assets: &'a [RegistryAsset],
+    monitors: &[MonitorArea],
+    assigned_monitors: &mut HashSet<usize>,
+    keep_unresolved: bool,
+) -> Vec<PlannedHost<'a>> {
+    warn!(
+        "[WALLPAPER][PROFILE] section='{}' wallpaper_id='{}' monitor_index={:?} mode='{}' z_index='{}'",
+        profile.section,
+        profile.wallpaper_id,
+        profile.monitor_index,
+        profile.mode,
+        profile.z_index
+    );
 
-    if matches!(ac_status.as_str(), "online" | "ac" | "plugged" | "plugged_in") {
-        return false;
+    if !profile.per_monitor.is_empty() {
+        return plan_per_monitor_profile(
+            profile,
+            assets,
+            monitors,
+            assigned_monitors,
+            keep_unresolved,
+        );
     }
 
-    if matches!(ac_status.as_str(), "offline" | "battery" | "on_battery") {
-        return true;
+    let resolved = resolve_profile_asset(assets, &profile.section, &profile.wallpaper_id);
+    if resolved.is_err() && !keep_unresolved {
+        return Vec::new();
     }
 
-    power
-        .get("battery")
-        .and_then(|battery| battery.get("present"))
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false)
-        && !power
-            .get("battery")
-            .and_then(|battery| battery.get("charging"))
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false)
-}
+    let targets = resolve_target_monitors(
+        monitors,
+        &profile.monitor_index,
+        assigned_monitors,
+        profile_random_seed(profile),
+    );
+    if targets.is_empty() {
+        warn!(
+            "[WALLPAPER] Section '{}' has no resolved monitor targets",
+            profile.section
+        );
+        return Vec::new();
+    }
 
-fn build_registry_snapshot_and_payload(sections: &HashSet<String>) -> Option<(Value, Value, String)> {
-    // Single IPC round-trip using the combined `snapshot` command.
-    // Uses request_quick (no retries) so the tick loop never blocks for seconds.
-    let mut section_list: Vec<String> = sections.iter().cloned().collect();
-    section_list.sort();
-    let args = serde_json::json!({ "sections": section_list });
-    let snapshot_raw = request_quick("registry", "snapshot", Some(args))?;
-    let snapshot: Value = serde_json::from_str(&snapshot_raw).ok()?;
+    for target in &targets {
+        assigned_monitors.insert(target.index);
+    }
 
-    let sysdata = snapshot.get("sysdata").cloned().unwrap_or(Value::Null);
-    let appdata = snapshot.get("appdata").cloned().unwrap_or(Value::Null);
+    let asset_layer = resolved
+        .as_ref()
+        .ok()
+        .and_then(|(asset, _)| manifest_default_z_index(&asset.path));
+    let layer_for = |monitor: &MonitorArea| z_index_for_monitor(profile, monitor, asset_layer.as_deref());
+
+    let host = |monitor: MonitorArea, z_index: &str, content_rect, span_count: usize, content| PlannedHost {
+        profile,
+        wallpaper_id: profile.wallpaper_id.clone(),
+        monitor,
+        z_index: z_index.to_string(),
+        content_rect,
+        span_count,
+        content,
+    };
 
-    let payload = serde_json::json!({
-        "type": "native_registry",
-        "sysdata": sysdata,
-        "appdata": appdata,
-    })
-    .to_string();
+    // Error hosts are never spanned; each monitor gets its own notice.
+    if resolved.is_ok() && profile.mode.eq_ignore_ascii_case("span") && targets.len() > 1 {
+        let span = make_span_monitor_area(&targets);
+        let first_layer = layer_for(targets[0]);
+        if targets.iter().all(|m| layer_for(m) == first_layer) {
+            let rect = span.rect;
+            return vec![host(span, first_layer, rect, targets.len(), resolved)];
+        }
 
-    Some((sysdata, appdata, payload))
+        // One window can only sit in one layer, so mixed layers split the
+        // span into per-monitor windows that each show their slice of it.
+        warn!(
+            "[WALLPAPER] Section '{}' spans monitors with different z_index; splitting into {} windows",
+            profile.section,
+            targets.len()
+        );
+        return targets
+            .into_iter()
+            .map(|monitor| {
+                host(monitor.clone(), layer_for(monitor), span.rect, 1, resolved.clone())
+            })
+            .collect();
+    }
+
+    targets
+        .into_iter()
+        .map(|monitor| host(monitor.clone(), layer_for(monitor), monitor.rect, 1, resolved.clone()))
+        .collect()
 }
 
-fn post_webview_json(webview: &ICoreWebView2, payload: &str) -> std::result::Result<(), String> {
-    let payload_wide = to_wstring(payload);
-    unsafe {
-        webview
-            .PostWebMessageAsJson(PCWSTR(payload_wide.as_ptr()))
-            .map_err(|e| format!("WebView2 PostWebMessageAsJson failed: {e:?}"))
+/// Plan a section that maps individual monitors to different assets via
+/// `per_monitor`.  Monitors without an entry fall back to `wallpaper_id`
+/// (when set); otherwise they are left unclaimed for later sections.
+fn plan_per_monitor_profile<'a>(
+    profile: &'a WallpaperConfig,
+    assets: &'a [RegistryAsset],
+    monitors: &[MonitorArea],
+    assigned_monitors: &mut HashSet<usize>,
+    keep_unresolved: bool,
+) -> Vec<PlannedHost<'a>> {
+    for (key, id) in &profile.per_monitor {
+        let known_key = key.eq_ignore_ascii_case("p")
+            || key.parse::<usize>().map(|i| i < monitors.len()).unwrap_or(false);
+        if !known_key {
+            warn!(
+                "[WALLPAPER] Section '{}' per_monitor key '{}' does not match any monitor (0..{} or 'p')",
+                profile.section,
+                key,
+                monitors.len().saturating_sub(1)
+            );
+        }
+        if resolve_asset(assets, id).is_none() {
+            warn!(
+                "[WALLPAPER] Section '{}' per_monitor['{}'] references missing wallpaper_id '{}'",
+                profile.section,
+                key,
+                id
+            );
+        }
     }
-}
 
-/// Walk the editable tree from manifest.json and collect { "--css-var": "value" } pairs.
-fn extract_css_vars(editable: &Value) -> serde_json::Map<String, Value> {
-    let mut vars = serde_json::Map::new();
-    let obj = match editable.as_object() {
-        Some(o) => o,
-        None => return vars,
-    };
+    if profile.mode.eq_ignore_ascii_case("span") {
+        warn!(
+            "[WALLPAPER] Section '{}' uses per_monitor; ignoring span mode",
+            profile.section
+        );
+    }
 
-    for (_key, entry) in obj {
-        if let Some(variable) = entry.get("variable").and_then(|v| v.as_str()) {
-            // Direct editable with a variable
-            if let Some(value) = entry.get("value") {
-                vars.insert(variable.to_string(), Value::String(value_to_css_string(value)));
-            }
-        } else if let Some(sub_obj) = entry.as_object() {
-            // Group — iterate sub-entries (skip non-object fields like "name", "description")
-            for (_sub_key, sub) in sub_obj {
-                if let Some(variable) = sub.get("variable").and_then(|v| v.as_str()) {
-                    if let Some(value) = sub.get("value") {
-                        vars.insert(variable.to_string(), Value::String(value_to_css_string(value)));
-                    }
-                }
-            }
+    let targets = resolve_target_monitors(
+        monitors,
+        &profile.monitor_index,
+        assigned_monitors,
+        profile_random_seed(profile),
+    );
+    if targets.is_empty() {
+        warn!(
+            "[WALLPAPER] Section '{}' has no resolved monitor targets",
+            profile.section
+        );
+        return Vec::new();
+    }
+
+    let mut plan = Vec::new();
+    for monitor in targets {
+        let Some(wallpaper_id) = per_monitor_wallpaper_id(profile, monitor) else {
+            warn!(
+                "[WALLPAPER] Section '{}' has no per_monitor entry or wallpaper_id for monitor {}",
+                profile.section,
+                monitor.index
+            );
+            continue;
+        };
+
+        let content = resolve_profile_asset(assets, &profile.section, wallpaper_id);
+        if content.is_err() && !keep_unresolved {
+            continue;
         }
+
+        let asset_layer = content
+            .as_ref()
+            .ok()
+            .and_then(|(asset, _)| manifest_default_z_index(&asset.path));
+        let z_index = z_index_for_monitor(profile, monitor, asset_layer.as_deref()).to_string();
+
+        assigned_monitors.insert(monitor.index);
+        plan.push(PlannedHost {
+            profile,
+            wallpaper_id: wallpaper_id.to_string(),
+            monitor: monitor.clone(),
+            z_index,
+            content_rect: monitor.rect,
+            span_count: 1,
+            content,
+        });
     }
 
-    vars
+    plan
 }
 
-/// Convert a serde_json Value to a CSS-appropriate string.
-fn value_to_css_string(value: &Value) -> String {
-    match value {
-        Value::String(s) => s.clone(),
-        Value::Number(n) => n.to_string(),
-        Value::Bool(b) => b.to_string(),
-        _ => value.to_string(),
+/// Find the asset for `wallpaper_id`.  An exact registry id always wins;
+/// otherwise the asset folder name, then the manifest `name`, are matched
+/// case-insensitively so configs written against friendly names still load.
+fn resolve_asset<'a>(assets: &'a [RegistryAsset], wallpaper_id: &str) -> Option<&'a RegistryAsset> {
+    let wallpaper_id = expand_config_value(wallpaper_id, "wallpaper_id");
+    let wallpaper_id = wallpaper_id.as_ref();
+    if let Some(asset) = assets.iter().find(|a| a.id == wallpaper_id) {
+        return Some(asset);
     }
+
+    // A folder path (typically built from `%VAR%` / `${VAR}`) names the
+    // asset stored there.
+    if wallpaper_id.contains(['\\', '/']) {
+        let wanted = comparable_path(wallpaper_id);
+        if let Some(asset) = assets
+            .iter()
+            .find(|a| comparable_path(&a.path.to_string_lossy()) == wanted)
+        {
+            return Some(asset);
+        }
+    }
+
+    let by_folder = assets.iter().find(|a| {
+        a.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.eq_ignore_ascii_case(wallpaper_id))
+    });
+    let (asset, matched_on) = match by_folder {
+        Some(asset) => (asset, "folder name"),
+        None => {
+            let asset = assets.iter().find(|a| {
+                a.metadata
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(wallpaper_id))
+            })?;
+            (asset, "manifest name")
+        }
+    };
+
+    warn!(
+        "[WALLPAPER] wallpaper_id '{}' matched asset '{}' by {}; use the asset id in config.yaml",
+        wallpaper_id,
+        asset.id,
+        matched_on
+    );
+    Some(asset)
 }
 
-fn ensure_host_class() -> std::result::Result<(), String> {
-    static CLASS_ONCE: OnceLock<bool> = OnceLock::new();
-    if CLASS_ONCE.get().is_some() {
-        return Ok(());
+/// `path` lowercased with `\\` separators and no long-path prefix or
+/// trailing separator, for comparing Windows paths.
+fn comparable_path(path: &str) -> String {
+    strip_long_path_prefix(path)
+        .replace('/', "\\")
+        .trim_end_matches('\\')
+        .to_lowercase()
+}
+
+/// `value` with environment variables expanded (see `expand_env_vars`),
+/// logging any that are unset; `what` names the field for the log.
+fn expand_config_value<'a>(value: &'a str, what: &str) -> Cow<'a, str> {
+    let (expanded, unresolved) = expand_env_vars(value);
+    for name in unresolved {
+        warn!(
+            "[WALLPAPER] {} '{}' references unset environment variable '{}'; left as written",
+            what,
+            value,
+            name
+        );
     }
+    expanded
+}
 
-    let hinstance = unsafe {
-        GetModuleHandleW(None)
-            .map(|h| HINSTANCE(h.0))
-            .map_err(|e| format!("GetModuleHandleW failed: {e:?}"))?
+/// Resolve a section's asset and entry URL.  The error string is a short,
+/// user-facing hint suitable for the error wallpaper.
+fn resolve_profile_asset<'a>(
+    assets: &'a [RegistryAsset],
+    section: &str,
+    wallpaper_id: &str,
+) -> std::result::Result<(&'a RegistryAsset, String), String> {
+    let Some(asset) = resolve_asset(assets, wallpaper_id) else {
+        warn!(
+            "[WALLPAPER] Section '{}' references missing wallpaper_id '{}'",
+            section,
+            wallpaper_id
+        );
+        return Err(format!(
+            "Section '{section}' references a wallpaper that is not installed. Check wallpaper_id in config.yaml or install the asset."
+        ));
     };
 
-    let wc = WNDCLASSW {
-        lpfnWndProc: Some(host_window_proc),
-        hInstance: hinstance,
-        lpszClassName: HOST_CLASS_NAME,
-        ..Default::default()
+    let Some(url) = resolve_asset_url(asset) else {
+        warn!(
+            "[WALLPAPER] Asset '{}' has no 'url', no local index.html and no single .gif or video",
+            asset.id
+        );
+        return Err(format!(
+            "Asset '{}' has no 'url' in its manifest and no index.html (or single .gif / .mp4 / .webm) in {}.",
+            asset.id,
+            asset.path.display()
+        ));
     };
 
-    unsafe {
-        let _ = RegisterClassW(&wc);
-    }
+    warn!(
+        "[WALLPAPER][PROFILE] asset='{}' resolved url='{}'",
+        asset.id,
+        url
+    );
 
-    let _ = CLASS_ONCE.set(true);
-    Ok(())
+    Ok((asset, url))
 }
 
-unsafe extern "system" fn host_window_proc(
-    hwnd: HWND,
-    msg: u32,
-    wparam: WPARAM,
-    lparam: LPARAM,
-) -> LRESULT {
-    DefWindowProcW(hwnd, msg, wparam, lparam)
+/// Self-contained `data:` URL for the error wallpaper.
+fn solid_color_url(color: [u8; 3]) -> String {
+    let html = format!(
+        "<!doctype html><html><body style=\"margin:0;background:#{:02x}{:02x}{:02x}\"></body></html>",
+        color[0], color[1], color[2]
+    );
+    format!("data:text/html;charset=utf-8,{}", percent_encode(&html))
 }
 
-fn create_desktop_child_window(worker: HWND, parent_rect: RECT, rect: RECT) -> std::result::Result<HWND, String> {
-    let x = rect.left - parent_rect.left;
-    let y = rect.top - parent_rect.top;
-    let width = rect.right - rect.left;
-    let height = rect.bottom - rect.top;
-    warn!(
-        "[WALLPAPER][HOST] creating child window parent={:?} pos=({}, {}) size={}x{}",
-        worker,
-        x,
-        y,
-        width,
-        height
+fn error_wallpaper_url(wallpaper_id: &str, reason: &str) -> String {
+    let html = format!(
+        concat!(
+            "<!doctype html><html><head><meta charset=\"utf-8\"><style>",
+            "html,body{{margin:0;height:100%;background:#1b1012;color:#f3d6d6;",
+            "font-family:'Segoe UI',sans-serif;display:flex;align-items:center;justify-content:center}}",
+            "main{{max-width:60%;text-align:center}}h1{{font-weight:300;font-size:2.4em;margin:0 0 .4em}}",
+            "code{{color:#ff8a8a}}p{{opacity:.8;line-height:1.5}}",
+            "</style></head><body><main><h1>Wallpaper failed to load</h1>",
+            "<p><code>{}</code></p><p>{}</p>",
+            "<p>Disable this page with <code>settings.development.show_error_wallpaper: false</code>.</p>",
+            "</main></body></html>"
+        ),
+        html_escape(wallpaper_id),
+        html_escape(reason)
     );
+    format!("data:text/html;charset=utf-8,{}", percent_encode(&html))
+}
 
-    let style = WINDOW_STYLE((WS_CHILD | WS_VISIBLE | WS_CLIPSIBLINGS | WS_CLIPCHILDREN).0);
-    let ex_style = WINDOW_EX_STYLE((WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE).0);
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
 
-    let hinstance = unsafe {
-        GetModuleHandleW(None)
-            .map(|h| HINSTANCE(h.0))
-            .map_err(|e| format!("GetModuleHandleW failed: {e:?}"))?
-    };
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 3);
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
 
-    let hwnd = unsafe {
-        CreateWindowExW(
-            ex_style,
-            HOST_CLASS_NAME,
-            PCWSTR::null(),
-            style,
-            x,
-            y,
-            width,
-            height,
-            Some(worker),
-            None,
-            Some(hinstance),
-            Some(ptr::null()),
-        )
+/// Pick the wallpaper id for one monitor of a `per_monitor` section: an exact
+/// index entry wins, then `p` for the primary, then the section's `wallpaper_id`.
+fn per_monitor_wallpaper_id<'a>(profile: &'a WallpaperConfig, monitor: &MonitorArea) -> Option<&'a str> {
+    if let Some(id) = profile.per_monitor.get(&monitor.index.to_string()) {
+        return Some(id.as_str());
     }
-    .map_err(|e| format!("CreateWindowExW failed: {e:?}"))?;
 
-    Ok(hwnd)
+    if monitor.primary {
+        if let Some(id) = profile
+            .per_monitor
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("p"))
+            .map(|(_, id)| id)
+        {
+            return Some(id.as_str());
+        }
+    }
+
+    if profile.wallpaper_id.is_empty() {
+        None
+    } else {
+        Some(profile.wallpaper_id.as_str())
+    }
 }
 
-fn window_rect(hwnd: HWND) -> Option<RECT> {
-    unsafe {
-        let mut rect = RECT::default();
-        if GetWindowRect(hwnd, &mut rect).is_ok() {
-            Some(rect)
-        } else {
-            None
+/// Layer for `monitor`: a `z_index_per_monitor` entry by index, then `"p"`
+/// for the primary, then the section's `z_index` if it set one, then the
+/// asset's manifest `default_z_index`.
+fn z_index_for_monitor<'a>(
+    profile: &'a WallpaperConfig,
+    monitor: &MonitorArea,
+    asset_layer: Option<&'a str>,
+) -> &'a str {
+    if let Some(layer) = profile.z_index_per_monitor.get(&monitor.index.to_string()) {
+        return layer;
+    }
+
+    if monitor.primary {
+        if let Some(layer) = profile
+            .z_index_per_monitor
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("p"))
+            .map(|(_, layer)| layer)
+        {
+            return layer;
         }
     }
+
+    match asset_layer {
+        Some(layer) if !profile.z_index_set => layer,
+        _ => &profile.z_index,
+    }
 }
 
-fn apply_host_style(hwnd: HWND, z_index: &str) -> std::result::Result<(), String> {
-    unsafe {
-        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
-        let mut new_style = style
-            & !(WS_CAPTION.0 | WS_THICKFRAME.0 | WS_MINIMIZEBOX.0 | WS_MAXIMIZEBOX.0 | WS_SYSMENU.0);
-        new_style |= WS_VISIBLE.0 | WS_CHILD.0;
-        let _ = SetWindowLongW(hwnd, GWL_STYLE, new_style as i32);
+/// The manifest's `default_z_index` for an asset, if it names a known layer.
+fn manifest_default_z_index(asset_dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(asset_dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())?;
+    let layer = manifest.get("default_z_index")?.as_str()?.trim().to_ascii_lowercase();
 
-        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
-        let mut new_ex = ex_style & !(WS_EX_APPWINDOW.0 | WS_EX_WINDOWEDGE.0 | WS_EX_DLGMODALFRAME.0);
-        new_ex |= WS_EX_TOOLWINDOW.0 | WS_EX_NOACTIVATE.0;
-        let _ = SetWindowLongW(hwnd, GWL_EXSTYLE, new_ex as i32);
+    if matches!(
+        layer.as_str(),
+        "desktop" | "bottom" | "normal" | "top" | "topmost" | "overlay"
+    ) {
+        Some(layer)
+    } else {
+        warn!(
+            "[WALLPAPER] Ignoring unknown default_z_index '{}' in {}",
+            layer,
+            asset_dir.join("manifest.json").display()
+        );
+        None
+    }
+}
 
-        let insert_after = match z_index.to_lowercase().as_str() {
-            "desktop" => HWND_TOPMOST,
-            "bottom" => HWND_BOTTOM,
-            "normal" => HWND_NOTOPMOST,
-            "top" => HWND_TOP,
-            "topmost" | "overlay" => HWND_TOPMOST,
-            _ => HWND_BOTTOM,
-        };
+/// The section's `mode`, except that `tile` only works for image assets (the
+/// tile shim repeats the image WebView2 shows); HTML pages fall back to `fill`.
+fn effective_mode<'a>(profile: &'a WallpaperConfig, url: &str) -> &'a str {
+    let mode = layout_mode(profile, url);
+    if mode != profile.mode {
         warn!(
-            "[WALLPAPER][STYLE] hwnd={:?} old_style=0x{:X} new_style=0x{:X} old_ex=0x{:X} new_ex=0x{:X}",
-            hwnd,
-            style,
-            new_style,
-            ex_style,
-            new_ex
+            "[WALLPAPER] Section '{}' uses tile mode but '{}' is not an image; falling back to fill",
+            profile.section,
+            url
         );
-        warn!("[WALLPAPER][STYLE] insert_after={:?}", insert_after);
+    }
+    mode
+}
 
-        if SetWindowPos(
-            hwnd,
-            Some(insert_after),
-            0,
-            0,
-            0,
-            0,
-            SWP_NOACTIVATE | SWP_SHOWWINDOW | SWP_FRAMECHANGED,
-        )
-        .is_err()
-        {
-            return Err("SetWindowPos failed for host style".to_string());
-        }
+/// `effective_mode` without the log, for comparing plans.
+fn layout_mode<'a>(profile: &'a WallpaperConfig, url: &str) -> &'a str {
+    if profile.mode != "tile" || is_image_url(url) || url.starts_with(GIF_PLAYER_URL_PREFIX) {
+        return &profile.mode;
     }
+    "fill"
+}
 
-    Ok(())
+/// The URL a host navigates to: player pages pick their object-fit from
+/// the fragment.
+fn mode_fragment_url(url: &str, mode: &str) -> String {
+    if is_player_url(url) {
+        format!("{url}#{mode}")
+    } else {
+        url.to_string()
+    }
 }
 
-fn create_webview_controller(
-    hwnd: HWND,
-    rect: RECT,
-    url: &str,
-) -> std::result::Result<ICoreWebView2Controller, String> {
-    warn!("[WALLPAPER][WEBVIEW] creating environment for hwnd={:?}", hwnd);
-    let environment = {
-        let (tx, rx) = mpsc::channel();
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let Some((_, ext)) = path.rsplit_once('.') else {
+        return false;
+    };
+    ["png", "jpg", "jpeg", "gif", "webp", "bmp", "svg", "avif"]
+        .iter()
+        .any(|known| ext.eq_ignore_ascii_case(known))
+}
 
-        webview2_com::CreateCoreWebView2EnvironmentCompletedHandler::wait_for_async_operation(
-            Box::new(|handler| unsafe {
-                CreateCoreWebView2Environment(&handler).map_err(webview2_com::Error::WindowsError)
-            }),
-            Box::new(move |error_code, environment| {
-                error_code?;
-                tx.send(environment.ok_or_else(|| windows::core::Error::from(E_POINTER)))
-                    .expect("send WebView2 environment");
-                Ok(())
-            }),
-        )
-        .map_err(|e| format!("CreateCoreWebView2Environment failed: {e:?}"))?;
+/// Injected after each load of a `tile` host: when WebView2 is showing a bare
+/// image document, hide the `<img>` and repeat it as the body background.
+/// Fallback opacity for non-overlay hosts: fade the root element.
+fn page_opacity_script(opacity: f32) -> String {
+    format!(
+        "document.documentElement.style.opacity = '{}';",
+        opacity.clamp(0.0, 1.0)
+    )
+}
 
-        rx.recv()
-            .map_err(|_| "Failed to receive WebView2 environment".to_string())?
-            .map_err(|e| format!("WebView2 environment unavailable: {e:?}"))?
-    };
-    warn!("[WALLPAPER][WEBVIEW] environment ready for hwnd={:?}", hwnd);
+const TILE_IMAGE_SCRIPT: &str = r#"(() => {
+  if (!document.contentType || !document.contentType.startsWith('image/')) return;
+  const img = document.images[0];
+  if (!img) return;
+  document.documentElement.style.height = '100%';
+  document.body.style.cssText =
+    `margin:0;height:100%;background:url("${img.src}") repeat top left`;
+  img.style.display = 'none';
+})();"#;
+
+/// Manifest `allow_remote`; absent (or no asset, as for the error page)
+/// keeps the historical behaviour of allowing remote content.
+fn manifest_allows_remote(asset_dir: &Path) -> bool {
+    if asset_dir.as_os_str().is_empty() {
+        return true;
+    }
 
-    let controller = {
-        let (tx, rx) = mpsc::channel();
+    fs::read_to_string(asset_dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|manifest| manifest.get("allow_remote").and_then(|v| v.as_bool()))
+        .unwrap_or(true)
+}
 
-        webview2_com::CreateCoreWebView2ControllerCompletedHandler::wait_for_async_operation(
-            Box::new(move |handler| unsafe {
-                environment
-                    .CreateCoreWebView2Controller(hwnd, &handler)
-                    .map_err(webview2_com::Error::WindowsError)
-            }),
-            Box::new(move |error_code, controller| {
-                error_code?;
-                tx.send(controller.ok_or_else(|| windows::core::Error::from(E_POINTER)))
-                    .expect("send WebView2 controller");
-                Ok(())
-            }),
-        )
-        .map_err(|e| format!("CreateCoreWebView2Controller failed: {e:?}"))?;
+/// Browser flags a manifest may not request: they disable the sandbox or
+/// same-origin checks, open a debugging port or move the profile.
+const DENIED_WEBVIEW_ARGS: &[&str] = &[
+    "--allow-file-access-from-files",
+    "--allow-running-insecure-content",
+    "--disable-features",
+    "--disable-site-isolation-trials",
+    "--disable-web-security",
+    "--load-extension",
+    "--no-sandbox",
+    "--remote-allow-origins",
+    "--remote-debugging-pipe",
+    "--remote-debugging-port",
+    "--unsafely-treat-insecure-origin-as-secure",
+    "--user-data-dir",
+];
+
+/// Manifest `webview_args`, minus anything on `DENIED_WEBVIEW_ARGS`,
+/// `--autoplay-policy` (set through the manifest `autoplay` field) or not
+/// shaped like a `--flag`.  Sorted so the same set in any order shares one
+/// environment.
+fn manifest_webview_args(asset_dir: &Path) -> Vec<String> {
+    if asset_dir.as_os_str().is_empty() {
+        return Vec::new();
+    }
 
-        rx.recv()
-            .map_err(|_| "Failed to receive WebView2 controller".to_string())?
-            .map_err(|e| format!("WebView2 controller unavailable: {e:?}"))?
+    let Some(requested) = fs::read_to_string(asset_dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|manifest| manifest.get("webview_args").and_then(|v| v.as_array()).cloned())
+    else {
+        return Vec::new();
     };
-    warn!("[WALLPAPER][WEBVIEW] controller ready for hwnd={:?}", hwnd);
 
-    unsafe {
-        let width = rect.right - rect.left;
-        let height = rect.bottom - rect.top;
-        warn!(
-            "[WALLPAPER][WEBVIEW] setting bounds {}x{} and navigating to '{}'",
-            width,
-            height,
-            url
-        );
-        controller
-            .SetBounds(RECT {
-                left: 0,
-                top: 0,
-                right: width,
-                bottom: height,
-            })
-            .map_err(|e| format!("WebView2 SetBounds failed: {e:?}"))?;
+    let mut args: Vec<String> = requested
+        .iter()
+        .filter_map(|arg| arg.as_str().map(str::trim))
+        .filter(|arg| {
+            let name = arg.split('=').next().unwrap_or("").to_ascii_lowercase();
+            let allowed = name.starts_with("--")
+                && !arg.contains(char::is_whitespace)
+                && name != "--autoplay-policy"
+                && !DENIED_WEBVIEW_ARGS.contains(&name.as_str());
+            if !allowed {
+                warn!(
+                    "[WALLPAPER][WEBVIEW] ignoring webview_args entry '{}' in {}",
+                    arg,
+                    asset_dir.display()
+                );
+            }
+            allowed
+        })
+        .map(str::to_string)
+        .collect();
+    args.sort();
+    args.dedup();
+    args
+}
 
-        controller
-            .SetIsVisible(true)
-            .map_err(|e| format!("WebView2 SetIsVisible failed: {e:?}"))?;
+/// User data folder for an environment with `args`, one per distinct set.
+fn webview_args_data_dir(args: &[String]) -> PathBuf {
+    use std::hash::{Hash, Hasher};
 
-        let webview = controller
-            .CoreWebView2()
-            .map_err(|e| format!("WebView2 CoreWebView2 unavailable: {e:?}"))?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    args.hash(&mut hasher);
+    addon_root_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("webview_data")
+        .join(format!("{:016x}", hasher.finish()))
+}
 
-        let url_wide = to_wstring(url);
-        webview
-            .Navigate(PCWSTR(url_wide.as_ptr()))
-            .map_err(|e| format!("WebView2 Navigate failed for '{}': {e:?}", url))?;
+/// Manifest `heartbeat`; off unless the asset opts in.
+fn manifest_heartbeat(asset_dir: &Path) -> bool {
+    if asset_dir.as_os_str().is_empty() {
+        return false;
     }
-    warn!("[WALLPAPER][WEBVIEW] navigation submitted successfully");
 
-    Ok(controller)
+    fs::read_to_string(asset_dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|manifest| manifest.get("heartbeat").and_then(|v| v.as_bool()))
+        .unwrap_or(false)
 }
 
-fn fetch_wallpaper_assets() -> Vec<RegistryAsset> {
-    if let Some(raw) = request("registry", "list_assets", None) {
-        if let Ok(entries) = serde_json::from_str::<Vec<RegistryAsset>>(&raw) {
-            let filtered: Vec<RegistryAsset> = entries
-                .into_iter()
-                .filter(|e| e.category.eq_ignore_ascii_case("wallpaper"))
-                .collect();
-
-            if !filtered.is_empty() {
-                return filtered;
-            }
-        } else if let Ok(grouped) = serde_json::from_str::<serde_json::Map<String, Value>>(&raw) {
-            let mut flattened = Vec::<RegistryAsset>::new();
-            for (category, arr) in grouped {
-                if !category.eq_ignore_ascii_case("wallpaper") {
-                    continue;
-                }
-                if let Some(items) = arr.as_array() {
-                    for item in items {
-                        if let Ok(mut asset) = serde_json::from_value::<RegistryAsset>(item.clone()) {
-                            if asset.category.is_empty() {
-                                asset.category = category.clone();
-                            }
-                            flattened.push(asset);
-                        }
-                    }
-                }
-            }
-            if !flattened.is_empty() {
-                return flattened;
-            }
-        } else {
-            warn!("[WALLPAPER] Failed to parse registry list_assets payload");
-        }
-    } else {
-        warn!("[WALLPAPER] IPC list_assets request failed");
-    }
+fn is_local_content_url(url: &str) -> bool {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme).unwrap_or("");
+    scheme.eq_ignore_ascii_case("file")
+        || scheme.eq_ignore_ascii_case("data")
+        || url.eq_ignore_ascii_case("about:blank")
+        || is_virtual_host_url(url)
+}
 
-    Vec::new()
+/// Manifest `virtual_host`; when true the asset folder is served from
+/// `https://VIRTUAL_ASSET_HOST/` rather than `file://`.
+fn manifest_uses_virtual_host(asset_dir: &Path) -> bool {
+    fs::read_to_string(asset_dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|manifest| manifest.get("virtual_host").and_then(|v| v.as_bool()))
+        .unwrap_or(false)
 }
 
-fn resolve_asset<'a>(assets: &'a [RegistryAsset], wallpaper_id: &str) -> Option<&'a RegistryAsset> {
-    assets.iter().find(|a| a.id == wallpaper_id)
+fn virtual_host_prefix() -> String {
+    format!("https://{}/", VIRTUAL_ASSET_HOST)
+}
+
+fn is_virtual_host_url(url: &str) -> bool {
+    let prefix = virtual_host_prefix();
+    url.get(..prefix.len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(&prefix))
+}
+
+/// `file:///` equivalent of a `virtual_host` URL, for runtimes without
+/// folder mapping.
+fn virtual_host_fallback_url(url: &str, asset_dir: &Path) -> String {
+    let relative = url.get(virtual_host_prefix().len()..).unwrap_or("");
+    resolve_manifest_url(asset_dir, relative)
+}
+
+/// Virtual host name `url` is served from, paired with the folder mapped
+/// to it.
+fn asset_host_for<'a>(url: &str, asset_dir: &'a Path) -> Option<(&'static str, &'a Path)> {
+    if is_player_url(url) {
+        Some((PLAYER_ASSET_HOST, asset_dir))
+    } else if is_virtual_host_url(url) {
+        Some((VIRTUAL_ASSET_HOST, asset_dir))
+    } else {
+        None
+    }
 }
 
 fn resolve_asset_url(asset: &RegistryAsset) -> Option<String> {
+    let virtual_host = manifest_uses_virtual_host(&asset.path);
     if let Some(url) = asset.metadata.get("url").and_then(|v| v.as_str()) {
-        return Some(url.to_string());
+        let url = expand_config_value(url.trim(), "manifest url");
+        let url = url.as_ref();
+        // Only relative paths move onto the virtual host; absolute URLs and
+        // drive paths are used as written.
+        if virtual_host && !url.contains(':') {
+            let relative = url.replace('\\', "/");
+            return Some(format!(
+                "{}{}",
+                virtual_host_prefix(),
+                relative.trim_start_matches('/')
+            ));
+        }
+        return Some(resolve_manifest_url(&asset.path, url));
     }
 
     let local_html = asset.path.join("index.html");
     if local_html.exists() {
+        if virtual_host {
+            return Some(format!("{}index.html", virtual_host_prefix()));
+        }
         return Some(path_to_file_url(&local_html));
     }
 
-    None
+    if let Some(gif) = lone_file(&asset.path, &["gif"]) {
+        return Some(gif_player_url(&gif));
+    }
+    // Videos start muted unless the manifest sets `"muted": false`.
+    let muted = asset
+        .metadata
+        .get("muted")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    lone_file(&asset.path, &["mp4", "webm"]).map(|video| video_player_url(&video, muted))
+}
+
+/// The asset folder's only file with one of `extensions`, for media assets
+/// shipped without a page.
+fn lone_file(asset_dir: &Path, extensions: &[&str]) -> Option<String> {
+    let mut files = fs::read_dir(asset_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            Path::new(name).extension().is_some_and(|ext| {
+                extensions.iter().any(|wanted| ext.eq_ignore_ascii_case(wanted))
+            })
+        });
+    let file = files.next()?;
+    files.next().is_none().then_some(file)
+}
+
+/// Generated GIF or video player page (see `asset_host_for`).
+fn is_player_url(url: &str) -> bool {
+    url.starts_with(GIF_PLAYER_URL_PREFIX) || url.starts_with(VIDEO_PLAYER_URL_PREFIX)
+}
+
+/// Self-contained player page for `gif_name` (served from `PLAYER_ASSET_HOST`).
+/// The layout mode arrives as the URL fragment; while paused the current
+/// frame is painted onto a canvas and the animated `<img>` is hidden.
+fn gif_player_url(gif_name: &str) -> String {
+    // Percent-encoded, so it needs no further escaping inside CSS or HTML.
+    let src = format!("https://{}/{}", PLAYER_ASSET_HOST, percent_encode(gif_name));
+    let html = format!(
+        concat!(
+            "<!doctype html><html><head><meta charset=\"utf-8\"><style>",
+            "html,body{{margin:0;height:100%;overflow:hidden;background:#000}}",
+            "img,canvas{{position:absolute;inset:0;width:100%;height:100%;object-fit:cover}}",
+            "canvas{{display:none}}.fit img,.fit canvas{{object-fit:contain}}",
+            ".stretch img,.stretch canvas{{object-fit:fill}}.center img,.center canvas{{object-fit:none}}",
+            ".tile img{{display:none}}.tile{{background:url(\"{src}\") repeat top left}}",
+            ".tile.paused{{background:none}}.paused img{{display:none}}.paused canvas{{display:block}}",
+            "</style></head><body><img src=\"{src}\" alt=\"\"><canvas></canvas><script>",
+            "const body=document.body,img=document.querySelector('img'),canvas=document.querySelector('canvas');",
+            "body.classList.add(location.hash.slice(1)||'fill');",
+            "function setPaused(paused){{",
+            "if(paused===body.classList.contains('paused'))return;",
+            "if(paused&&img.naturalWidth){{const ctx=canvas.getContext('2d');",
+            "if(body.classList.contains('tile')){{canvas.width=innerWidth;canvas.height=innerHeight;",
+            "ctx.fillStyle=ctx.createPattern(img,'repeat');ctx.fillRect(0,0,canvas.width,canvas.height);}}",
+            "else{{canvas.width=img.naturalWidth;canvas.height=img.naturalHeight;ctx.drawImage(img,0,0);}}}}",
+            "body.classList.toggle('paused',paused);}}",
+            "window.chrome?.webview?.addEventListener('message',e=>{{",
+            "if(e.data&&e.data.type==='native_pause')setPaused(!!e.data.paused);}});",
+            "</script></body></html>"
+        ),
+        src = src
+    );
+    format!("{}{}", GIF_PLAYER_URL_PREFIX, percent_encode(&html))
+}
+
+/// Self-contained looping player page for `video_name`, laid out like the
+/// GIF player.  `native_pause` pauses the element and mutes it until it
+/// resumes, `native_volume` sets its volume; `player_ready` is posted once the first frame has decoded so
+/// the snapshot warmup starts from a painted page.
+fn video_player_url(video_name: &str, muted: bool) -> String {
+    let src = format!("https://{}/{}", PLAYER_ASSET_HOST, percent_encode(video_name));
+    let html = format!(
+        concat!(
+            "<!doctype html><html><head><meta charset=\"utf-8\"><style>",
+            "html,body{{margin:0;height:100%;overflow:hidden;background:#000}}",
+            "video{{position:absolute;inset:0;width:100%;height:100%;object-fit:cover}}",
+            ".fit video{{object-fit:contain}}.stretch video{{object-fit:fill}}.center video{{object-fit:none}}",
+            "</style></head><body><video src=\"{src}\" autoplay loop playsinline></video><script>",
+            "const body=document.body,video=document.querySelector('video'),muted={muted};",
+            "body.classList.add(location.hash.slice(1)||'fill');video.muted=muted;",
+            "video.addEventListener('loadeddata',()=>",
+            "window.chrome?.webview?.postMessage({{type:'player_ready'}}),{{once:true}});",
+            "window.chrome?.webview?.addEventListener('message',e=>{{",
+            "if(e.data?.type==='native_volume'){{video.volume=e.data.volume;return;}}",
+            "if(!e.data||e.data.type!=='native_pause')return;",
+            "if(e.data.paused){{video.pause();video.muted=true;}}",
+            "else{{video.muted=muted;video.play().catch(()=>{{}});}}}});",
+            "</script></body></html>"
+        ),
+        src = src,
+        muted = muted
+    );
+    format!("{}{}", VIDEO_PLAYER_URL_PREFIX, percent_encode(&html))
 }
 
 fn resolve_target_monitors<'a>(
     monitors: &'a [MonitorArea],
     keys: &[String],
     assigned_monitors: &HashSet<usize>,
+    random_seed: u64,
 ) -> Vec<&'a MonitorArea> {
     let mut result = Vec::<&MonitorArea>::new();
 
@@ -1677,25 +6411,54 @@ fn resolve_target_monitors<'a>(
     }
 
     for key in keys {
-        if key == "*" || key.eq_ignore_ascii_case("p") {
+        if key == "*" || key.eq_ignore_ascii_case("p") || key.eq_ignore_ascii_case("random") {
             continue;
         }
 
-        if let Ok(index) = key.parse::<usize>() {
-            if let Some(monitor) = monitors.get(index) {
-                if assigned_monitors.contains(&monitor.index) {
-                    continue;
-                }
-                if !result.iter().any(|m| m.index == monitor.index) {
-                    result.push(monitor);
-                }
+        let indices = if let Ok(index) = key.parse::<usize>() {
+            index..=index
+        } else if let Some((start, end)) = parse_monitor_range(key) {
+            if start > end {
+                warn!(
+                    "[WALLPAPER][PLAN] Ignoring reversed monitor range '{}' (write it as '{}-{}')",
+                    key, end, start
+                );
+                continue;
+            }
+            // Bounds past the last monitor are clamped, so a huge `end`
+            // can't spin the loop.
+            if start >= monitors.len() {
+                continue;
+            }
+            start..=end.min(monitors.len().saturating_sub(1))
+        } else {
+            continue;
+        };
+
+        for monitor in indices.filter_map(|index| monitors.get(index)) {
+            if assigned_monitors.contains(&monitor.index) {
+                continue;
+            }
+            if !result.iter().any(|m| m.index == monitor.index) {
+                result.push(monitor);
             }
         }
     }
 
+    if keys.iter().any(|key| key.eq_ignore_ascii_case("random")) {
+        let candidates: Vec<&MonitorArea> = monitors
+            .iter()
+            .filter(|m| !assigned_monitors.contains(&m.index))
+            .filter(|m| !result.iter().any(|r| r.index == m.index))
+            .collect();
+        if let Some(pick) = pick_random_index(random_seed, candidates.len()) {
+            result.push(candidates[pick]);
+        }
+    }
+
     if keys.iter().any(|key| key == "*") {
         for monitor in monitors {
-            if assigned_monitors.contains(&monitor.index) {
+            if assigned_monitors.contains(&monitor.index) || excluded_from_wildcard(keys, monitor) {
                 continue;
             }
             if !result.iter().any(|m| m.index == monitor.index) {
@@ -1707,9 +6470,152 @@ fn resolve_target_monitors<'a>(
     result
 }
 
+/// Whether a `!N` / `!A-B` / `!p` entry removes `monitor` from the `*`
+/// expansion.  Exclusions never undo an explicit include in the same list.
+fn excluded_from_wildcard(keys: &[String], monitor: &MonitorArea) -> bool {
+    keys.iter()
+        .filter_map(|key| key.strip_prefix('!'))
+        .map(str::trim)
+        .any(|key| {
+            (key.eq_ignore_ascii_case("p") && monitor.primary)
+                || key.parse::<usize>().is_ok_and(|index| index == monitor.index)
+                || parse_monitor_range(key)
+                    .is_some_and(|(start, end)| (start..=end).contains(&monitor.index))
+        })
+}
+
+/// Seed used for a section's `random` keyword.  An explicit `random_seed`
+/// is reproducible; otherwise a per-process seed keeps the pick stable across
+/// reapplies within one session.  The section name is mixed in so several
+/// random sections don't all land on the same screen.
+fn profile_random_seed(profile: &WallpaperConfig) -> u64 {
+    static SESSION_SEED: OnceLock<u64> = OnceLock::new();
+    let base = profile.random_seed.unwrap_or_else(|| {
+        *SESSION_SEED.get_or_init(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            nanos ^ ((std::process::id() as u64) << 32)
+        })
+    });
+
+    profile
+        .section
+        .bytes()
+        .fold(base, |acc, b| acc.rotate_left(5) ^ b as u64)
+}
+
+/// Deterministic index in `0..len` derived from `seed` (splitmix64 finaliser).
+fn pick_random_index(seed: u64, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    Some((z % len as u64) as usize)
+}
+
+/// Turn a manifest `url` into something WebView2 can navigate to.  URLs
+/// with a scheme (`http(s)://`, `file://`, `data:`, ...) pass through as-is;
+/// anything else is a path relative to the asset folder (or an absolute
+/// drive path) and becomes a `file:///` URL, keeping any `?query` / `#hash`.
+fn resolve_manifest_url(asset_dir: &Path, url: &str) -> String {
+    let scheme_len = url
+        .find(':')
+        .filter(|&i| {
+            url[..i].chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && url[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        });
+    // A one-letter "scheme" is a drive letter (`C:\...`), not a URL.
+    if scheme_len.is_some_and(|len| len > 1) {
+        return url.to_string();
+    }
+
+    let split = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, suffix) = url.split_at(split);
+    let file = if scheme_len.is_some() {
+        PathBuf::from(path)
+    } else {
+        asset_dir.join(path.trim_start_matches(['/', '\\']))
+    };
+    format!("{}{}", path_to_file_url(&file), suffix)
+}
+
+/// `file:` URL for `path`.  URLs have no `\\?\` form, so a long-path
+/// prefix is dropped first (WebView2 copes with long paths on its own);
+/// UNC shares become `file://server/share/...`.
 fn path_to_file_url(path: &Path) -> String {
-    let normalized = path.to_string_lossy().replace('\\', "/");
-    format!("file:///{normalized}")
+    let plain = strip_long_path_prefix(&path.to_string_lossy());
+    let normalized = plain.replace('\\', "/");
+    match normalized.strip_prefix("//") {
+        Some(unc) => format!("file://{unc}"),
+        None => format!("file:///{normalized}"),
+    }
+}
+
+/// Merge monitors reporting the exact same rect (mirrored/duplicated
+/// displays) into one area so `*` does not stack hosts on one screen.  The
+/// merged area is primary if any of its duplicates was.  Overlapping but
+/// distinct rects are left alone.  Indices are reassigned by the caller.
+fn collapse_mirrored_monitors(monitors: Vec<MonitorArea>) -> Vec<MonitorArea> {
+    let mut collapsed = Vec::<MonitorArea>::with_capacity(monitors.len());
+    for monitor in monitors {
+        match collapsed.iter_mut().find(|m| m.rect == monitor.rect) {
+            Some(existing) => existing.primary |= monitor.primary,
+            None => collapsed.push(monitor),
+        }
+    }
+    collapsed
+}
+
+/// Desktop rects of the outputs DXGI reports in an HDR (PQ / BT.2020)
+/// colour space.  Empty when DXGI is unavailable or nothing is HDR.
+fn hdr_monitor_rects() -> Vec<RECT> {
+    let Ok(factory) = (unsafe { CreateDXGIFactory1::<IDXGIFactory1>() }) else {
+        return Vec::new();
+    };
+
+    let mut rects = Vec::new();
+    for adapter_index in 0.. {
+        let Ok(adapter) = (unsafe { factory.EnumAdapters1(adapter_index) }) else {
+            break;
+        };
+        for output_index in 0.. {
+            let Ok(output) = (unsafe { adapter.EnumOutputs(output_index) }) else {
+                break;
+            };
+            let Ok(desc) = output
+                .cast::<IDXGIOutput6>()
+                .and_then(|output| unsafe { output.GetDesc1() })
+            else {
+                continue;
+            };
+            if desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020 {
+                rects.push(desc.DesktopCoordinates);
+            }
+        }
+    }
+    if !rects.is_empty() {
+        warn!("[WALLPAPER][SNAP] {} HDR monitor(s) get the snapshot gamma pass", rects.len());
+    }
+    rects
+}
+
+/// Per-channel lookup table that darkens washed-out HDR captures with
+/// `HDR_SNAPSHOT_GAMMA`.
+fn hdr_gamma_curve() -> [u8; 256] {
+    let mut curve = [0u8; 256];
+    for (value, out) in curve.iter_mut().enumerate() {
+        *out = ((value as f64 / 255.0).powf(HDR_SNAPSHOT_GAMMA) * 255.0).round() as u8;
+    }
+    curve
 }
 
 fn enumerate_monitors() -> Vec<MonitorArea> {
@@ -1725,10 +6631,12 @@ fn enumerate_monitors() -> Vec<MonitorArea> {
         info.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
 
         if GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut _).as_bool() {
+            let name_len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
             vec.push(MonitorArea {
                 index: vec.len(),
                 primary: info.monitorInfo.dwFlags != 0,
                 rect: info.monitorInfo.rcMonitor,
+                device_name: String::from_utf16_lossy(&info.szDevice[..name_len]),
             });
         }
 
@@ -1744,6 +6652,7 @@ fn enumerate_monitors() -> Vec<MonitorArea> {
             LPARAM((&mut monitors as *mut Vec<MonitorArea>) as isize),
         );
     }
+    let mut monitors = collapse_mirrored_monitors(monitors);
 
     if monitors.len() > 1 {
         let min_height = monitors
@@ -1795,6 +6704,14 @@ fn profile_priority(profile: &WallpaperConfig) -> u8 {
     }
 
     if profile.monitor_index.iter().any(|key| key == "*") {
+        return 3;
+    }
+
+    if profile
+        .monitor_index
+        .iter()
+        .any(|key| key.eq_ignore_ascii_case("random"))
+    {
         return 2;
     }
 
@@ -1807,7 +6724,9 @@ fn make_span_monitor_area(monitors: &[&MonitorArea]) -> MonitorArea {
     let right = monitors.iter().map(|m| m.rect.right).max().unwrap_or(0);
     let bottom = monitors.iter().map(|m| m.rect.bottom).max().unwrap_or(0);
     let primary = monitors.iter().any(|m| m.primary);
-    let index = monitors.iter().map(|m| m.index).min().unwrap_or(0);
+    let first = monitors.iter().min_by_key(|m| m.index);
+    let index = first.map(|m| m.index).unwrap_or(0);
+    let device_name = first.map(|m| m.device_name.clone()).unwrap_or_default();
 
     MonitorArea {
         index,
@@ -1818,64 +6737,124 @@ fn make_span_monitor_area(monitors: &[&MonitorArea]) -> MonitorArea {
             right,
             bottom,
         },
+        device_name,
     }
 }
 
-fn ensure_desktop_host() -> Option<HWND> {
-    unsafe {
-        let progman = FindWindowW(w!("Progman"), None).ok()?;
-        warn!("[WALLPAPER][HOSTSEL] Progman={:?}", progman);
-
-        let mut spawn_result = 0usize;
-        let _ = SendMessageTimeoutW(
-            progman,
-            0x052C,
-            WPARAM(0),
-            LPARAM(0),
-            SMTO_NORMAL,
-            1000,
-            Some(&mut spawn_result),
-        );
+/// The engine's monitor model for the options UI, in the row/column order
+/// `enumerate_monitors` assigns and `monitor_index` refers to.
+fn monitors_json(monitors: &[MonitorArea]) -> Value {
+    let monitors = monitors
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "index": m.index,
+                "primary": m.primary,
+                "device_name": m.device_name,
+                "rect": {
+                    "left": m.rect.left,
+                    "top": m.rect.top,
+                    "right": m.rect.right,
+                    "bottom": m.rect.bottom,
+                },
+                "dpi": monitor_effective_dpi(m.rect).ok(),
+            })
+        })
+        .collect();
+    Value::Array(monitors)
+}
 
-        let mut defview_host: Option<HWND> = None;
-        unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
-            let out = (lparam.0 as *mut Option<HWND>).as_mut().unwrap();
-            if FindWindowExW(Some(hwnd), None, w!("SHELLDLL_DefView"), None).ok().is_some() {
-                *out = Some(hwnd);
-                return BOOL(0);
-            }
-            BOOL(1)
-        }
-        let _ = EnumWindows(
-            Some(enum_proc),
-            LPARAM((&mut defview_host) as *mut Option<HWND> as isize),
-        );
+/// Whether Explorer's desktop windows exist again, i.e. a reapply can find
+/// a WorkerW host.
+pub fn desktop_host_ready() -> bool {
+    unsafe { FindWindowW(w!("Progman"), None).is_ok() }
+}
 
-        if let Some(host) = defview_host {
-            warn!("[WALLPAPER][HOSTSEL] DefView host={:?}", host);
+/// Desktop window to parent wallpaper hosts under.  `auto` runs the
+/// fallback cascade; the other strategies force one window (and fail
+/// instead of falling back) for machines where the cascade picks wrong.
+fn ensure_desktop_host(strategy: HostStrategy) -> Option<HWND> {
+    let host = unsafe { resolve_desktop_host(strategy) };
+    warn!(
+        "[WALLPAPER][HOSTSEL] strategy={} resolved={:?}",
+        strategy.name(),
+        host
+    );
+    host
+}
 
-            if let Some(workerw) = FindWindowExW(None, Some(host), w!("WorkerW"), None).ok() {
-                warn!("[WALLPAPER][HOSTSEL] WorkerW sibling selected={:?}", workerw);
-                return Some(workerw);
-            }
+unsafe fn resolve_desktop_host(strategy: HostStrategy) -> Option<HWND> {
+    let progman = FindWindowW(w!("Progman"), None).ok()?;
+    warn!("[WALLPAPER][HOSTSEL] Progman={:?}", progman);
+    if strategy == HostStrategy::Progman {
+        return Some(progman);
+    }
 
-            if let Some(workerw) = FindWindowExW(Some(progman), None, w!("WorkerW"), None).ok() {
-                warn!("[WALLPAPER][HOSTSEL] WorkerW under Progman selected={:?}", workerw);
+    // Ask Progman to spawn the WorkerW that sits behind the desktop icons.
+    let mut spawn_result = 0usize;
+    let _ = SendMessageTimeoutW(
+        progman,
+        0x052C,
+        WPARAM(0),
+        LPARAM(0),
+        SMTO_NORMAL,
+        1000,
+        Some(&mut spawn_result),
+    );
+
+    let defview_host = find_defview_host();
+    if let Some(host) = defview_host {
+        warn!("[WALLPAPER][HOSTSEL] DefView host={:?}", host);
+    }
+
+    match strategy {
+        HostStrategy::DefView => defview_host,
+        HostStrategy::WorkerW => find_workerw(progman, defview_host),
+        HostStrategy::Progman => Some(progman),
+        HostStrategy::Auto => {
+            if let Some(workerw) = find_workerw(progman, defview_host) {
                 return Some(workerw);
             }
+            if let Some(host) = defview_host {
+                warn!("[WALLPAPER][HOSTSEL] No WorkerW found; using DefView host as fallback");
+                return Some(host);
+            }
+            warn!("[WALLPAPER][HOSTSEL] Final fallback to Progman");
+            Some(progman)
+        }
+    }
+}
 
-            warn!("[WALLPAPER][HOSTSEL] No WorkerW found; using DefView host as fallback");
-            return Some(host);
+/// Top-level window that owns `SHELLDLL_DefView` (the desktop icons).
+unsafe fn find_defview_host() -> Option<HWND> {
+    let mut defview_host: Option<HWND> = None;
+    unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let out = (lparam.0 as *mut Option<HWND>).as_mut().unwrap();
+        if FindWindowExW(Some(hwnd), None, w!("SHELLDLL_DefView"), None).ok().is_some() {
+            *out = Some(hwnd);
+            return BOOL(0);
         }
+        BOOL(1)
+    }
+    let _ = EnumWindows(
+        Some(enum_proc),
+        LPARAM((&mut defview_host) as *mut Option<HWND> as isize),
+    );
+    defview_host
+}
 
-        if let Some(workerw) = FindWindowExW(Some(progman), None, w!("WorkerW"), None).ok() {
-            warn!("[WALLPAPER][HOSTSEL] Fallback WorkerW selected={:?}", workerw);
+/// WorkerW right after the DefView host, else the one under Progman.
+unsafe fn find_workerw(progman: HWND, defview_host: Option<HWND>) -> Option<HWND> {
+    if let Some(host) = defview_host {
+        if let Ok(workerw) = FindWindowExW(None, Some(host), w!("WorkerW"), None) {
+            warn!("[WALLPAPER][HOSTSEL] WorkerW sibling selected={:?}", workerw);
             return Some(workerw);
         }
-
-        warn!("[WALLPAPER][HOSTSEL] Final fallback to Progman");
-        Some(progman)
     }
+
+    let workerw = FindWindowExW(Some(progman), None, w!("WorkerW"), None).ok()?;
+    warn!("[WALLPAPER][HOSTSEL] WorkerW under Progman selected={:?}", workerw);
+    Some(workerw)
 }
 
 fn global_window_states(appdata: &Value) -> Option<MonitorWindowStates> {
@@ -1924,7 +6903,52 @@ fn is_shell_foreground_active() -> bool {
     }
 }
 
+/// Script that re-fetches the `<link>` stylesheets matching `changed`, or
+/// `None` when anything other than `.css` changed (or the list is unknown).
+/// Falls back to `location.reload()` if no stylesheet link matched, e.g. for
+/// CSS pulled in via `@import`.
+fn css_hot_reload_script(asset_dir: &Path, changed: &[PathBuf]) -> Option<String> {
+    if changed.is_empty() {
+        return None;
+    }
+
+    let mut relative = Vec::with_capacity(changed.len());
+    for path in changed {
+        let is_css = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("css"));
+        if !is_css {
+            return None;
+        }
+        let rel = path.strip_prefix(asset_dir).ok()?;
+        relative.push(rel.to_string_lossy().replace('\\', "/"));
+    }
+
+    let changed_json = serde_json::to_string(&relative).ok()?;
+    Some(format!(
+        r#"(() => {{
+  const changed = {changed_json}.map(p => p.toLowerCase());
+  let matched = 0;
+  for (const link of document.querySelectorAll('link[rel~="stylesheet"]')) {{
+    const url = new URL(link.href, location.href);
+    const path = decodeURIComponent(url.pathname).toLowerCase();
+    if (!changed.some(rel => path.endsWith("/" + rel))) continue;
+    url.searchParams.set("__sentinel_reload", Date.now());
+    link.href = url.toString();
+    matched++;
+  }}
+  if (!matched) location.reload();
+}})();"#
+    ))
+}
+
 fn add_reload_nonce(url: &str) -> String {
+    // Generated pages: a query string would land in the document itself.
+    if url.starts_with("data:") {
+        return url.to_string();
+    }
+
     let nonce = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_millis())
@@ -1935,4 +6959,570 @@ fn add_reload_nonce(url: &str) -> String {
     } else {
         format!("{}?__sentinel_reload={}", url, nonce)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpi_maps_to_scale_factor() {
+        assert_eq!(dpi_to_scale(96), 1.0);
+        assert_eq!(dpi_to_scale(144), 1.5);
+        assert_eq!(dpi_to_scale(192), 2.0);
+        // Unknown DPI falls back to 100%.
+        assert_eq!(dpi_to_scale(0), 1.0);
+    }
+
+    /// Side-by-side 1920x1080 monitors; `primary` marks the primary one.
+    fn test_monitors(count: usize, primary: usize) -> Vec<MonitorArea> {
+        (0..count)
+            .map(|index| MonitorArea {
+                index,
+                primary: index == primary,
+                rect: RECT {
+                    left: index as i32 * 1920,
+                    top: 0,
+                    right: (index as i32 + 1) * 1920,
+                    bottom: 1080,
+                },
+                device_name: format!("\\\\.\\DISPLAY{}", index + 1),
+            })
+            .collect()
+    }
+
+    fn targets(monitors: &[MonitorArea], keys: &[&str]) -> Vec<usize> {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        resolve_target_monitors(monitors, &keys, &HashSet::new(), 0)
+            .iter()
+            .map(|monitor| monitor.index)
+            .collect()
+    }
+
+    #[test]
+    fn wildcard_honours_exclusions() {
+        let monitors = test_monitors(3, 0);
+        assert_eq!(targets(&monitors, &["*"]), vec![0, 1, 2]);
+        assert_eq!(targets(&monitors, &["*", "!1"]), vec![0, 2]);
+        assert_eq!(targets(&monitors, &["*", "!p"]), vec![1, 2]);
+    }
+
+    #[test]
+    fn primary_keyword_combines_with_other_keys() {
+        let monitors = test_monitors(3, 1);
+        assert_eq!(targets(&monitors, &["p"]), vec![1]);
+        // The primary is listed once even when also named by index.
+        assert_eq!(targets(&monitors, &["p", "1", "2"]), vec![1, 2]);
+        assert_eq!(targets(&monitors, &["p", "*", "!0"]), vec![1, 2]);
+    }
+
+    #[test]
+    fn exclusion_never_removes_explicit_index() {
+        let monitors = test_monitors(3, 0);
+        assert_eq!(targets(&monitors, &["1", "*", "!1"]), vec![1, 0, 2]);
+        assert_eq!(targets(&monitors, &["p", "*", "!p"]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn monitor_ranges_expand_inclusively() {
+        let monitors = test_monitors(4, 0);
+        assert_eq!(targets(&monitors, &["0-2"]), vec![0, 1, 2]);
+        // Reversed ranges are ignored rather than guessed at.
+        assert!(targets(&monitors, &["2-0"]).is_empty());
+        assert_eq!(targets(&monitors, &["3", "0-1", "1"]), vec![3, 0, 1]);
+        assert_eq!(targets(&monitors, &["*", "!1-2"]), vec![0, 3]);
+    }
+
+    #[test]
+    fn monitor_ranges_clamp_to_layout() {
+        let monitors = test_monitors(2, 0);
+        assert_eq!(targets(&monitors, &["0-18446744073709551615"]), vec![0, 1]);
+        assert!(targets(&monitors, &["5-9"]).is_empty());
+    }
+
+    #[test]
+    fn fixed_random_seed_picks_the_same_unassigned_monitor() {
+        let monitors = test_monitors(4, 0);
+        let keys = vec!["random".to_string()];
+        let assigned = HashSet::from([0, 2]);
+        let mut profile = WallpaperConfig::preview("user.random");
+        profile.random_seed = Some(42);
+        let pick = |profile: &WallpaperConfig| {
+            resolve_target_monitors(&monitors, &keys, &assigned, profile_random_seed(profile))
+                .iter()
+                .map(|monitor| monitor.index)
+                .collect::<Vec<_>>()
+        };
+
+        let first = pick(&profile);
+        assert_eq!(first.len(), 1);
+        assert!(!assigned.contains(&first[0]));
+        assert_eq!(pick(&profile), first);
+        for seed in 0..64 {
+            profile.random_seed = Some(seed);
+            assert!(pick(&profile).iter().all(|index| !assigned.contains(index)));
+        }
+    }
+
+    #[test]
+    fn mirrored_monitors_collapse_into_one() {
+        let mut monitors = test_monitors(2, 1);
+        monitors[1].rect = monitors[0].rect;
+        let collapsed = collapse_mirrored_monitors(monitors);
+        assert_eq!(collapsed.len(), 1);
+        // The duplicate was the primary; the merged area keeps the flag.
+        assert!(collapsed[0].primary);
+        assert_eq!(collapsed[0].device_name, "\\\\.\\DISPLAY1");
+    }
+
+    #[test]
+    fn overlapping_monitors_are_kept_apart() {
+        let mut monitors = test_monitors(2, 0);
+        monitors[1].rect.left = 960;
+        monitors[1].rect.right = 2880;
+        let collapsed = collapse_mirrored_monitors(monitors);
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed.iter().filter(|m| m.primary).count(), 1);
+    }
+
+    #[test]
+    fn random_pick_needs_a_candidate() {
+        assert_eq!(pick_random_index(42, 0), None);
+        assert_eq!(pick_random_index(42, 1), Some(0));
+    }
+
+    fn test_config(yaml: &str) -> AddonConfig {
+        AddonConfig::from_yaml(&serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    /// Fresh folder under the temp dir holding `files` (name, contents).
+    fn temp_dir_with(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("sentinel-wallpaper-tests")
+            .join(format!("{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn test_asset(id: &str, path: PathBuf) -> RegistryAsset {
+        RegistryAsset {
+            id: id.to_string(),
+            category: "wallpaper".to_string(),
+            metadata: serde_json::json!({}),
+            path,
+        }
+    }
+
+    fn placements<'a>(plan: &'a [PlannedHost]) -> Vec<(&'a str, usize)> {
+        plan.iter()
+            .map(|host| (host.wallpaper_id.as_str(), host.monitor.index))
+            .collect()
+    }
+
+    #[test]
+    fn plan_places_explicit_monitors_before_wildcards() {
+        let dir = temp_dir_with("plan-priority", &[("index.html", b"<html></html>")]);
+        let assets = vec![test_asset("user.a", dir.clone()), test_asset("user.b", dir)];
+        let config = test_config(
+            "wallpaper:\n  wallpaper_id: user.b\n  monitor_index: ['*']\n\
+             wallpaper2:\n  wallpaper_id: user.a\n  monitor_index: ['1']\n",
+        );
+        let plan = plan_hosts(&config, &assets, &test_monitors(3, 0));
+        assert_eq!(placements(&plan), vec![("user.a", 1), ("user.b", 0), ("user.b", 2)]);
+        assert!(plan.iter().all(|host| host.content.is_ok()));
+    }
+
+    #[test]
+    fn plan_spans_one_host_across_monitors() {
+        let dir = temp_dir_with("plan-span", &[("index.html", b"<html></html>")]);
+        let assets = vec![test_asset("user.span", dir)];
+        let config = test_config("wallpaper:\n  wallpaper_id: user.span\n  mode: span\n");
+        let plan = plan_hosts(&config, &assets, &test_monitors(2, 0));
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].span_count, 2);
+        assert_eq!((plan[0].monitor.rect.left, plan[0].monitor.rect.right), (0, 3840));
+    }
+
+    #[test]
+    fn plan_keeps_missing_assets_only_with_a_fallback() {
+        let monitors = test_monitors(2, 0);
+        let config = test_config("wallpaper:\n  wallpaper_id: user.missing\n");
+        assert!(plan_hosts(&config, &[], &monitors).is_empty());
+
+        let config = test_config(
+            "settings:\n  rendering:\n    fallback_color: '#102030'\n\
+             wallpaper:\n  wallpaper_id: user.missing\n",
+        );
+        let plan = plan_hosts(&config, &[], &monitors);
+        assert_eq!(placements(&plan), vec![("user.missing", 0), ("user.missing", 1)]);
+        assert!(plan.iter().all(|host| host.content.is_err()));
+    }
+
+    #[test]
+    fn plan_stacks_sections_on_a_claimed_monitor_by_z_index() {
+        let dir = temp_dir_with("plan-stack", &[("index.html", b"<html></html>")]);
+        let assets: Vec<_> = ["user.base", "user.top", "user.mid"]
+            .into_iter()
+            .map(|id| test_asset(id, dir.clone()))
+            .collect();
+        let config = test_config(
+            "wallpaper:\n  wallpaper_id: user.base\n  monitor_index: ['0']\n\
+             wallpaper2:\n  wallpaper_id: user.top\n  monitor_index: ['0']\n  stack: true\n  z_index: overlay\n\
+             wallpaper3:\n  wallpaper_id: user.mid\n  monitor_index: ['0']\n  stack: true\n  z_index: normal\n",
+        );
+        let plan = plan_hosts(&config, &assets, &test_monitors(2, 0));
+        assert_eq!(placements(&plan), vec![("user.base", 0), ("user.mid", 0), ("user.top", 0)]);
+        assert!(plan.iter().all(|host| host.content.is_ok()));
+    }
+
+    fn host_keys<'a>(plan: &'a [PlannedHost]) -> Vec<Option<HostKey<'a>>> {
+        plan.iter().map(PlannedHost::key).collect()
+    }
+
+    #[test]
+    fn diagnostics_only_edits_keep_the_hosts() {
+        let dir = temp_dir_with("apply-keys", &[("index.html", b"<html></html>")]);
+        let assets = vec![test_asset("user.a", dir)];
+        let monitors = test_monitors(2, 0);
+        let section = "wallpaper:\n  wallpaper_id: user.a\n  mode: fit\n";
+        let before = test_config(section);
+        let logging = "settings:\n  diagnostics:\n    log_watcher_reloads: true\n    recent_log_lines: 50\n";
+        let after = test_config(&format!("{logging}  development:\n    debug: true\n{section}"));
+        let planned = plan_hosts(&before, &assets, &monitors);
+        assert!(host_keys(&planned).iter().all(Option::is_some));
+        assert_eq!(host_keys(&planned), host_keys(&plan_hosts(&after, &assets, &monitors)));
+        assert_eq!(LaunchSettings::of(&before), LaunchSettings::of(&after));
+
+        let restyled = test_config("wallpaper:\n  wallpaper_id: user.a\n  mode: stretch\n");
+        assert_ne!(host_keys(&planned), host_keys(&plan_hosts(&restyled, &assets, &monitors)));
+        let filled =
+            test_config(&format!("settings:\n  rendering:\n    fallback_color: '#000000'\n{section}"));
+        assert_ne!(LaunchSettings::of(&before), LaunchSettings::of(&filled));
+    }
+
+    fn url_for(dir: &Path, manifest_url: &str) -> Option<String> {
+        let mut asset = test_asset("user.url", dir.to_path_buf());
+        asset.metadata = serde_json::json!({ "url": manifest_url });
+        resolve_asset_url(&asset)
+    }
+
+    #[test]
+    fn manifest_urls_with_a_scheme_pass_through() {
+        let dir = temp_dir_with("url-scheme", &[]);
+        for url in [
+            "https://example.com/wallpaper?x=1",
+            "http://localhost:8080/",
+            "file:///C:/Wallpapers/page.html",
+            "data:text/html,<p>hi</p>",
+        ] {
+            assert_eq!(url_for(&dir, url).as_deref(), Some(url));
+        }
+    }
+
+    #[test]
+    fn relative_manifest_urls_resolve_against_the_asset_folder() {
+        let dir = temp_dir_with("url-relative", &[]);
+        let page = path_to_file_url(&dir.join("pages/index.html"));
+        assert!(page.starts_with("file:///"));
+        assert_eq!(url_for(&dir, "pages/index.html"), Some(page.clone()));
+        assert_eq!(url_for(&dir, "/pages/index.html"), Some(page.clone()));
+        // The query and fragment survive the path conversion untouched.
+        assert_eq!(
+            url_for(&dir, "pages/index.html?theme=dark&fps=30#top"),
+            Some(format!("{page}?theme=dark&fps=30#top"))
+        );
+    }
+
+    #[test]
+    fn long_paths_become_plain_file_urls() {
+        let folder = "nested-folder-".repeat(20);
+        let path = long_path(Path::new(&format!(r"C:\Wallpapers\{folder}\index.html")));
+        assert!(path.to_string_lossy().len() > 260);
+        assert!(path.to_string_lossy().starts_with(r"\\?\"));
+        let url = path_to_file_url(&path);
+        assert_eq!(url, format!("file:///C:/Wallpapers/{folder}/index.html"));
+        assert!(!url.contains('?'));
+
+        let share = long_path(Path::new(&format!(r"\\server\share\{folder}\index.html")));
+        assert_eq!(
+            path_to_file_url(&share),
+            format!("file://server/share/{folder}/index.html")
+        );
+    }
+
+    #[test]
+    fn lone_gif_resolves_to_the_gif_player() {
+        let dir = temp_dir_with("gif-only", &[("manifest.json", b"{}"), ("loop.gif", b"GIF89a")]);
+        let url = resolve_asset_url(&test_asset("user.gif", dir.clone())).unwrap();
+        assert!(url.starts_with(GIF_PLAYER_URL_PREFIX));
+        assert!(url.contains(&percent_encode(&format!("https://{PLAYER_ASSET_HOST}/loop.gif"))));
+        assert_eq!(asset_host_for(&url, &dir), Some((PLAYER_ASSET_HOST, dir.as_path())));
+
+        // A second GIF leaves nothing to pick.
+        fs::write(dir.join("other.gif"), b"GIF89a").unwrap();
+        assert_eq!(resolve_asset_url(&test_asset("user.gif", dir)), None);
+    }
+
+    #[test]
+    fn lone_video_resolves_to_the_video_player() {
+        let dir = temp_dir_with("mp4-only", &[("manifest.json", b"{}"), ("clip.mp4", b"")]);
+        let url = resolve_asset_url(&test_asset("user.video", dir.clone())).unwrap();
+        assert!(url.starts_with(VIDEO_PLAYER_URL_PREFIX));
+        assert!(url.contains(&percent_encode(&format!("https://{PLAYER_ASSET_HOST}/clip.mp4"))));
+        assert!(url.contains(&percent_encode("<video ")));
+        assert!(url.contains(&percent_encode("muted=true")));
+        assert_eq!(asset_host_for(&url, &dir), Some((PLAYER_ASSET_HOST, dir.as_path())));
+
+        let mut asset = test_asset("user.video", dir.clone());
+        asset.metadata = serde_json::json!({ "muted": false });
+        let url = resolve_asset_url(&asset).unwrap();
+        assert!(url.contains(&percent_encode("muted=false")));
+
+        // An index.html beside the video takes precedence.
+        fs::write(dir.join("index.html"), b"<p>page</p>").unwrap();
+        let url = resolve_asset_url(&test_asset("user.video", dir)).unwrap();
+        assert!(url.starts_with("file:///") && url.ends_with("/index.html"));
+    }
+
+    #[test]
+    fn local_scan_fills_in_assets_the_registry_lacks() {
+        let root = temp_dir_with("local-scan", &[("stray.txt", b"")]);
+        for (folder, manifest) in [
+            ("alpha", r#"{ "id": "user.alpha", "url": "index.html" }"#),
+            ("beta", "{}"),
+            ("broken", "{ not json"),
+        ] {
+            fs::create_dir(root.join(folder)).unwrap();
+            fs::write(root.join(folder).join("manifest.json"), manifest).unwrap();
+        }
+        fs::create_dir(root.join("no-manifest")).unwrap();
+
+        let local = scan_local_wallpaper_assets(&root);
+        let ids: Vec<&str> = local.iter().map(|asset| asset.id.as_str()).collect();
+        assert_eq!(ids, ["beta", "user.alpha"]);
+        assert_eq!(local[1].path, root.join("alpha"));
+        assert_eq!(local[1].metadata["url"], "index.html");
+
+        let registry_dir = temp_dir_with("local-scan-registry", &[]);
+        let mut assets = vec![test_asset("user.alpha", registry_dir.clone())];
+        assert_eq!(merge_local_wallpaper_assets(&mut assets, local), 1);
+        let merged: Vec<(&str, &Path)> = assets
+            .iter()
+            .map(|asset| (asset.id.as_str(), asset.path.as_path()))
+            .collect();
+        let beta_dir = root.join("beta");
+        assert_eq!(merged, [("user.alpha", registry_dir.as_path()), ("beta", beta_dir.as_path())]);
+    }
+
+    #[test]
+    fn higher_jpeg_quality_writes_a_larger_file() {
+        // Noisy pixels, so the quantiser has detail to throw away.
+        let image = RgbaImage::from_fn(96, 64, |x, y| {
+            let v = (x * 31 + y * 17 + x * y * 7) as u8;
+            Rgba([v, v.wrapping_mul(3), v ^ 0x5a, 255])
+        });
+        let dir = temp_dir_with("jpeg-quality", &[]);
+        let size_at = |quality: u8| {
+            let path = dir.join(format!("q{quality}.jpg"));
+            save_snapshot_image(image.clone(), &path, quality, 0).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            assert_eq!(bytes[..2], [0xFF, 0xD8]);
+            bytes.len()
+        };
+        assert!(size_at(95) > size_at(30));
+    }
+
+    /// Three side-by-side 4K captures, stitched at full size and capped at
+    /// 4096 px.  Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn time_stitch_tiled_snapshot() {
+        const RUNS: u32 = 5;
+        let rects: Vec<RECT> = (0..3)
+            .map(|i| RECT { left: i * 3840, top: 0, right: (i + 1) * 3840, bottom: 2160 })
+            .collect();
+        let pixels: Arc<[u8]> = vec![0x40u8; 3840 * 2160 * 4].into();
+        for max_dimension in [0, 4096] {
+            let mut total = Duration::ZERO;
+            for _ in 0..RUNS {
+                let captures = rects.iter().map(|rect| (*rect, Arc::clone(&pixels))).collect();
+                let started = Instant::now();
+                let stitched =
+                    stitch_tiled_snapshot(captures, rect(0, 0, 11520, 2160), max_dimension, [0, 0, 0], &[]);
+                total += started.elapsed();
+                assert!(stitched.is_some());
+            }
+            println!("max_dimension={max_dimension}: {:?} per stitch", total / RUNS);
+        }
+    }
+
+    /// The stitch before row copies: one `put_pixel` per captured pixel.
+    fn stitch_per_pixel(
+        captures: &[(RECT, Arc<[u8]>)],
+        width: i32,
+        height: i32,
+        fill: [u8; 3],
+    ) -> Option<RgbaImage> {
+        let fill = Rgba([fill[0], fill[1], fill[2], 255]);
+        let mut stitched = RgbaImage::from_pixel(width as u32, height as u32, fill);
+        let mut has_non_black_pixel = false;
+        for (rect, pixels) in captures {
+            let tile_width = (rect.right - rect.left) as usize;
+            for (i, px) in pixels.chunks_exact(4).enumerate() {
+                let px = Rgba([px[2], px[1], px[0], 255]);
+                has_non_black_pixel |= px[0] != 0 || px[1] != 0 || px[2] != 0;
+                let dst_x = (rect.left + (i % tile_width) as i32).rem_euclid(width) as u32;
+                let dst_y = (rect.top + (i / tile_width) as i32).rem_euclid(height) as u32;
+                stitched.put_pixel(dst_x, dst_y, px);
+            }
+        }
+        has_non_black_pixel.then_some(stitched)
+    }
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT { left, top, right, bottom }
+    }
+
+    fn noise_capture(left: i32, top: i32, right: i32, bottom: i32, seed: u32) -> (RECT, Arc<[u8]>) {
+        let len = ((right - left) * (bottom - top) * 4) as u32;
+        let pixels = (0..len)
+            .map(|i| (i.wrapping_add(seed).wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        (RECT { left, top, right, bottom }, pixels)
+    }
+
+    #[test]
+    fn row_copy_stitch_matches_per_pixel_stitch() {
+        // The second monitor sits above and left of the primary and wraps
+        // across the right edge; the third overlaps the primary.
+        let captures = vec![
+            noise_capture(0, 0, 64, 48, 1),
+            noise_capture(-20, -30, 20, 10, 2),
+            noise_capture(50, 20, 90, 60, 3),
+        ];
+        let expected = stitch_per_pixel(&captures, 110, 90, [7, 8, 9]).unwrap();
+        let stitched = stitch_tiled_snapshot(captures, rect(0, 0, 110, 90), 0, [7, 8, 9], &[]).unwrap();
+        assert_eq!(stitched.as_raw(), expected.as_raw());
+    }
+
+    #[test]
+    fn all_black_captures_stitch_to_nothing() {
+        // GDI leaves alpha undefined, so an opaque black capture is still black.
+        let black = |left: i32| {
+            let rect = RECT { left, top: -10, right: left + 16, bottom: 6 };
+            (rect, Arc::from([0, 0, 0, 0xFF].repeat(16 * 16)))
+        };
+        let captures = vec![black(-16), black(0)];
+        assert!(stitch_per_pixel(&captures, 32, 16, [1, 2, 3]).is_none());
+        assert!(stitch_tiled_snapshot(captures.clone(), rect(0, 0, 32, 16), 0, [1, 2, 3], &[]).is_none());
+
+        let mut captures = captures;
+        let mut lit = captures[1].1.to_vec();
+        lit[4 * 37] = 1;
+        captures[1].1 = lit.into();
+        let expected = stitch_per_pixel(&captures, 32, 16, [1, 2, 3]).unwrap();
+        let stitched = stitch_tiled_snapshot(captures, rect(0, 0, 32, 16), 0, [1, 2, 3], &[]).unwrap();
+        assert_eq!(stitched.as_raw(), expected.as_raw());
+    }
+
+    fn solid_capture(rect: RECT, rgb: [u8; 3]) -> (RECT, Arc<[u8]>) {
+        let len = ((rect.right - rect.left) * (rect.bottom - rect.top)) as usize;
+        (rect, Arc::from([rgb[2], rgb[1], rgb[0], 0].repeat(len)))
+    }
+
+    /// The snapshot pixel Windows shows at virtual-desktop point `(x, y)`
+    /// when `image` is applied with `layout` on a virtual screen `screen`.
+    fn desktop_pixel(image: &RgbaImage, layout: WallpaperLayout, screen: RECT, x: i32, y: i32) -> [u8; 3] {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let (image_x, image_y) = match layout {
+            WallpaperLayout::Tiled => (x.rem_euclid(width), y.rem_euclid(height)),
+            WallpaperLayout::Span => (
+                (x - screen.left) * width / (screen.right - screen.left),
+                (y - screen.top) * height / (screen.bottom - screen.top),
+            ),
+        };
+        let px = image.get_pixel(image_x as u32, image_y as u32);
+        [px[0], px[1], px[2]]
+    }
+
+    #[test]
+    fn downscaled_snapshot_lines_up_with_every_monitor() {
+        // One monitor above and left of the primary, one to its right.
+        let monitors = [
+            (rect(0, 0, 192, 108), [200, 0, 0]),
+            (rect(-192, -40, 0, 68), [0, 200, 0]),
+            (rect(192, 0, 384, 108), [0, 0, 200]),
+        ];
+        let screen = rect(-192, -40, 384, 108);
+        let screen_size = (screen.right - screen.left, screen.bottom - screen.top);
+        for max_dimension in [0, 144] {
+            let captures = monitors.iter().map(|&(rect, rgb)| solid_capture(rect, rgb)).collect();
+            let stitched = stitch_tiled_snapshot(captures, screen, max_dimension, [9, 9, 9], &[]).unwrap();
+            let layout = WallpaperLayout::for_snapshot(stitched.dimensions(), screen_size);
+            assert_eq!(layout == WallpaperLayout::Span, max_dimension != 0);
+            for (rect, rgb) in monitors {
+                let (mid_x, mid_y) = ((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2);
+                for (x, y) in [(rect.left, rect.top), (mid_x, mid_y), (rect.right - 1, rect.bottom - 1)] {
+                    assert_eq!(
+                        desktop_pixel(&stitched, layout, screen, x, y),
+                        rgb,
+                        "max_dimension {max_dimension} at ({x}, {y})"
+                    );
+                }
+            }
+        }
+    }
+
+    fn test_job(id: i32) -> SnapshotJob {
+        SnapshotJob {
+            captures: Vec::new(),
+            bounds: rect(id, 0, id + 1, 1),
+            max_dimension: 0,
+            format: SnapshotFormat::Bmp,
+            jpeg_quality: 90,
+            keep_history: 0,
+            fill: [0, 0, 0],
+            hdr_rects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn snapshot_mailbox_keeps_the_newest_job() {
+        let mailbox = Arc::new(SnapshotMailbox::default());
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let worker = {
+            let mailbox = Arc::clone(&mailbox);
+            let processed = Arc::clone(&processed);
+            thread::spawn(move || {
+                run_mailbox(&mailbox, |job| {
+                    // A slow save, so later captures replace pending ones.
+                    thread::sleep(Duration::from_millis(2));
+                    processed.lock().unwrap().push(job.bounds.left);
+                })
+            })
+        };
+
+        for id in 0..200 {
+            mailbox.put(test_job(id));
+        }
+        mailbox.close();
+        worker.join().unwrap();
+
+        let processed = processed.lock().unwrap();
+        assert_eq!(processed.last(), Some(&199));
+        assert!(processed.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(processed.len() < 200);
+    }
+
+    #[test]
+    fn closed_snapshot_mailbox_ends_the_worker() {
+        let mailbox = SnapshotMailbox::default();
+        mailbox.close();
+        let mut processed = 0;
+        run_mailbox(&mailbox, |_| processed += 1);
+        assert_eq!(processed, 0);
+    }
+}